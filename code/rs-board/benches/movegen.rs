@@ -0,0 +1,232 @@
+// Random playouts from the starting position, run with `cargo bench`.
+//
+// Before the fixed-capacity generator (Vec candidates per piece, board scans
+// in `get_coord`, SipHash position keys):
+//   playouts:            ~5530 ns/ply, 25.71 allocations/ply
+// After:
+//   playouts, Vec moves:  ~480 ns/ply,  1.01 allocations/ply
+//   playouts, MoveList:   ~380 ns/ply,  0.01 allocations/ply (one per game, the history)
+//   generate_moves:        ~90 ns/ply,  0 allocations
+// Under the README rules the Core has up to 16 moves and games run longer:
+//   playouts, MoveList:   ~660 ns/ply,  0.01 allocations/ply
+//   generate_moves:       ~185 ns/ply,  0 allocations
+//
+// Before timing, every benchmark position is checked to give exactly the
+// moves of a plain Vec-based generator, the one `generate_moves` replaced
+// brought up to the same rules.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashSet;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rs_board::game::Game;
+use rs_board::movegen::{MoveList, generate_moves};
+use rs_board::types::board::Board;
+use rs_board::types::{
+    Coordinate, Direction, KINDS, Move, MoveKind, PLRS, Piece, PieceKind, Player,
+};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// `Game::get_possible_moves` before the fixed-capacity generator, with the
+// Core's two-square step, Brutes attacking only forward and no dashes
+// through a Core
+fn reference_moves(board: &Board, plr: Player) -> Vec<Move> {
+    let piece = |kind| Piece {
+        plr,
+        kind,
+        alive: true,
+    };
+    let candidates = |from: Coordinate, dirs: &[Direction]| -> (Vec<_>, Vec<_>) {
+        dirs.iter()
+            .filter_map(|&dir| from.step(dir, plr).map(|coord| (dir, coord)))
+            .partition(|&(_, coord)| board.get_piece(coord).is_none())
+    };
+    let mut moves = Vec::new();
+    for (_, kind) in KINDS {
+        let Some(from) = board.get_coord(&piece(kind)) else {
+            continue;
+        };
+        match kind {
+            PieceKind::Core => {
+                let monarch = board.get_coord(&piece(PieceKind::Monarch));
+                if !monarch.is_some_and(|m| m.is_adjacent(from)) {
+                    continue;
+                }
+                for dir in Direction::ALL {
+                    let Some(near) = from.step(dir, plr) else {
+                        continue;
+                    };
+                    if board.get_piece(near).is_none() {
+                        moves.push(Move(piece(kind), MoveKind::Move { to: near }));
+                    }
+                    if let Some(to) = near.step(dir, plr)
+                        && board.get_piece(to).is_none()
+                    {
+                        moves.push(Move(piece(kind), MoveKind::Move { to }));
+                    }
+                }
+            }
+            PieceKind::Monarch => {
+                for (_, to) in candidates(from, &Direction::ALL).0 {
+                    moves.push(Move(piece(kind), MoveKind::Move { to }));
+                }
+            }
+            PieceKind::Brute(_) => {
+                let (empty, taken) = candidates(from, &Direction::ORTHOGONAL);
+                for (_, to) in empty {
+                    moves.push(Move(piece(kind), MoveKind::Move { to }));
+                }
+                let forward = from.step(Direction::Forward, plr);
+                for (_, target) in taken {
+                    if Some(target) != forward || board.get_piece(target).unwrap().plr == plr {
+                        continue;
+                    }
+                    moves.push(Move(piece(kind), MoveKind::Attack { target }));
+                }
+            }
+            PieceKind::Tank => {
+                let (empty, taken) = candidates(from, &Direction::ORTHOGONAL);
+                for (_, to) in empty {
+                    moves.push(Move(piece(kind), MoveKind::Move { to }));
+                }
+                for (dir, target) in taken {
+                    if board.get_piece(target).unwrap().kind == PieceKind::Core {
+                        continue;
+                    }
+                    if let Some(to) = target.step(dir, plr)
+                        && board.get_piece(to).is_none()
+                    {
+                        moves.push(Move(piece(kind), MoveKind::MoveAndAttack { to, target }));
+                    }
+                }
+            }
+        }
+    }
+    moves
+}
+
+const PLAYOUTS: usize = 20_000;
+
+fn report(name: &str, plies: usize, elapsed: Duration, allocs: usize) {
+    println!(
+        "{name}: {plies} plies, {:.1} ns/ply, {:.3} allocations/ply",
+        elapsed.as_nanos() as f64 / plies as f64,
+        allocs as f64 / plies as f64,
+    );
+}
+
+fn measure(name: &str, mut playout: impl FnMut(&mut XorShift) -> usize) {
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let allocs_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let plies: usize = (0..PLAYOUTS).map(|_| playout(&mut rng)).sum();
+    let elapsed = start.elapsed();
+    report(
+        name,
+        plies,
+        elapsed,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocs_before,
+    );
+}
+
+fn main() {
+    // `legal_moves` hands out a fresh Vec every ply
+    measure("playouts, Vec moves", |rng| {
+        let mut game = Game::new();
+        let mut plies = 0;
+        while game.result().is_none() {
+            let moves = game.legal_moves();
+            game.play(black_box(moves[rng.below(moves.len())])).unwrap();
+            plies += 1;
+        }
+        plies
+    });
+
+    // the MCTS path, one MoveList on the stack for the whole playout
+    measure("playouts, MoveList", |rng| {
+        let mut game = Game::new();
+        let mut moves = MoveList::new();
+        let mut plies = 0;
+        while game.result().is_none() {
+            game.legal_moves_into(&mut moves);
+            game.play(black_box(moves[rng.below(moves.len())])).unwrap();
+            plies += 1;
+        }
+        plies
+    });
+
+    // generation alone has to stay off the heap entirely
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let mut positions = Vec::new();
+    let mut game = Game::new();
+    let mut moves = MoveList::new();
+    while positions.len() < 10_000 {
+        if game.result().is_some() {
+            game = Game::new();
+        }
+        positions.push(*game.board());
+        game.legal_moves_into(&mut moves);
+        game.play(moves[rng.below(moves.len())]).unwrap();
+    }
+    for (i, board) in positions.iter().enumerate() {
+        for plr in [PLRS[i % 2], PLRS[(i + 1) % 2]] {
+            generate_moves(board, plr, &mut moves);
+            let expected = reference_moves(board, plr);
+            assert_eq!(moves.len(), expected.len(), "{board:?}");
+            assert_eq!(
+                moves.iter().collect::<HashSet<_>>(),
+                expected.iter().collect::<HashSet<_>>(),
+                "{board:?}"
+            );
+        }
+    }
+    println!(
+        "generate_moves: same moves as the Vec generator on {} positions",
+        positions.len()
+    );
+    let allocs_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..100 {
+        for (i, board) in positions.iter().enumerate() {
+            generate_moves(black_box(board), PLRS[i % 2], &mut moves);
+            black_box(&moves);
+        }
+    }
+    let allocs = ALLOCATIONS.load(Ordering::Relaxed) - allocs_before;
+    report(
+        "generate_moves",
+        positions.len() * 100,
+        start.elapsed(),
+        allocs,
+    );
+    assert_eq!(allocs, 0, "move generation allocated");
+}
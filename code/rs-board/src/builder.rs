@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::game::{DrawRules, Game};
+use crate::types::{Coordinate, Piece, PieceKind, Player, board::Board};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    // the same piece was placed twice
+    DuplicatePiece(Piece),
+    // two pieces were placed on the same square
+    SquareTaken(Coordinate),
+    OutOfBounds(Coordinate),
+    CapturedAndPlaced(Piece),
+    // a round can't start with the Cores already adjacent
+    CoresTouching(Coordinate, Coordinate),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicatePiece(piece) => {
+                write!(f, "{:?} {:?} is placed twice", piece.plr, piece.kind)
+            }
+            Self::SquareTaken(coord) => write!(f, "more than one piece is placed on {coord}"),
+            Self::OutOfBounds(coord) => write!(f, "square {:?} is off the board", coord.xy()),
+            Self::CapturedAndPlaced(piece) => write!(
+                f,
+                "{:?} {:?} is both captured and on the board",
+                piece.plr, piece.kind
+            ),
+            Self::CoresTouching(a, b) => write!(f, "the Cores on {a} and {b} already touch"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+// Sets up arbitrary positions, starting from an empty board. Pieces that are
+// never placed count as captured; `capture` only makes that explicit.
+#[derive(Clone, Debug)]
+pub struct BoardBuilder {
+    placed: Vec<(Piece, Coordinate)>,
+    captured: Vec<Piece>,
+    turn: Player,
+    last_core_mover: Option<Player>,
+    rules: DrawRules,
+}
+
+fn piece(plr: Player, kind: PieceKind) -> Piece {
+    Piece {
+        plr,
+        kind,
+        alive: true,
+    }
+}
+
+impl BoardBuilder {
+    pub fn new() -> Self {
+        Self {
+            placed: Vec::new(),
+            captured: Vec::new(),
+            turn: Player::A,
+            last_core_mover: None,
+            rules: DrawRules::default(),
+        }
+    }
+
+    pub fn place(mut self, plr: Player, kind: PieceKind, at: Coordinate) -> Self {
+        self.placed.push((piece(plr, kind), at));
+        self
+    }
+
+    pub fn capture(mut self, plr: Player, kind: PieceKind) -> Self {
+        self.captured.push(piece(plr, kind));
+        self
+    }
+
+    pub fn turn(mut self, plr: Player) -> Self {
+        self.turn = plr;
+        self
+    }
+
+    pub fn last_core_mover(mut self, plr: Option<Player>) -> Self {
+        self.last_core_mover = plr;
+        self
+    }
+
+    pub fn draw_rules(mut self, rules: DrawRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn build_board(&self) -> Result<Board, BuildError> {
+        let mut board = Board::empty();
+        for (i, (piece, at)) in self.placed.iter().enumerate() {
+            if !at.is_on_board() {
+                return Err(BuildError::OutOfBounds(*at));
+            }
+            if self.placed[..i].iter().any(|(other, _)| other == piece) {
+                return Err(BuildError::DuplicatePiece(*piece));
+            }
+            if board.get_piece(*at).is_some() {
+                return Err(BuildError::SquareTaken(*at));
+            }
+            if self.captured.contains(piece) {
+                return Err(BuildError::CapturedAndPlaced(*piece));
+            }
+            board.place(piece, *at);
+        }
+
+        let core = |plr| board.get_coord(&piece(plr, PieceKind::Core));
+        if let (Some(a), Some(b)) = (core(Player::A), core(Player::B))
+            && a.is_adjacent(b)
+        {
+            return Err(BuildError::CoresTouching(a, b));
+        }
+        debug_assert_eq!(board.validate(), Ok(()));
+        Ok(board)
+    }
+
+    pub fn build(self) -> Result<Game, BuildError> {
+        let board = self.build_board()?;
+        Ok(Game::from_position(
+            board,
+            self.turn,
+            self.last_core_mover,
+            self.rules,
+        ))
+    }
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::RoundResult;
+    use crate::types::{BruteSide, Move, MoveKind};
+
+    fn sq(name: &str) -> Coordinate {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn builds_custom_position() {
+        let game = BoardBuilder::new()
+            .place(Player::A, PieceKind::Core, sq("e3"))
+            .place(Player::A, PieceKind::Monarch, sq("f3"))
+            .place(Player::B, PieceKind::Core, sq("e5"))
+            .place(Player::B, PieceKind::Tank, sq("k7"))
+            .capture(Player::B, PieceKind::Monarch)
+            .turn(Player::B)
+            .build()
+            .unwrap();
+        let board = game.board();
+        assert_eq!(game.turn(), Player::B);
+        assert_eq!(board.validate(), Ok(()));
+        assert_eq!(
+            board.get_coord(&piece(Player::B, PieceKind::Tank)),
+            Some(sq("k7"))
+        );
+        assert!(!board.is_alive(&piece(Player::B, PieceKind::Monarch)));
+        assert!(!board.is_alive(&piece(Player::A, PieceKind::Brute(BruteSide::Left))));
+        assert_eq!(game.result(), None);
+    }
+
+    #[test]
+    fn core_touch_from_custom_position() {
+        let mut game = BoardBuilder::new()
+            .place(Player::A, PieceKind::Core, sq("e3"))
+            .place(Player::A, PieceKind::Monarch, sq("f3"))
+            .place(Player::B, PieceKind::Core, sq("e5"))
+            .build()
+            .unwrap();
+        let core = piece(Player::A, PieceKind::Core);
+        let result = game
+            .play(Move(core, MoveKind::Move { to: sq("e4") }))
+            .unwrap();
+        assert_eq!(result, Some(RoundResult::Touch(Player::A)));
+    }
+
+    #[test]
+    fn rejects_invalid_positions() {
+        let err = |builder: BoardBuilder| builder.build().unwrap_err();
+        assert_eq!(
+            err(BoardBuilder::new()
+                .place(Player::A, PieceKind::Tank, sq("a1"))
+                .place(Player::A, PieceKind::Tank, sq("a2"))),
+            BuildError::DuplicatePiece(piece(Player::A, PieceKind::Tank))
+        );
+        assert_eq!(
+            err(BoardBuilder::new()
+                .place(Player::A, PieceKind::Tank, sq("a1"))
+                .place(Player::B, PieceKind::Tank, sq("a1"))),
+            BuildError::SquareTaken(sq("a1"))
+        );
+        assert_eq!(
+            err(BoardBuilder::new()
+                .place(Player::A, PieceKind::Tank, sq("a1"))
+                .capture(Player::A, PieceKind::Tank)),
+            BuildError::CapturedAndPlaced(piece(Player::A, PieceKind::Tank))
+        );
+        assert_eq!(
+            err(BoardBuilder::new()
+                .place(Player::A, PieceKind::Core, sq("d4"))
+                .place(Player::B, PieceKind::Core, sq("e5"))),
+            BuildError::CoresTouching(sq("d4"), sq("e5"))
+        );
+        assert_eq!(
+            err(BoardBuilder::new().place(Player::A, PieceKind::Tank, Coordinate::new(0, 7))),
+            BuildError::OutOfBounds(Coordinate::new(0, 7))
+        );
+    }
+}
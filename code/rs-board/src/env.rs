@@ -0,0 +1,343 @@
+use std::fmt;
+
+use crate::game::{GameError, RoundResult};
+use crate::r#match::{Match, MatchResult, MatchRules, RoundRecord};
+use crate::types::{
+    Coordinate, Direction, HEIGHT, KINDS, Move, MoveKind, PLRS, Piece, Player, WIDTH,
+};
+
+// Reinforcement-learning style wrapper around a `Match`: flat action indices
+// with a legal-action mask and observations as a flat tensor.
+//
+// Action index = (kind * 8 + direction) * 4 + move kind, where kind is the
+// piece's position in `KINDS`, direction its position in `Direction::ALL`
+// (compass directions, the same for both players) and move kind 0 = move,
+// 1 = attack, 2 = move and attack, 3 = move two squares (the Core's long
+// step). The piece always belongs to the side to move, and most of the 160
+// combinations are never legal for some kind.
+pub const ACTIONS: usize = KINDS.len() * 8 * MOVE_KINDS;
+const MOVE_KINDS: usize = 4;
+
+// A's Brute L, Core, Monarch, Tank, Brute R, then B's, a plane that is all
+// ones when A is to move, then A's and B's match score as a share of the
+// points needed, each filling a whole plane.
+pub const PLANES: usize = 2 * KINDS.len() + 3;
+// laid out as [plane][rank][file]
+pub const OBSERVATION_LEN: usize = PLANES * HEIGHT * WIDTH;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnvConfig {
+    pub rules: MatchRules,
+    // uniformly random moves played at reset, picked by the seed, so episodes
+    // don't all start from the same position
+    pub opening_plies: u8,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            rules: MatchRules::default(),
+            opening_plies: 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvError {
+    IllegalAction(usize),
+    // the match ended, call `reset`
+    Done,
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalAction(action) => write!(f, "action {action} is not legal here"),
+            Self::Done => write!(f, "the match is over"),
+        }
+    }
+}
+
+impl std::error::Error for EnvError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepInfo {
+    // set when the step ended a round
+    pub round: Option<RoundRecord>,
+    pub result: Option<MatchResult>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f32>,
+    // for the player who acted: 1 for winning the round by a touch, -1 for
+    // losing it, 0 otherwise
+    pub reward: f32,
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+// splitmix64, enough to pick opening moves without a dependency
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn direction_between(from: Coordinate, to: Coordinate) -> Option<usize> {
+    let dx = to.x() as i8 - from.x() as i8;
+    let dy = to.y() as i8 - from.y() as i8;
+    Direction::ALL
+        .iter()
+        .position(|dir| dir.delta(Player::A) == (dx.signum(), dy.signum()))
+}
+
+#[derive(Clone, Debug)]
+pub struct Env {
+    config: EnvConfig,
+    game: Match,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Self {
+        Self {
+            config,
+            game: Match::new(config.rules),
+        }
+    }
+
+    // starts a new match and returns the first observation
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.game = Match::new(self.config.rules);
+        let mut random = seed;
+        for _ in 0..self.config.opening_plies {
+            let moves = self.game.game().legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let mv = moves[(next_random(&mut random) % moves.len() as u64) as usize];
+            if self.game.play(mv).expect("the move is legal").is_some() {
+                // a random opening that ends a round isn't much of an opening
+                self.game = Match::new(self.config.rules);
+                break;
+            }
+        }
+        self.observation()
+    }
+
+    pub fn current(&self) -> &Match {
+        &self.game
+    }
+
+    pub fn action_to_move(&self, action: usize) -> Option<Move> {
+        if action >= ACTIONS {
+            return None;
+        }
+        let (slot, dir, kind) = (
+            action / (8 * MOVE_KINDS),
+            action / MOVE_KINDS % 8,
+            action % MOVE_KINDS,
+        );
+        let game = self.game.game();
+        let piece = Piece {
+            plr: game.turn(),
+            kind: KINDS[slot].1,
+            alive: true,
+        };
+        let from = game.board().get_coord(&piece)?;
+        let dir = Direction::ALL[dir];
+        let next = from.step(dir, piece.plr)?;
+        let kind = match kind {
+            0 => MoveKind::Move { to: next },
+            1 => MoveKind::Attack { target: next },
+            2 => MoveKind::MoveAndAttack {
+                to: next.step(dir, piece.plr)?,
+                target: next,
+            },
+            _ => MoveKind::Move {
+                to: next.step(dir, piece.plr)?,
+            },
+        };
+        Some(Move(piece, kind))
+    }
+
+    pub fn move_to_action(&self, Move(piece, kind): &Move) -> Option<usize> {
+        let slot = KINDS.iter().position(|(_, k)| *k == piece.kind)?;
+        let from = self.game.game().board().get_coord(piece)?;
+        let (square, kind) = match kind {
+            MoveKind::Move { to } if from.chebyshev(*to) == 2 => (*to, 3),
+            MoveKind::Move { to } => (*to, 0),
+            MoveKind::Attack { target } => (*target, 1),
+            MoveKind::MoveAndAttack { target, .. } => (*target, 2),
+        };
+        let dir = direction_between(from, square)?;
+        // one or two squares along a line
+        let reach = if kind == 3 { 2 } else { 1 };
+        if from.step(Direction::ALL[dir], piece.plr)?.chebyshev(square) != reach - 1 {
+            return None;
+        }
+        Some((slot * 8 + dir) * MOVE_KINDS + kind)
+    }
+
+    pub fn legal_mask(&self) -> [bool; ACTIONS] {
+        let mut mask = [false; ACTIONS];
+        if self.game.result().is_none() {
+            for mv in self.game.game().legal_moves() {
+                let action = self
+                    .move_to_action(&mv)
+                    .expect("legal moves are one or two steps from their piece");
+                mask[action] = true;
+            }
+        }
+        mask
+    }
+
+    pub fn observation(&self) -> Vec<f32> {
+        let mut obs = vec![0.0; OBSERVATION_LEN];
+        let plane_len = HEIGHT * WIDTH;
+        let game = self.game.game();
+        for coord in Coordinate::all() {
+            if let Some(piece) = game.board().get_piece(coord) {
+                let slot = KINDS
+                    .iter()
+                    .position(|(_, k)| *k == piece.kind)
+                    .expect("every kind is in KINDS");
+                let plane = piece.plr.index() * KINDS.len() + slot;
+                obs[plane * plane_len + usize::from(coord)] = 1.0;
+            }
+        }
+        let mut fill =
+            |plane: usize, value: f32| obs[plane * plane_len..(plane + 1) * plane_len].fill(value);
+        fill(2 * KINDS.len(), (game.turn() == Player::A) as u8 as f32);
+        let target = self.config.rules.points_to_win as f32;
+        for plr in PLRS {
+            fill(
+                2 * KINDS.len() + 1 + plr.index(),
+                self.game.score(plr) / target,
+            );
+        }
+        obs
+    }
+
+    pub fn step(&mut self, action: usize) -> Result<Step, EnvError> {
+        if self.game.result().is_some() {
+            return Err(EnvError::Done);
+        }
+        let mv = self
+            .action_to_move(action)
+            .ok_or(EnvError::IllegalAction(action))?;
+        let actor = self.game.game().turn();
+        let round = match self.game.play(mv) {
+            Ok(round) => round,
+            Err(GameError::IllegalMove(_)) => return Err(EnvError::IllegalAction(action)),
+            Err(err) => unreachable!("the match is running: {err}"),
+        };
+        let reward = match round.map(|record| record.result) {
+            Some(RoundResult::Touch(winner)) if winner == actor => 1.0,
+            Some(RoundResult::Touch(_)) => -1.0,
+            _ => 0.0,
+        };
+        let result = self.game.result();
+        Ok(Step {
+            observation: self.observation(),
+            reward,
+            done: result.is_some(),
+            info: StepInfo { round, result },
+        })
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new(EnvConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::types::PieceKind;
+
+    #[test]
+    fn legal_moves_map_to_distinct_actions() {
+        let mut env = Env::default();
+        let mut random = 7;
+        env.reset(1);
+        for _ in 0..300 {
+            if env.current().result().is_some() {
+                env.reset(random);
+            }
+            let moves = env.current().game().legal_moves();
+            let mask = env.legal_mask();
+            assert_eq!(mask.iter().filter(|&&legal| legal).count(), moves.len());
+            for mv in &moves {
+                let action = env.move_to_action(mv).unwrap();
+                assert!(mask[action]);
+                assert_eq!(env.action_to_move(action), Some(*mv));
+            }
+            let mv = moves[(next_random(&mut random) % moves.len() as u64) as usize];
+            let step = env.step(env.move_to_action(&mv).unwrap()).unwrap();
+            assert_eq!(step.observation.len(), OBSERVATION_LEN);
+        }
+    }
+
+    #[test]
+    fn starting_observation() {
+        let env = Env::new(EnvConfig {
+            opening_plies: 0,
+            ..EnvConfig::default()
+        });
+        let obs = env.observation();
+        let plane = |i: usize| &obs[i * HEIGHT * WIDTH..(i + 1) * HEIGHT * WIDTH];
+        // A's Core on e1, B's on e7
+        assert_eq!(plane(1)[usize::from(Coordinate::new(4, 0))], 1.0);
+        assert_eq!(plane(6)[usize::from(Coordinate::new(4, 6))], 1.0);
+        assert_eq!(obs.iter().take(10 * HEIGHT * WIDTH).sum::<f32>(), 10.0);
+        assert!(plane(10).iter().all(|&x| x == 1.0));
+        assert!(plane(11).iter().chain(plane(12)).all(|&x| x == 0.0));
+    }
+
+    #[test]
+    fn reset_is_reproducible() {
+        let mut env = Env::default();
+        let first = env.reset(42);
+        assert_eq!(env.current().rounds(), []);
+        assert_eq!(env.reset(42), first);
+        assert_ne!(first, Env::default().observation());
+    }
+
+    #[test]
+    fn touch_is_rewarded_and_scored() {
+        let mut env = Env::new(EnvConfig {
+            opening_plies: 0,
+            ..EnvConfig::default()
+        });
+        // replace the round with one where A touches at once
+        env.game
+            .set_game(Game::from_notation("10t/11/4c6/11/4CM5/11/11 a").unwrap());
+        let core = Piece {
+            plr: Player::A,
+            kind: PieceKind::Core,
+            alive: true,
+        };
+        let touch = Move(
+            core,
+            MoveKind::Move {
+                to: "e4".parse().unwrap(),
+            },
+        );
+        let step = env.step(env.move_to_action(&touch).unwrap()).unwrap();
+        assert_eq!(step.reward, 1.0);
+        assert!(!step.done);
+        assert_eq!(
+            step.info.round.map(|r| r.result),
+            Some(RoundResult::Touch(Player::A))
+        );
+        // A's score plane
+        assert!((step.observation[11 * HEIGHT * WIDTH] - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(env.step(ACTIONS), Err(EnvError::IllegalAction(ACTIONS)));
+    }
+}
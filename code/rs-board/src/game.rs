@@ -1,147 +1,402 @@
+use std::fmt;
+
+use crate::movegen::{MoveList, generate_moves};
 use crate::types::{
-    BruteSide, Coordinate, KINDS, Move, MoveKind, Piece, PieceKind, Player, board::Board,
+    Coordinate, Move, MoveKind, PLRS, Piece, PieceKind, Player,
+    board::{Board, BoardError},
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawRules {
+    // how often the same position (with the same side to move) may occur
+    pub repetitions: u8,
+    // plies without a capture or a Core move
+    pub no_progress_limit: u16,
+}
+
+impl Default for DrawRules {
+    fn default() -> Self {
+        Self {
+            repetitions: 3,
+            no_progress_limit: 100,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    Repetition,
+    NoProgress,
+    // the Cores can never touch again, e.g. both Monarchs were captured
+    FrozenCores,
+    NoMoves,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundResult {
+    Touch(Player),
+    Draw(DrawReason),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameError {
+    IllegalMove(Move),
+    RoundOver,
+    // the match has been decided, no more rounds are played
+    MatchOver,
+    Board(BoardError),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalMove(mv) => write!(f, "illegal move {mv:?}"),
+            Self::RoundOver => write!(f, "the round is already over"),
+            Self::MatchOver => write!(f, "the match is already over"),
+            Self::Board(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl From<BoardError> for GameError {
+    fn from(err: BoardError) -> Self {
+        Self::Board(err)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Game {
-    // turn: Player,
+    turn: Player,
     board: Board,
+    last_core_mover: Option<Player>,
+    rules: DrawRules,
+    // keys of every position since the last capture, current one included
+    history: Vec<u128>,
+    no_progress: u16,
+    result: Option<RoundResult>,
 }
 
 pub struct TurnState {
-    player: Player,
-    possible_moves: Vec<Move>,
+    pub player: Player,
+    pub possible_moves: Vec<Move>,
 }
 
-fn add(lhs: u8, rhs: i8) -> Option<u8> {
-    if rhs >= 0 {
-        lhs.checked_add(rhs as u8)
-    } else {
-        lhs.checked_sub(rhs.abs() as u8)
+impl Game {
+    pub fn get_possible_moves(&self, plr: Player) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        generate_moves(&self.board, plr, &mut moves);
+        moves.to_vec()
     }
 }
 
-fn add_coord(coord: Coordinate, (dx, dy): (i8, i8)) -> Option<Coordinate> {
-    match (add(coord.x(), dx), add(coord.y(), dy)) {
-        (Some(x), Some(y)) => Some(Coordinate::new(x, y)),
-        _ => None,
+impl Game {
+    pub fn new() -> Self {
+        Self::with_rules(Player::A, DrawRules::default())
     }
-}
 
-impl Game {
-    fn check_coord(&self, coord: Coordinate) -> (bool, bool) {
-        let is_valid = Board::is_valid_coord(coord);
-        let is_empty = self.board.get_piece(coord).is_none();
-        (is_valid, is_empty)
-    }
-
-    fn check_candidates(
-        &self,
-        piece_coord: Coordinate,
-        candidates: Vec<(i8, i8)>,
-    ) -> (Vec<((i8, i8), Coordinate)>, Vec<((i8, i8), Coordinate)>) {
-        candidates
-            .iter()
-            .filter_map(|dxy| {
-                add_coord(piece_coord, *dxy)
-                    .map(|coord| (*dxy, coord, self.check_coord(coord)))
-                    .and_then(|(dxy, coord, (is_valid, is_empty))| {
-                        is_valid.then_some((dxy, coord, is_empty))
-                    })
-            })
-            .fold(
-                (Vec::new(), Vec::new()),
-                |(mut empty, mut non_empty), (dxy, coord, is_empty)| {
-                    if is_empty {
-                        empty.push((dxy, coord));
-                    } else {
-                        non_empty.push((dxy, coord));
-                    }
-                    (empty, non_empty)
-                },
-            )
-    }
-
-    fn get_possible_moves(&self, plr: Player) -> Vec<Move> {
-        let mut possible_moves = Vec::new();
-        for (_, kind) in KINDS {
-            let piece = Piece {
-                plr,
-                kind,
-                alive: true,
-            };
-            let piece_coord = self.board.get_coord(&piece);
-            if let Some(piece_coord) = piece_coord {
-                match kind {
-                    PieceKind::Core => {
-                        let candidates = vec![(0i8, -1i8), (0, 1), (1, 0), (-1, 0)];
-                        let (coords, _) = self.check_candidates(piece_coord, candidates);
-                        for (_, coord) in coords {
-                            possible_moves.push(Move(piece, MoveKind::Move { to: coord }));
-                        }
-                    }
-                    PieceKind::Monarch => {
-                        let candidates = vec![
-                            (0i8, -1i8),
-                            (0, 1),
-                            (1, 0),
-                            (-1, 0),
-                            (-1, -1),
-                            (-1, 1),
-                            (1, -1),
-                            (1, 1),
-                        ];
-                        let (coords, _) = self.check_candidates(piece_coord, candidates);
-                        for (_, coord) in coords {
-                            possible_moves.push(Move(piece, MoveKind::Move { to: coord }));
-                        }
-                    }
-                    PieceKind::Brute(_) => {
-                        let candidates = vec![(0i8, -1i8), (0, 1), (1, 0), (-1, 0)];
-                        let (move_coords, attack_coords) =
-                            self.check_candidates(piece_coord, candidates);
-                        for (_, coord) in move_coords {
-                            possible_moves.push(Move(piece, MoveKind::Move { to: coord }));
-                        }
-                        for (_, coord) in attack_coords {
-                            possible_moves.push(Move(piece, MoveKind::Attack { target: coord }));
-                        }
-                    }
-                    PieceKind::Tank => {
-                        let candidates = vec![(0i8, -1i8), (0, 1), (1, 0), (-1, 0)];
-                        let (move_coords, attack_coords) =
-                            self.check_candidates(piece_coord, candidates);
-                        for (_, coord) in move_coords {
-                            possible_moves.push(Move(piece, MoveKind::Move { to: coord }));
-                        }
-
-                        for (dxy, coord) in attack_coords {
-                            let move_to =
-                                add_coord(piece_coord, dxy).and_then(|coord| add_coord(coord, dxy));
-                            if let Some(move_to) = move_to {
-                                let (is_valid, is_empty) = self.check_coord(move_to);
-                                if is_valid && is_empty {
-                                    possible_moves.push(Move(
-                                        piece,
-                                        MoveKind::MoveAndAttack {
-                                            to: move_to,
-                                            target: coord,
-                                        },
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                }
+    pub fn with_rules(first: Player, rules: DrawRules) -> Self {
+        Self::from_position(Board::new(), first, None, rules)
+    }
+
+    pub(crate) fn from_position(
+        board: Board,
+        turn: Player,
+        last_core_mover: Option<Player>,
+        rules: DrawRules,
+    ) -> Self {
+        let mut game = Self {
+            turn,
+            board,
+            last_core_mover,
+            rules,
+            // enough for typical rounds without regrowing mid-playout
+            history: Vec::with_capacity(128),
+            no_progress: 0,
+            result: None,
+        };
+        game.history.push(game.position_key());
+        game.result = game.evaluate();
+        game
+    }
+
+    pub fn turn(&self) -> Player {
+        self.turn
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn last_core_mover(&self) -> Option<Player> {
+        self.last_core_mover
+    }
+
+    pub fn result(&self) -> Option<RoundResult> {
+        self.result
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.legal_moves_into(&mut moves);
+        moves.to_vec()
+    }
+
+    // same as `legal_moves` without allocating, meant for playouts
+    pub fn legal_moves_into(&self, out: &mut MoveList) {
+        if self.result.is_some() {
+            out.clear();
+        } else {
+            generate_moves(&self.board, self.turn, out);
+        }
+    }
+
+    pub fn play(&mut self, mv: Move) -> Result<Option<RoundResult>, GameError> {
+        if self.result.is_some() {
+            return Err(GameError::RoundOver);
+        }
+        let mut legal = MoveList::new();
+        self.legal_moves_into(&mut legal);
+        if !legal.contains(&mv) {
+            return Err(GameError::IllegalMove(mv));
+        }
+
+        let Move(piece, kind) = mv;
+        let captured = match kind {
+            MoveKind::Move { to } => {
+                self.board.r#move(&piece, to)?;
+                false
             }
+            MoveKind::Attack { target } => {
+                self.kill_at(target)?;
+                true
+            }
+            MoveKind::MoveAndAttack { to, target } => {
+                self.kill_at(target)?;
+                self.board.r#move(&piece, to)?;
+                true
+            }
+        };
+        let core_moved = matches!(piece.kind, PieceKind::Core);
+        if core_moved {
+            self.last_core_mover = Some(piece.plr);
+        }
+
+        if captured {
+            // positions from before a capture can never come back
+            self.history.clear();
+        }
+        if captured || core_moved {
+            self.no_progress = 0;
+        } else {
+            self.no_progress = self.no_progress.saturating_add(1);
+        }
+
+        self.turn = self.turn.opponent();
+        self.history.push(self.position_key());
+        self.result = self.evaluate();
+        Ok(self.result)
+    }
+
+    fn kill_at(&mut self, target: Coordinate) -> Result<(), BoardError> {
+        let piece = *self
+            .board
+            .get_piece(target)
+            .ok_or(BoardError::EmptySquare(target))?;
+        self.board.kill(&piece)
+    }
+
+    fn piece_coord(&self, plr: Player, kind: PieceKind) -> Option<Coordinate> {
+        self.board.get_coord(&Piece {
+            plr,
+            kind,
+            alive: true,
+        })
+    }
+
+    fn cores_touching(&self) -> bool {
+        match (
+            self.piece_coord(Player::A, PieceKind::Core),
+            self.piece_coord(Player::B, PieceKind::Core),
+        ) {
+            (Some(a), Some(b)) => a.is_adjacent(b),
+            _ => false,
+        }
+    }
+
+    // true once neither Core can ever move again (or one of them is gone)
+    pub fn cores_frozen(&self) -> bool {
+        let alive = |kind| {
+            PLRS.map(|plr| {
+                self.board.is_alive(&Piece {
+                    plr,
+                    kind,
+                    alive: true,
+                })
+            })
+        };
+        let cores = alive(PieceKind::Core);
+        let monarchs = alive(PieceKind::Monarch);
+        !cores.iter().all(|&c| c) || !monarchs.iter().any(|&m| m)
+    }
+
+    pub fn repetitions(&self) -> usize {
+        let current = self.position_key();
+        self.history.iter().filter(|&&key| key == current).count()
+    }
+
+    pub fn no_progress(&self) -> u16 {
+        self.no_progress
+    }
+
+    fn position_key(&self) -> u128 {
+        (self.board.key() << 1) | self.turn.index() as u128
+    }
+
+    fn has_moves(&self) -> bool {
+        let mut moves = MoveList::new();
+        generate_moves(&self.board, self.turn, &mut moves);
+        !moves.is_empty()
+    }
+
+    fn evaluate(&self) -> Option<RoundResult> {
+        if self.cores_touching() {
+            return self.last_core_mover.map(RoundResult::Touch);
         }
-        possible_moves
+        let reason = if self.repetitions() >= self.rules.repetitions as usize {
+            DrawReason::Repetition
+        } else if self.no_progress >= self.rules.no_progress_limit {
+            DrawReason::NoProgress
+        } else if self.cores_frozen() {
+            DrawReason::FrozenCores
+        } else if !self.has_moves() {
+            DrawReason::NoMoves
+        } else {
+            return None;
+        };
+        Some(RoundResult::Draw(reason))
     }
 }
 
-impl Game {
-    pub fn new() -> Self {
-        Self {
-            board: Board::new(),
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monarch(plr: Player) -> Piece {
+        Piece {
+            plr,
+            kind: PieceKind::Monarch,
+            alive: true,
+        }
+    }
+
+    fn step(game: &mut Game, plr: Player, to: (u8, u8)) -> Option<RoundResult> {
+        let mv = Move(
+            monarch(plr),
+            MoveKind::Move {
+                to: Coordinate::new(to.0, to.1),
+            },
+        );
+        game.play(mv).unwrap()
+    }
+
+    #[test]
+    fn threefold_repetition_draws() {
+        let mut game = Game::new();
+        for _ in 0..2 {
+            assert_eq!(step(&mut game, Player::A, (5, 1)), None);
+            assert_eq!(step(&mut game, Player::B, (5, 5)), None);
+            assert_eq!(step(&mut game, Player::A, (5, 0)), None);
+            step(&mut game, Player::B, (5, 6));
         }
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(
+            game.result(),
+            Some(RoundResult::Draw(DrawReason::Repetition))
+        );
+        assert!(game.legal_moves().is_empty());
+        assert_eq!(
+            game.play(Move(
+                monarch(Player::A),
+                MoveKind::Move {
+                    to: Coordinate::new(5, 1)
+                }
+            )),
+            Err(GameError::RoundOver)
+        );
+    }
+
+    #[test]
+    fn no_progress_limit_draws() {
+        let rules = DrawRules {
+            repetitions: 3,
+            no_progress_limit: 3,
+        };
+        let mut game = Game::with_rules(Player::A, rules);
+        assert_eq!(step(&mut game, Player::A, (5, 1)), None);
+        assert_eq!(step(&mut game, Player::B, (5, 5)), None);
+        assert_eq!(
+            step(&mut game, Player::A, (5, 2)),
+            Some(RoundResult::Draw(DrawReason::NoProgress))
+        );
+    }
+
+    #[test]
+    fn core_moves_reset_no_progress() {
+        let mut game = Game::new();
+        step(&mut game, Player::A, (5, 1));
+        assert_eq!(game.no_progress(), 1);
+        let core = Piece {
+            plr: Player::B,
+            kind: PieceKind::Core,
+            alive: true,
+        };
+        game.play(Move(
+            core,
+            MoveKind::Move {
+                to: Coordinate::new(4, 5),
+            },
+        ))
+        .unwrap();
+        assert_eq!(game.no_progress(), 0);
+        assert_eq!(game.last_core_mover(), Some(Player::B));
+    }
+
+    #[test]
+    fn cores_freeze_without_monarchs() {
+        let mut game = Game::new();
+        game.board.kill(&monarch(Player::A)).unwrap();
+        assert!(!game.cores_frozen());
+        assert!(
+            game.get_possible_moves(Player::A)
+                .iter()
+                .all(|Move(piece, _)| piece.kind != PieceKind::Core)
+        );
+        game.board.kill(&monarch(Player::B)).unwrap();
+        assert!(game.cores_frozen());
+        assert_eq!(
+            game.evaluate(),
+            Some(RoundResult::Draw(DrawReason::FrozenCores))
+        );
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let mut game = Game::new();
+        let mv = Move(
+            monarch(Player::B),
+            MoveKind::Move {
+                to: Coordinate::new(5, 5),
+            },
+        );
+        assert_eq!(game.play(mv), Err(GameError::IllegalMove(mv)));
+        assert_eq!(game.turn(), Player::A);
     }
 }
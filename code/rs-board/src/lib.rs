@@ -1,14 +1,152 @@
-mod game;
-#[allow(unused)]
-mod types;
+pub mod builder;
+pub mod env;
+pub mod game;
+pub mod r#match;
+pub mod movegen;
+pub mod notation;
+pub mod types;
 
 #[cfg(test)]
 mod tests {
-    use super::types::board::Board;
+    use super::types::board::{Board, BoardError};
+    use super::types::{BruteSide, Coordinate, Direction, Piece, PieceKind, Player};
+
+    fn piece(plr: Player, kind: PieceKind) -> Piece {
+        Piece {
+            plr,
+            kind,
+            alive: true,
+        }
+    }
 
     #[test]
     fn it_works() {
         let board = Board::new();
         println!("{:?}", board);
     }
+
+    #[test]
+    fn starting_board_is_valid() {
+        assert_eq!(Board::new().validate(), Ok(()));
+    }
+
+    #[test]
+    fn move_to_empty_square() {
+        let mut board = Board::new();
+        let tank = piece(Player::A, PieceKind::Tank);
+        board.r#move(&tank, Coordinate::new(6, 1)).unwrap();
+        assert_eq!(board.get_coord(&tank), Some(Coordinate::new(6, 1)));
+        assert!(board.get_piece(Coordinate::new(6, 0)).is_none());
+        assert_eq!(board.validate(), Ok(()));
+    }
+
+    #[test]
+    fn move_rejects_occupied_and_out_of_bounds() {
+        let mut board = Board::new();
+        let core = piece(Player::A, PieceKind::Core);
+        let monarch_square = Coordinate::new(5, 0);
+        assert_eq!(
+            board.r#move(&core, monarch_square),
+            Err(BoardError::DestinationOccupied(monarch_square))
+        );
+        // l1 would be a2 if the file wrapped round to the next rank
+        for off_board in [Coordinate::new(0, 7), Coordinate::new(11, 0)] {
+            assert_eq!(
+                board.r#move(&core, off_board),
+                Err(BoardError::OutOfBounds(off_board))
+            );
+            assert!(board.get_piece(off_board).is_none());
+        }
+        assert_ne!(Coordinate::new(11, 0), Coordinate::new(0, 1));
+        assert_eq!(board.get_coord(&core), Some(Coordinate::new(4, 0)));
+        assert_eq!(board.validate(), Ok(()));
+    }
+
+    #[test]
+    fn kill_clears_piece_once() {
+        let mut board = Board::new();
+        let brute = piece(Player::B, PieceKind::Brute(BruteSide::Left));
+        board.kill(&brute).unwrap();
+        assert!(board.get_coord(&brute).is_none());
+        assert_eq!(board.validate(), Ok(()));
+
+        let dead = Piece {
+            alive: false,
+            ..brute
+        };
+        assert_eq!(board.kill(&brute), Err(BoardError::PieceDead(dead)));
+        assert_eq!(
+            board.r#move(&brute, Coordinate::new(0, 5)),
+            Err(BoardError::PieceDead(dead))
+        );
+        assert_eq!(board.validate(), Ok(()));
+    }
+
+    #[test]
+    fn try_new_checks_bounds() {
+        assert_eq!(Coordinate::try_new(10, 6), Some(Coordinate::new(10, 6)));
+        assert_eq!(Coordinate::try_new(11, 0), None);
+        assert_eq!(Coordinate::try_new(0, 7), None);
+        assert_eq!(Coordinate::all().count(), 77);
+    }
+
+    #[test]
+    fn steps_do_not_wrap_rows() {
+        let east_edge = Coordinate::new(10, 3);
+        assert_eq!(east_edge.step(Direction::East, Player::A), None);
+        assert_eq!(east_edge.neighbors().count(), 5);
+        assert_eq!(Coordinate::new(0, 0).neighbors().count(), 3);
+        assert_eq!(Coordinate::new(5, 3).neighbors().count(), 8);
+    }
+
+    #[test]
+    fn forward_depends_on_player() {
+        let center = Coordinate::new(5, 3);
+        assert_eq!(
+            center.step(Direction::Forward, Player::A),
+            Some(Coordinate::new(5, 4))
+        );
+        assert_eq!(
+            center.step(Direction::Forward, Player::B),
+            Some(Coordinate::new(5, 2))
+        );
+        assert_eq!(
+            center.step(Direction::Back, Player::B),
+            center.step(Direction::Forward, Player::A)
+        );
+    }
+
+    #[test]
+    fn rays_stop_at_the_edge() {
+        let ray: Vec<_> = Coordinate::new(8, 1)
+            .ray(Direction::NorthEast, Player::A)
+            .collect();
+        assert_eq!(ray, vec![Coordinate::new(9, 2), Coordinate::new(10, 3)]);
+    }
+
+    #[test]
+    fn distances() {
+        let a = Coordinate::new(1, 1);
+        let b = Coordinate::new(4, 3);
+        assert_eq!(a.chebyshev(b), 3);
+        assert_eq!(a.manhattan(b), 5);
+        assert!(a.is_adjacent(Coordinate::new(2, 2)));
+        assert!(!a.is_adjacent(a));
+    }
+
+    #[test]
+    fn square_names_round_trip() {
+        assert_eq!("a1".parse(), Ok(Coordinate::new(0, 0)));
+        assert_eq!("K7".parse(), Ok(Coordinate::new(10, 6)));
+        assert!("l1".parse::<Coordinate>().is_err());
+        assert!("a8".parse::<Coordinate>().is_err());
+        assert!("a0".parse::<Coordinate>().is_err());
+        assert!("a01".parse::<Coordinate>().is_err());
+        assert!("a+1".parse::<Coordinate>().is_err());
+        assert!("a10".parse::<Coordinate>().is_err());
+        assert!("".parse::<Coordinate>().is_err());
+        for coord in Coordinate::all() {
+            assert_eq!(coord.to_string().parse(), Ok(coord));
+        }
+    }
 }
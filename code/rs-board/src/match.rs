@@ -0,0 +1,253 @@
+use crate::game::{DrawRules, Game, GameError, RoundResult};
+use crate::types::{Move, PLRS, Player};
+
+// what a drawn round is worth
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawOutcome {
+    // nobody scores and the same round is played again with the same first player
+    ReplayRound,
+    // nobody scores and the match moves on to the next round
+    NoPoint,
+    // both players get half a point
+    Split,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchRules {
+    pub points_to_win: u8,
+    pub draw_rules: DrawRules,
+    pub on_draw: DrawOutcome,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            points_to_win: 3,
+            draw_rules: DrawRules::default(),
+            on_draw: DrawOutcome::ReplayRound,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoundRecord {
+    // 1-based, replayed rounds keep their number
+    pub round: u32,
+    pub first: Player,
+    pub result: RoundResult,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchResult {
+    Winner(Player),
+    // only possible with split draws, both players reached the target together
+    Drawn,
+}
+
+#[derive(Clone, Debug)]
+pub struct Match {
+    rules: MatchRules,
+    // counted in half points so split draws stay exact, wider than
+    // `points_to_win` so doubling it can't overflow
+    half_points: [u16; 2],
+    // 0-based index of the round being played
+    round: u32,
+    game: Game,
+    rounds: Vec<RoundRecord>,
+    result: Option<MatchResult>,
+}
+
+impl Match {
+    pub fn new(rules: MatchRules) -> Self {
+        Self {
+            rules,
+            half_points: [0; 2],
+            round: 0,
+            game: Game::with_rules(Self::first_player(0), rules.draw_rules),
+            rounds: Vec::new(),
+            result: None,
+        }
+    }
+
+    // rounds alternate who moves first, starting with A
+    fn first_player(round: u32) -> Player {
+        PLRS[round as usize % 2]
+    }
+
+    pub fn rules(&self) -> &MatchRules {
+        &self.rules
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_game(&mut self, game: Game) {
+        self.game = game;
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round + 1
+    }
+
+    pub fn score(&self, plr: Player) -> f32 {
+        self.half_points[plr.index()] as f32 / 2.0
+    }
+
+    pub fn rounds(&self) -> &[RoundRecord] {
+        &self.rounds
+    }
+
+    pub fn result(&self) -> Option<MatchResult> {
+        self.result
+    }
+
+    pub fn play(&mut self, mv: Move) -> Result<Option<RoundRecord>, GameError> {
+        if self.result.is_some() {
+            return Err(GameError::MatchOver);
+        }
+        Ok(self.game.play(mv)?.map(|result| self.finish_round(result)))
+    }
+
+    // Scores a round played outside `game()`, for callers with rules of
+    // their own. Whatever was played in `game()` is dropped with it.
+    pub fn record_round(&mut self, result: RoundResult) -> Result<RoundRecord, GameError> {
+        if self.result.is_some() {
+            return Err(GameError::MatchOver);
+        }
+        Ok(self.finish_round(result))
+    }
+
+    fn finish_round(&mut self, result: RoundResult) -> RoundRecord {
+        let record = RoundRecord {
+            round: self.round(),
+            first: Self::first_player(self.round),
+            result,
+        };
+        self.rounds.push(record);
+
+        match (result, self.rules.on_draw) {
+            (RoundResult::Touch(winner), _) => {
+                self.half_points[winner.index()] += 2;
+                self.round += 1;
+            }
+            (RoundResult::Draw(_), DrawOutcome::ReplayRound) => {}
+            (RoundResult::Draw(_), DrawOutcome::NoPoint) => self.round += 1,
+            (RoundResult::Draw(_), DrawOutcome::Split) => {
+                self.half_points[0] += 1;
+                self.half_points[1] += 1;
+                self.round += 1;
+            }
+        }
+
+        let target = u16::from(self.rules.points_to_win) * 2;
+        self.result = match self.half_points.map(|points| points >= target) {
+            [true, true] => Some(MatchResult::Drawn),
+            [true, false] => Some(MatchResult::Winner(Player::A)),
+            [false, true] => Some(MatchResult::Winner(Player::B)),
+            [false, false] => None,
+        };
+        if self.result.is_none() {
+            self.game = Game::with_rules(Self::first_player(self.round), self.rules.draw_rules);
+        }
+        record
+    }
+}
+
+impl Default for Match {
+    fn default() -> Self {
+        Self::new(MatchRules::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DrawReason;
+
+    fn with_draws(on_draw: DrawOutcome) -> Match {
+        Match::new(MatchRules {
+            on_draw,
+            ..MatchRules::default()
+        })
+    }
+
+    #[test]
+    fn touches_score_a_point_and_alternate_first_player() {
+        let mut m = Match::default();
+        assert_eq!(m.game().turn(), Player::A);
+        m.finish_round(RoundResult::Touch(Player::B));
+        assert_eq!(m.score(Player::B), 1.0);
+        assert_eq!(m.round(), 2);
+        assert_eq!(m.game().turn(), Player::B);
+        m.finish_round(RoundResult::Touch(Player::B));
+        m.finish_round(RoundResult::Touch(Player::B));
+        assert_eq!(m.result(), Some(MatchResult::Winner(Player::B)));
+        assert_eq!(m.rounds().len(), 3);
+    }
+
+    #[test]
+    fn replayed_round_keeps_number_and_first_player() {
+        let mut m = with_draws(DrawOutcome::ReplayRound);
+        let record = m.finish_round(RoundResult::Draw(DrawReason::Repetition));
+        assert_eq!(record.round, 1);
+        assert_eq!(m.round(), 1);
+        assert_eq!(m.game().turn(), Player::A);
+        assert_eq!(m.score(Player::A), 0.0);
+        assert_eq!(m.score(Player::B), 0.0);
+    }
+
+    #[test]
+    fn no_point_draw_moves_on() {
+        let mut m = with_draws(DrawOutcome::NoPoint);
+        m.finish_round(RoundResult::Draw(DrawReason::FrozenCores));
+        assert_eq!(m.round(), 2);
+        assert_eq!(m.game().turn(), Player::B);
+        assert_eq!(m.score(Player::A), 0.0);
+    }
+
+    #[test]
+    fn split_draws_can_draw_the_match() {
+        let mut m = with_draws(DrawOutcome::Split);
+        for _ in 0..5 {
+            m.finish_round(RoundResult::Draw(DrawReason::NoProgress));
+        }
+        assert_eq!(m.score(Player::A), 2.5);
+        assert_eq!(m.result(), None);
+        m.finish_round(RoundResult::Draw(DrawReason::NoProgress));
+        assert_eq!(m.result(), Some(MatchResult::Drawn));
+        assert_eq!(m.play(m.game().legal_moves()[0]), Err(GameError::MatchOver));
+    }
+
+    #[test]
+    fn rounds_played_elsewhere_are_scored_alike() {
+        let mut m = Match::default();
+        let record = m.record_round(RoundResult::Touch(Player::A)).unwrap();
+        assert_eq!((record.round, record.first), (1, Player::A));
+        assert_eq!(m.score(Player::A), 1.0);
+        assert_eq!(m.game().turn(), Player::B);
+        m.record_round(RoundResult::Touch(Player::A)).unwrap();
+        m.record_round(RoundResult::Touch(Player::A)).unwrap();
+        assert_eq!(m.result(), Some(MatchResult::Winner(Player::A)));
+        assert_eq!(
+            m.record_round(RoundResult::Touch(Player::B)),
+            Err(GameError::MatchOver)
+        );
+    }
+
+    #[test]
+    fn large_targets_do_not_overflow() {
+        let mut m = Match::new(MatchRules {
+            points_to_win: u8::MAX,
+            ..MatchRules::default()
+        });
+        for _ in 0..254 {
+            m.finish_round(RoundResult::Touch(Player::A));
+        }
+        assert_eq!(m.score(Player::A), 254.0);
+        assert_eq!(m.result(), None);
+        m.finish_round(RoundResult::Touch(Player::A));
+        assert_eq!(m.result(), Some(MatchResult::Winner(Player::A)));
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+use std::ops::Deref;
+
+use crate::types::{
+    Coordinate, Direction, HEIGHT, KINDS, Move, MoveKind, Piece, PieceKind, Player, WIDTH,
+    board::Board,
+};
+
+// Core 16 + Monarch 8 + two Brutes and a Tank with 4 each (a Brute's attack
+// takes the place of its forward step)
+pub const MAX_MOVES: usize = 36;
+
+const OFF_BOARD: u8 = u8::MAX;
+
+// neighbouring square of every square in each of `Direction::ALL`, the
+// orthogonal directions come first
+static STEPS: [[u8; 8]; HEIGHT * WIDTH] = step_table();
+
+const fn step_table() -> [[u8; 8]; HEIGHT * WIDTH] {
+    let mut table = [[OFF_BOARD; 8]; HEIGHT * WIDTH];
+    let mut idx = 0;
+    while idx < HEIGHT * WIDTH {
+        let (x, y) = ((idx % WIDTH) as i8, (idx / WIDTH) as i8);
+        let mut d = 0;
+        while d < Direction::ALL.len() {
+            let (dx, dy) = Direction::ALL[d].delta(Player::A);
+            let (nx, ny) = (x + dx, y + dy);
+            if nx >= 0 && ny >= 0 && (nx as usize) < WIDTH && (ny as usize) < HEIGHT {
+                table[idx][d] = Coordinate::idx(nx as u8, ny as u8) as u8;
+            }
+            d += 1;
+        }
+        idx += 1;
+    }
+    table
+}
+
+fn step(from: usize, d: usize) -> Option<Coordinate> {
+    match STEPS[from][d] {
+        OFF_BOARD => None,
+        idx => Some(Coordinate::from_idx(idx as usize)),
+    }
+}
+
+// Fixed-capacity move buffer, so generating moves never touches the heap.
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+const PLACEHOLDER: Move = Move(
+    Piece {
+        plr: Player::A,
+        kind: PieceKind::Core,
+        alive: false,
+    },
+    MoveKind::Move {
+        to: Coordinate::new(0, 0),
+    },
+);
+
+impl MoveList {
+    pub const fn new() -> Self {
+        Self {
+            moves: [PLACEHOLDER; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// a Core only moves while its Monarch stands next to it
+pub(crate) fn core_can_move(board: &Board, plr: Player) -> bool {
+    let coord = |kind| {
+        board.get_coord(&Piece {
+            plr,
+            kind,
+            alive: true,
+        })
+    };
+    match (coord(PieceKind::Core), coord(PieceKind::Monarch)) {
+        (Some(core), Some(monarch)) => core.is_adjacent(monarch),
+        _ => false,
+    }
+}
+
+pub fn generate_moves(board: &Board, plr: Player, out: &mut MoveList) {
+    out.clear();
+    for (_, kind) in KINDS {
+        let piece = Piece {
+            plr,
+            kind,
+            alive: true,
+        };
+        let Some(from) = board.get_coord(&piece) else {
+            continue;
+        };
+        if kind == PieceKind::Core && !core_can_move(board, plr) {
+            continue;
+        }
+        let directions = match kind {
+            PieceKind::Core | PieceKind::Monarch => Direction::ALL.len(),
+            _ => Direction::ORTHOGONAL.len(),
+        };
+        let forward = Direction::Forward.resolve(plr);
+
+        for d in 0..directions {
+            let Some(to) = step(from.into(), d) else {
+                continue;
+            };
+            let occupant = board.get_piece(to);
+            if occupant.is_none() {
+                out.push(Move(piece, MoveKind::Move { to }));
+            }
+            match (kind, occupant) {
+                // one or two squares, the second may jump the first
+                (PieceKind::Core, _) => {
+                    if let Some(far) = step(to.into(), d)
+                        && board.get_piece(far).is_none()
+                    {
+                        out.push(Move(piece, MoveKind::Move { to: far }));
+                    }
+                }
+                // only the enemy piece in front of it
+                (PieceKind::Brute(_), Some(target))
+                    if Direction::ALL[d] == forward && target.plr != plr =>
+                {
+                    out.push(Move(piece, MoveKind::Attack { target: to }));
+                }
+                // through any piece but a Core
+                (PieceKind::Tank, Some(target)) if target.kind != PieceKind::Core => {
+                    if let Some(dash_to) = step(to.into(), d)
+                        && board.get_piece(dash_to).is_none()
+                    {
+                        out.push(Move(
+                            piece,
+                            MoveKind::MoveAndAttack {
+                                to: dash_to,
+                                target: to,
+                            },
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::types::BruteSide;
+
+    #[test]
+    fn step_table_matches_coordinate_steps() {
+        for coord in Coordinate::all() {
+            for (d, dir) in Direction::ALL.into_iter().enumerate() {
+                assert_eq!(step(coord.into(), d), coord.step(dir, Player::A));
+            }
+        }
+    }
+
+    #[test]
+    fn starting_moves() {
+        let mut moves = MoveList::new();
+        generate_moves(&Board::new(), Player::A, &mut moves);
+        // Brutes 2 + 2, Core 8, Monarch 3, Tank 2
+        assert_eq!(moves.len(), 17);
+        assert!(moves.iter().all(|Move(piece, _)| piece.plr == Player::A));
+    }
+
+    fn moves_of(notation: &str, plr: Player, kind: PieceKind) -> Vec<String> {
+        let game = Game::from_notation(notation).unwrap();
+        let mut moves = MoveList::new();
+        generate_moves(game.board(), plr, &mut moves);
+        moves
+            .iter()
+            .filter(|Move(piece, _)| piece.kind == kind)
+            .map(Move::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn brutes_attack_only_the_enemy_in_front() {
+        // A's Tank in front of B's Brute, A's Core and B's Tank beside it
+        let attacks = |notation| {
+            moves_of(notation, Player::B, PieceKind::Brute(BruteSide::Left))
+                .into_iter()
+                .filter(|mv| mv.contains('x'))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(attacks("11/11/11/1Clt7/2T8/11/11 b"), ["lxc3"]);
+        // its own Tank in front and A's behind it
+        assert!(attacks("11/11/2T8/1Cl8/2t8/11/11 b").is_empty());
+    }
+
+    #[test]
+    fn tanks_do_not_dash_through_cores() {
+        // B's Core below A's Tank, B's Brute beside it
+        let moves = moves_of("11/11/11/11/2Tl7/2c8/11 a", Player::A, PieceKind::Tank);
+        let dashes: Vec<_> = moves.iter().filter(|mv| mv.contains('x')).collect();
+        assert_eq!(dashes, ["Txd3e3"]);
+    }
+
+    #[test]
+    fn cores_move_up_to_two_squares() {
+        // B's Tank below A's Core and the Monarch beside it are jumped
+        let moves = moves_of("11/11/11/3CM6/3t7/11/11 a", Player::A, PieceKind::Core);
+        assert_eq!(moves.len(), 14);
+        assert!(moves.iter().any(|mv| mv == "Cd2") && moves.iter().any(|mv| mv == "Cf4"));
+        assert!(!moves.iter().any(|mv| mv == "Cd3" || mv == "Ce4"));
+        // without the Monarch next to it the Core stays put
+        assert!(moves_of("11/11/11/3C1M5/11/11/11 a", Player::A, PieceKind::Core).is_empty());
+    }
+}
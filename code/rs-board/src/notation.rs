@@ -0,0 +1,295 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::builder::{BoardBuilder, BuildError};
+use crate::game::Game;
+use crate::types::{
+    BruteSide, Coordinate, HEIGHT, Move, MoveKind, Piece, PieceKind, Player, WIDTH,
+};
+
+// Positions are written like FEN: the ranks from 7 down to 1 separated by '/',
+// pieces as letters (upper case for A, lower case for B) and runs of empty
+// squares as numbers, then the side to move and the last Core mover ('-' for
+// none). The starting position is
+//
+//     l3cmt3r/11/11/11/11/11/L3CMT3R a -
+//
+// The last field may be left out.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NotationError {
+    // not 7 ranks, or not 11 squares in the given rank (1-based)
+    RankCount(usize),
+    RankLength(u8),
+    UnknownPiece(char),
+    InvalidPlayer(String),
+    MissingTurn,
+    TrailingInput(String),
+    Build(BuildError),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RankCount(count) => write!(f, "expected {HEIGHT} ranks, found {count}"),
+            Self::RankLength(rank) => write!(f, "rank {rank} doesn't have {WIDTH} squares"),
+            Self::UnknownPiece(c) => write!(f, "unknown piece {c:?}"),
+            Self::InvalidPlayer(s) => write!(f, "expected 'a' or 'b', found {s:?}"),
+            Self::MissingTurn => write!(f, "the side to move is missing"),
+            Self::TrailingInput(s) => write!(f, "unexpected {s:?} after the position"),
+            Self::Build(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<BuildError> for NotationError {
+    fn from(err: BuildError) -> Self {
+        Self::Build(err)
+    }
+}
+
+fn kind_letter(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Core => 'C',
+        PieceKind::Monarch => 'M',
+        PieceKind::Tank => 'T',
+        PieceKind::Brute(BruteSide::Left) => 'L',
+        PieceKind::Brute(BruteSide::Right) => 'R',
+    }
+}
+
+fn piece_letter(piece: &Piece) -> char {
+    match piece.plr {
+        Player::A => kind_letter(piece.kind),
+        Player::B => kind_letter(piece.kind).to_ascii_lowercase(),
+    }
+}
+
+fn parse_piece(c: char) -> Option<(Player, PieceKind)> {
+    let plr = if c.is_ascii_uppercase() {
+        Player::A
+    } else {
+        Player::B
+    };
+    let kind = match c.to_ascii_uppercase() {
+        'C' => PieceKind::Core,
+        'M' => PieceKind::Monarch,
+        'T' => PieceKind::Tank,
+        'L' => PieceKind::Brute(BruteSide::Left),
+        'R' => PieceKind::Brute(BruteSide::Right),
+        _ => return None,
+    };
+    Some((plr, kind))
+}
+
+fn player_name(plr: Player) -> char {
+    match plr {
+        Player::A => 'a',
+        Player::B => 'b',
+    }
+}
+
+fn parse_player(s: &str) -> Result<Player, NotationError> {
+    match s {
+        "a" | "A" => Ok(Player::A),
+        "b" | "B" => Ok(Player::B),
+        _ => Err(NotationError::InvalidPlayer(s.to_string())),
+    }
+}
+
+// Only checks the notation itself, `BoardBuilder::build` checks the position.
+impl FromStr for BoardBuilder {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let ranks: Vec<&str> = fields.next().unwrap_or("").split('/').collect();
+        if ranks.len() != HEIGHT {
+            return Err(NotationError::RankCount(ranks.len()));
+        }
+
+        let mut builder = BoardBuilder::new();
+        for (row, rank) in ranks.iter().enumerate() {
+            let y = (HEIGHT - 1 - row) as u8;
+            let mut x = 0usize;
+            let mut chars = rank.chars().peekable();
+            while let Some(c) = chars.next() {
+                if let Some(digit) = c.to_digit(10) {
+                    let mut empty = digit as usize;
+                    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        // any run longer than the rank is already wrong, stop before it overflows
+                        empty = empty * 10 + digit as usize;
+                        if empty > WIDTH {
+                            return Err(NotationError::RankLength(y + 1));
+                        }
+                        chars.next();
+                    }
+                    x += empty;
+                } else {
+                    let (plr, kind) = parse_piece(c).ok_or(NotationError::UnknownPiece(c))?;
+                    if x < WIDTH {
+                        builder = builder.place(plr, kind, Coordinate::new(x as u8, y));
+                    }
+                    x += 1;
+                }
+                if x > WIDTH {
+                    break;
+                }
+            }
+            if x != WIDTH {
+                return Err(NotationError::RankLength(y + 1));
+            }
+        }
+
+        let turn = parse_player(fields.next().ok_or(NotationError::MissingTurn)?)?;
+        let last_core_mover = match fields.next() {
+            None | Some("-") => None,
+            Some(plr) => Some(parse_player(plr)?),
+        };
+        let rest: Vec<&str> = fields.collect();
+        if !rest.is_empty() {
+            return Err(NotationError::TrailingInput(rest.join(" ")));
+        }
+        Ok(builder.turn(turn).last_core_mover(last_core_mover))
+    }
+}
+
+impl Game {
+    pub fn from_notation(s: &str) -> Result<Game, NotationError> {
+        Ok(s.parse::<BoardBuilder>()?.build()?)
+    }
+
+    pub fn notation(&self) -> String {
+        let mut out = String::new();
+        for y in (0..HEIGHT as u8).rev() {
+            let mut empty = 0;
+            for x in 0..WIDTH as u8 {
+                match self.board().get_piece(Coordinate::new(x, y)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            out.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        out.push(piece_letter(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                out.push_str(&empty.to_string());
+            }
+            if y > 0 {
+                out.push('/');
+            }
+        }
+        out.push(' ');
+        out.push(player_name(self.turn()));
+        out.push(' ');
+        out.push(self.last_core_mover().map_or('-', player_name));
+        out
+    }
+}
+
+// The piece letter followed by the destination, 'x' marks the captured square:
+// "Ce4", "lxf5", "Txe5e6" (a Tank dash over e5 to e6).
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Move(piece, kind) = self;
+        let letter = piece_letter(piece);
+        match kind {
+            MoveKind::Move { to } => write!(f, "{letter}{to}"),
+            MoveKind::Attack { target } => write!(f, "{letter}x{target}"),
+            MoveKind::MoveAndAttack { to, target } => write!(f, "{letter}x{target}{to}"),
+        }
+    }
+}
+
+// moves separated by spaces, for printing lines of play
+pub fn format_line(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|mv| mv.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::board::Board;
+
+    #[test]
+    fn starting_position_round_trips() {
+        let start = "l3cmt3r/11/11/11/11/11/L3CMT3R a -";
+        assert_eq!(Game::new().notation(), start);
+        let game = Game::from_notation(start).unwrap();
+        assert_eq!(*game.board(), Board::new());
+        assert_eq!(game.turn(), Player::A);
+    }
+
+    #[test]
+    fn parses_custom_positions() {
+        let game = Game::from_notation("11/11/4c6/11/4CM5/11/11 b a").unwrap();
+        assert_eq!(game.turn(), Player::B);
+        assert_eq!(game.last_core_mover(), Some(Player::A));
+        let core = Piece {
+            plr: Player::B,
+            kind: PieceKind::Core,
+            alive: true,
+        };
+        assert_eq!(game.board().get_coord(&core), Some("e5".parse().unwrap()));
+        assert_eq!(game.notation(), "11/11/4c6/11/4CM5/11/11 b a");
+        assert_eq!(
+            Game::from_notation("11/11/4c6/11/4CM5/11/11 a")
+                .unwrap()
+                .last_core_mover(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_bad_notation() {
+        let err = |s| Game::from_notation(s).unwrap_err();
+        assert_eq!(err("11/11/11 a"), NotationError::RankCount(3));
+        assert_eq!(
+            err("11/11/4c6/11/4CM6/11/11 a"),
+            NotationError::RankLength(3)
+        );
+        assert_eq!(
+            err("11/11/4x6/11/4CM5/11/11 a"),
+            NotationError::UnknownPiece('x')
+        );
+        assert_eq!(
+            err("99999999999999999999999/11/4c6/11/4CM5/11/11 a"),
+            NotationError::RankLength(7)
+        );
+        assert_eq!(err("11/11/4c6/11/4CM5/11/11"), NotationError::MissingTurn);
+        assert_eq!(
+            err("11/11/4c6/11/4CM5/11/11 c"),
+            NotationError::InvalidPlayer("c".to_string())
+        );
+        assert!(matches!(
+            err("11/11/11/4c6/4CM5/11/11 a"),
+            NotationError::Build(BuildError::CoresTouching(_, _))
+        ));
+    }
+
+    #[test]
+    fn move_names() {
+        let tank = Piece {
+            plr: Player::B,
+            kind: PieceKind::Tank,
+            alive: true,
+        };
+        let mv = Move(
+            tank,
+            MoveKind::MoveAndAttack {
+                to: "e4".parse().unwrap(),
+                target: "e5".parse().unwrap(),
+            },
+        );
+        assert_eq!(mv.to_string(), "txe5e4");
+    }
+}
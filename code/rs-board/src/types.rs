@@ -1,4 +1,5 @@
-use std::slice::SliceIndex;
+use std::fmt;
+use std::str::FromStr;
 
 pub const HEIGHT: usize = 7;
 pub const WIDTH: usize = 11;
@@ -18,19 +19,35 @@ pub const KINDS: [(u8, PieceKind); 5] = [
     (10, PieceKind::Brute(BruteSide::Right)),
 ];
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BruteSide {
     Left,
     Right,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Player {
     A,
     B,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Player {
+    pub const fn opponent(self) -> Player {
+        match self {
+            Player::A => Player::B,
+            Player::B => Player::A,
+        }
+    }
+
+    pub const fn index(self) -> usize {
+        match self {
+            Player::A => 0,
+            Player::B => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PieceKind {
     Core,
     Monarch,
@@ -38,26 +55,125 @@ pub enum PieceKind {
     Tank,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub plr: Player,
     pub kind: PieceKind,
     pub alive: bool,
 }
-#[derive(Clone, Copy, Debug)]
-pub struct Coordinate(usize);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    // towards rank 7 (B's home row)
+    North,
+    // towards rank 1 (A's home row)
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+    // towards the opponent's home row
+    Forward,
+    // towards the player's own home row
+    Back,
+}
+
+impl Direction {
+    pub const ORTHOGONAL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+    pub const DIAGONAL: [Direction; 4] = [
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+
+    // turns Forward/Back into the compass direction they mean for `plr`
+    pub const fn resolve(self, plr: Player) -> Direction {
+        match (self, plr) {
+            (Direction::Forward, Player::A) | (Direction::Back, Player::B) => Direction::North,
+            (Direction::Forward, Player::B) | (Direction::Back, Player::A) => Direction::South,
+            (dir, _) => dir,
+        }
+    }
+
+    pub const fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::NorthWest => Direction::SouthEast,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::Forward => Direction::Back,
+            Direction::Back => Direction::Forward,
+        }
+    }
+
+    pub const fn delta(self, plr: Player) -> (i8, i8) {
+        match self.resolve(plr) {
+            Direction::North => (0, 1),
+            Direction::South => (0, -1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::NorthEast => (1, 1),
+            Direction::NorthWest => (-1, 1),
+            Direction::SouthEast => (1, -1),
+            Direction::SouthWest => (-1, -1),
+            Direction::Forward | Direction::Back => unreachable!(),
+        }
+    }
+}
+
+// file and rank as given, so a square off the board stays itself instead of
+// wrapping round to the next rank and `Board` can report it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Coordinate {
+    x: u8,
+    y: u8,
+}
 
 impl Coordinate {
-    pub fn new(x: u8, y: u8) -> Self {
-        Self(Self::idx(x, y))
+    // unchecked, see `try_new` for coordinates that come from outside the board
+    pub const fn new(x: u8, y: u8) -> Self {
+        Self { x, y }
+    }
+
+    pub(crate) const fn from_idx(idx: usize) -> Self {
+        Self::new((idx % WIDTH) as u8, (idx / WIDTH) as u8)
+    }
+
+    pub fn try_new(x: u8, y: u8) -> Option<Self> {
+        Self::new(x, y).is_on_board().then(|| Self::new(x, y))
+    }
+
+    pub const fn is_on_board(&self) -> bool {
+        (self.x as usize) < WIDTH && (self.y as usize) < HEIGHT
     }
 
     pub fn x(&self) -> u8 {
-        (self.0 % WIDTH) as u8
+        self.x
     }
 
     pub fn y(&self) -> u8 {
-        (self.0 / WIDTH) as u8
+        self.y
     }
 
     pub fn xy(&self) -> (u8, u8) {
@@ -69,27 +185,113 @@ impl Coordinate {
         // row-major indexing: row * COLS + column
         (y as usize) * WIDTH + (x as usize)
     }
+
+    pub fn all() -> impl Iterator<Item = Coordinate> {
+        (0..HEIGHT * WIDTH).map(Self::from_idx)
+    }
+
+    pub fn offset(self, dx: i8, dy: i8) -> Option<Self> {
+        let x = self.x().checked_add_signed(dx)?;
+        let y = self.y().checked_add_signed(dy)?;
+        Self::try_new(x, y)
+    }
+
+    pub fn step(self, dir: Direction, plr: Player) -> Option<Self> {
+        let (dx, dy) = dir.delta(plr);
+        self.offset(dx, dy)
+    }
+
+    // the squares touching this one, diagonals included
+    pub fn neighbors(self) -> impl Iterator<Item = Coordinate> {
+        Direction::ALL
+            .into_iter()
+            .filter_map(move |dir| self.step(dir, Player::A))
+    }
+
+    // every square from here to the edge of the board in `dir`, excluding this one
+    pub fn ray(self, dir: Direction, plr: Player) -> impl Iterator<Item = Coordinate> {
+        std::iter::successors(self.step(dir, plr), move |coord| coord.step(dir, plr))
+    }
+
+    pub fn chebyshev(self, other: Coordinate) -> u8 {
+        self.x()
+            .abs_diff(other.x())
+            .max(self.y().abs_diff(other.y()))
+    }
+
+    pub fn manhattan(self, other: Coordinate) -> u8 {
+        self.x().abs_diff(other.x()) + self.y().abs_diff(other.y())
+    }
+
+    pub fn is_adjacent(self, other: Coordinate) -> bool {
+        self.chebyshev(other) == 1
+    }
+}
+
+// the square's index on the board, only meaningful on the board
+impl From<Coordinate> for usize {
+    fn from(coord: Coordinate) -> usize {
+        Coordinate::idx(coord.x, coord.y)
+    }
+}
+
+// squares are named like chess squares: file a..k (x), rank 1..7 (y), and
+// squares off the board by their coordinates
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_on_board() {
+            return write!(f, "{:?}", self.xy());
+        }
+        write!(f, "{}{}", (b'a' + self.x) as char, self.y + 1)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseCoordinateError {
+    InvalidFile(String),
+    InvalidRank(String),
 }
 
-impl Into<usize> for Coordinate {
-    fn into(self) -> usize {
-        self.0
+impl fmt::Display for ParseCoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFile(s) => write!(f, "invalid file in square name {s:?}"),
+            Self::InvalidRank(s) => write!(f, "invalid rank in square name {s:?}"),
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl std::error::Error for ParseCoordinateError {}
+
+impl FromStr for Coordinate {
+    type Err = ParseCoordinateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let x = match chars.next().map(|file| file.to_ascii_lowercase()) {
+            Some(file @ 'a'..='z') if ((file as u8 - b'a') as usize) < WIDTH => file as u8 - b'a',
+            _ => return Err(ParseCoordinateError::InvalidFile(s.to_string())),
+        };
+        // a single digit, so "a01" and "a+1" aren't names of a1
+        let y = match chars.as_str().as_bytes() {
+            [rank @ b'1'..=b'9'] if ((rank - b'0') as usize) <= HEIGHT => rank - b'1',
+            _ => return Err(ParseCoordinateError::InvalidRank(s.to_string())),
+        };
+        Ok(Self::new(x, y))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum MoveKind {
     Move { to: Coordinate },
     Attack { target: Coordinate },
     MoveAndAttack { to: Coordinate, target: Coordinate },
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Move(pub Piece, pub MoveKind);
 
 pub mod board {
-    use std::ops::Index;
-
     use super::*;
     type RawBoard = ([Option<u8>; HEIGHT * WIDTH], [Piece; 10]);
 
@@ -109,7 +311,7 @@ pub mod board {
             let plr = PLRS[plr_i as usize];
             let (x, kind) = KINDS[i as usize % 5];
             let y = plr_i * (HEIGHT as u8 - 1);
-            board.0[Coordinate::idx(x, y)] = Some(i as u8);
+            board.0[Coordinate::idx(x, y)] = Some(i);
             board.1[i as usize] = Piece {
                 alive: true,
                 kind,
@@ -122,10 +324,49 @@ pub mod board {
         board
     }
 
-    #[derive(Clone, Copy, Debug)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum BoardError {
+        PieceNotOnBoard(Piece),
+        PieceDead(Piece),
+        DuplicatePiece(Piece),
+        DestinationOccupied(Coordinate),
+        EmptySquare(Coordinate),
+        OutOfBounds(Coordinate),
+    }
+
+    impl fmt::Display for BoardError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::PieceNotOnBoard(piece) => {
+                    write!(f, "{:?} {:?} is not on the board", piece.plr, piece.kind)
+                }
+                Self::PieceDead(piece) => {
+                    write!(f, "{:?} {:?} has been captured", piece.plr, piece.kind)
+                }
+                Self::DuplicatePiece(piece) => {
+                    write!(
+                        f,
+                        "{:?} {:?} is on more than one square",
+                        piece.plr, piece.kind
+                    )
+                }
+                Self::DestinationOccupied(coord) => {
+                    write!(f, "square {:?} is already occupied", coord.xy())
+                }
+                Self::EmptySquare(coord) => write!(f, "square {:?} is empty", coord.xy()),
+                Self::OutOfBounds(coord) => write!(f, "square {:?} is off the board", coord.xy()),
+            }
+        }
+    }
+
+    impl std::error::Error for BoardError {}
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
     pub struct Board {
         positions: [Option<u8>; HEIGHT * WIDTH],
         pieces: [Piece; 10],
+        // square index of every piece, mirrors `positions` so lookups don't scan the board
+        coords: [Option<u8>; 10],
     }
 
     impl Board {
@@ -136,10 +377,7 @@ pub mod board {
                 alive: _,
             }: &Piece,
         ) -> usize {
-            let plr_i = match plr {
-                Player::A => 0usize,
-                Player::B => 1usize,
-            };
+            let plr_i = plr.index();
             let kind_i = match kind {
                 PieceKind::Brute(BruteSide::Left) => 0usize,
                 PieceKind::Core => 1usize,
@@ -150,55 +388,139 @@ pub mod board {
             plr_i * 5 + kind_i
         }
 
-        fn set(&mut self, Coordinate(idx): Coordinate, piece: Option<Piece>) {
+        fn set(&mut self, coord: Coordinate, piece: Option<Piece>) {
+            let idx = usize::from(coord);
+            if let Some(old) = self.positions[idx]
+                && self.coords[old as usize] == Some(idx as u8)
+            {
+                self.coords[old as usize] = None;
+            }
             self.positions[idx] = piece.map(|p| Self::piece_id(&p) as u8);
+            if let Some(piece_id) = self.positions[idx] {
+                self.coords[piece_id as usize] = Some(idx as u8);
+            }
+        }
+
+        fn from_raw((positions, pieces): RawBoard) -> Self {
+            let mut coords = [None; 10];
+            for (idx, piece_id) in positions.iter().enumerate() {
+                if let Some(piece_id) = piece_id {
+                    coords[*piece_id as usize] = Some(idx as u8);
+                }
+            }
+            Self {
+                positions,
+                pieces,
+                coords,
+            }
         }
     }
 
     impl Board {
         pub fn new() -> Self {
-            let (positions, pieces) = default_board();
-            Self { positions, pieces }
+            Self::from_raw(default_board())
+        }
+
+        // every piece captured, used as the starting point for custom positions
+        pub fn empty() -> Self {
+            let (_, mut pieces) = default_board();
+            for piece in pieces.iter_mut() {
+                piece.alive = false;
+            }
+            Self::from_raw(([None; HEIGHT * WIDTH], pieces))
+        }
+
+        // callers are responsible for keeping the board valid, see `BoardBuilder`
+        pub(crate) fn place(&mut self, piece: &Piece, coord: Coordinate) {
+            self.pieces[Self::piece_id(piece)].alive = true;
+            self.set(coord, Some(*piece));
+        }
+
+        // None off the board as well
+        pub fn get_piece(&self, coord: Coordinate) -> Option<&Piece> {
+            if !coord.is_on_board() {
+                return None;
+            }
+            self.positions[usize::from(coord)].map(|piece| &self.pieces[piece as usize])
         }
 
-        pub fn get_piece(&self, Coordinate(idx): Coordinate) -> Option<&Piece> {
-            self.positions[idx].map(|piece| &self.pieces[piece as usize])
+        pub fn is_alive(&self, piece: &Piece) -> bool {
+            self.pieces[Self::piece_id(piece)].alive
         }
 
         pub fn get_coord(&self, piece: &Piece) -> Option<Coordinate> {
-            let piece_id = Self::piece_id(piece);
-            self.positions.iter().enumerate().find_map(|(idx, &pos)| {
-                if pos == Some(piece_id as u8) {
-                    Some(Coordinate(idx))
-                } else {
-                    None
-                }
+            self.coords[Self::piece_id(piece)].map(|idx| Coordinate::from_idx(idx as usize))
+        }
+
+        // exact, collision-free key of where every piece stands (captured pieces included)
+        pub fn key(&self) -> u128 {
+            self.coords.iter().fold(0u128, |key, coord| {
+                (key << 7) | coord.map_or(0x7f, |idx| idx as u128)
             })
         }
 
-        pub fn kill(&mut self, piece: &Piece) {
+        pub fn kill(&mut self, piece: &Piece) -> Result<(), BoardError> {
             let piece_id = Self::piece_id(piece);
-            let coord = self.get_coord(piece).unwrap();
-            let piece = &mut self.pieces[piece_id as usize];
-            piece.alive = !piece.alive;
+            let stored = self.pieces[piece_id];
+            if !stored.alive {
+                return Err(BoardError::PieceDead(stored));
+            }
+            let coord = self
+                .get_coord(piece)
+                .ok_or(BoardError::PieceNotOnBoard(stored))?;
+            self.pieces[piece_id].alive = false;
             self.set(coord, None);
+            debug_assert_eq!(self.validate(), Ok(()));
+            Ok(())
+        }
+
+        pub fn r#move(&mut self, piece: &Piece, to: Coordinate) -> Result<(), BoardError> {
+            if !to.is_on_board() {
+                return Err(BoardError::OutOfBounds(to));
+            }
+            let stored = self.pieces[Self::piece_id(piece)];
+            if !stored.alive {
+                return Err(BoardError::PieceDead(stored));
+            }
+            let current_coord = self
+                .get_coord(piece)
+                .ok_or(BoardError::PieceNotOnBoard(stored))?;
+            if self.get_piece(to).is_some() {
+                return Err(BoardError::DestinationOccupied(to));
+            }
+            self.set(to, Some(stored));
+            self.set(current_coord, None);
+            debug_assert_eq!(self.validate(), Ok(()));
+            Ok(())
         }
 
-        pub fn r#move(&mut self, piece: &Piece, to: Coordinate) -> bool {
-            let current_coord = self.get_coord(piece);
-            if let Some(current_coord) = current_coord {
-                if self.get_piece(to).is_none() {
-                    self.set(to, Some(*piece));
-                    self.set(current_coord, None);
-                    return true;
+        // every alive piece sits on exactly one square, captured pieces on none
+        pub fn validate(&self) -> Result<(), BoardError> {
+            let mut counts = [0u8; 10];
+            for &piece_id in self.positions.iter().flatten() {
+                counts[piece_id as usize] += 1;
+            }
+            for (piece_id, (piece, count)) in self.pieces.iter().zip(counts).enumerate() {
+                let indexed = self.coords[piece_id]
+                    .is_some_and(|idx| self.positions[idx as usize] == Some(piece_id as u8));
+                match (piece.alive, count) {
+                    (true, 1) if !indexed => return Err(BoardError::PieceNotOnBoard(*piece)),
+                    (false, 0) if self.coords[piece_id].is_some() => {
+                        return Err(BoardError::PieceDead(*piece));
+                    }
+                    (true, 1) | (false, 0) => {}
+                    (true, 0) => return Err(BoardError::PieceNotOnBoard(*piece)),
+                    (false, _) => return Err(BoardError::PieceDead(*piece)),
+                    (true, _) => return Err(BoardError::DuplicatePiece(*piece)),
                 }
             }
-            false
+            Ok(())
         }
+    }
 
-        pub fn is_valid_coord(coord: Coordinate) -> bool {
-            let (x, y) = coord.xy();
-            x < WIDTH as u8 && y < HEIGHT as u8
+    impl Default for Board {
+        fn default() -> Self {
+            Self::new()
         }
     }
 }
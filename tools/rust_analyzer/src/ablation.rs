@@ -0,0 +1,79 @@
+use std::fmt;
+
+use crate::state::{PieceKind, State};
+
+// pieces that may be taken off, the Core never is since the side could not touch
+const LETTERS: [(char, PieceKind); 4] = [('M', PieceKind::Monarch), ('L', PieceKind::BruteL), ('R', PieceKind::BruteR), ('T', PieceKind::Tank)];
+
+// Pieces one side starts without, written as their letters like "LR" for
+// both Brutes, or "none" for the full set as a control.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handicap(Vec<PieceKind>);
+
+impl Handicap {
+    pub fn parse(text: &str) -> Result<Handicap, String> {
+        if text == "none" { return Ok(Handicap(Vec::new())) }
+        let mut kinds = Vec::new();
+        for ch in text.chars() {
+            let &(_, kind) = LETTERS.iter().find(|(l, _)| *l == ch.to_ascii_uppercase()).ok_or_else(|| format!("unknown piece {:?}, expected letters from MLRT", ch))?;
+            if kinds.contains(&kind) { return Err(format!("{:?} appears twice in {:?}", ch, text)); }
+            kinds.push(kind);
+        }
+        if kinds.is_empty() { return Err("empty handicap, use none for the full set".into()) }
+        Ok(Handicap(kinds))
+    }
+
+    // the start position with `owner`'s handicapped pieces removed
+    pub fn apply(&self, start: &State, owner: char) -> State {
+        let mut s = start.clone();
+        for sq in s.board.iter_mut() {
+            if sq.is_some_and(|p| p.owner == owner && self.0.contains(&p.kind)) { *sq = None; }
+        }
+        s
+    }
+}
+
+impl fmt::Display for Handicap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() { return write!(f, "none") }
+        for &(letter, kind) in &LETTERS {
+            if self.0.contains(&kind) { write!(f, "{}", letter)?; }
+        }
+        Ok(())
+    }
+}
+
+// games of the handicapped side against the full set
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Record {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Record {
+    pub fn games(&self) -> usize { self.wins + self.losses + self.draws }
+
+    // the handicapped side's score with draws as half, 0.5 means the pieces are worth nothing
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games().max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handicaps_remove_one_side_only() {
+        let h = Handicap::parse("rl").unwrap();
+        assert_eq!(h.to_string(), "LR");
+        let s = h.apply(&State::default(), 'B');
+        assert_eq!(s.board.iter().flatten().filter(|p| p.owner == 'A').count(), 5);
+        assert_eq!(s.board.iter().flatten().filter(|p| p.owner == 'B').count(), 3);
+        assert!(s.find_piece('B', PieceKind::BruteL).is_none() && s.find_piece('B', PieceKind::Tank).is_some());
+        assert_eq!(Handicap::parse("none").unwrap().apply(&State::default(), 'A').board, State::default().board);
+        assert!(Handicap::parse("C").is_err());
+        assert!(Handicap::parse("TT").is_err());
+    }
+}
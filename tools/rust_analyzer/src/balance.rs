@@ -0,0 +1,166 @@
+use crate::solver::Variant;
+use crate::state::Dash;
+
+// Wilson score interval for `wins` out of `n` at normal quantile `z`. Wins
+// may be fractional, a score counting draws as half a win over n games.
+pub fn wilson(wins: f64, n: usize, z: f64) -> (f64, f64) {
+    if n == 0 { return (0.0, 1.0) }
+    let (n, p) = (n as f64, wins / n as f64);
+    let centre = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+    let half = z / (1.0 + z * z / n) * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+    ((centre - half).max(0.0), (centre + half).min(1.0))
+}
+
+// The three middle pieces of a back row with the Monarch next to the Core.
+// "TCM" and "TMC" are these two mirrored, Brutes move alike so the mirrored
+// back row plays the same game.
+pub const CENTRES: [&str; 2] = ["MCT", "CMT"];
+
+// where the Brutes start on the back row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BruteFiles {
+    // on the outer files with the centre pieces in the middle, the full game's back row
+    Corners,
+    // next to the centre pieces in the middle
+    Beside,
+    // the centre pieces on any files that leave room for a Brute on either
+    // side, Brute L anywhere to their left and Brute R anywhere to their right
+    Any,
+}
+
+impl BruteFiles {
+    pub const NAMES: [&'static str; 3] = ["corners", "beside", "any"];
+
+    pub fn by_name(name: &str) -> Option<BruteFiles> {
+        match name { "corners" => Some(BruteFiles::Corners), "beside" => Some(BruteFiles::Beside), "any" => Some(BruteFiles::Any), _ => None }
+    }
+
+    // (Brute L, first centre file, Brute R) on a back row of `cols` files
+    fn placements(self, cols: usize) -> Vec<(usize, usize, usize)> {
+        if cols < 5 { return Vec::new() }
+        let middle = (cols - 3) / 2;
+        match self {
+            BruteFiles::Corners => vec![(0, middle, cols - 1)],
+            BruteFiles::Beside => vec![(middle - 1, middle, middle + 3)],
+            BruteFiles::Any => (1..=cols - 4).flat_map(|first| (0..first).flat_map(move |left| (first + 3..cols).map(move |right| (left, first, right)))).collect(),
+        }
+    }
+}
+
+// Every combination of board size, back row, Core range and dash rule, each
+// variant once even where Brute placements coincide.
+pub fn candidates(sizes: &[(usize, usize)], core_steps: &[u8], dashes: &[Dash], brute_files: &[BruteFiles]) -> Vec<Variant> {
+    let mut out: Vec<Variant> = Vec::new();
+    for &(rows, cols) in sizes {
+        for centre in CENTRES {
+            for (left, first, right) in brute_files.iter().flat_map(|b| b.placements(cols)) {
+                let mut row = vec!['.'; cols];
+                row[left] = 'L';
+                row[right] = 'R';
+                for (i, ch) in centre.chars().enumerate() { row[first + i] = ch; }
+                let row: String = row.into_iter().collect();
+                for &core in core_steps {
+                    for dash in dashes {
+                        if let Ok(v) = Variant::parse(&format!("{}x{}:{},core={},dash={}", rows, cols, row, core, dash.name())) {
+                            if !out.contains(&v) { out.push(v); }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+// self-play results for one variant, accumulated over the rounds
+#[derive(Clone, Debug)]
+pub struct Tally {
+    pub variant: Variant,
+    pub first: usize,
+    pub second: usize,
+    pub draws: usize,
+}
+
+impl Tally {
+    pub fn new(variant: Variant) -> Tally { Tally { variant, first: 0, second: 0, draws: 0 } }
+
+    pub fn games(&self) -> usize { self.first + self.second + self.draws }
+
+    pub fn decisive(&self) -> usize { self.first + self.second }
+
+    // first player's share of the decisive games
+    pub fn first_rate(&self) -> Option<f64> {
+        (self.decisive() > 0).then(|| self.first as f64 / self.decisive() as f64)
+    }
+
+    // how far from even, variants without a decisive game last
+    pub fn imbalance(&self) -> f64 {
+        self.first_rate().map_or(f64::INFINITY, |r| (r - 0.5).abs())
+    }
+
+    // whether an even first-player rate is still inside the interval at `z`
+    pub fn could_be_even(&self, z: f64) -> bool {
+        let (lo, hi) = wilson(self.first as f64, self.decisive(), z);
+        lo <= 0.5 && 0.5 <= hi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::{mcts_action, MctsConfig};
+    use rand::SeedableRng;
+
+    #[test]
+    fn wilson_interval_brackets_the_rate() {
+        let (lo, hi) = wilson(50.0, 100, 1.96);
+        assert!((lo - 0.404).abs() < 1e-3 && (hi - 0.596).abs() < 1e-3, "{} {}", lo, hi);
+        let (lo, hi) = wilson(0.0, 10, 1.96);
+        assert_eq!(lo, 0.0);
+        assert!(hi > 0.2 && hi < 0.35);
+        assert_eq!(wilson(0.0, 0, 1.96), (0.0, 1.0));
+        // 4 wins and 2 draws in 10 games are a score of 5 over 10 games, not 10 over 20
+        let width = |(lo, hi): (f64, f64)| hi - lo;
+        assert!(width(wilson(5.0, 10, 1.96)) > 1.3 * width(wilson(10.0, 20, 1.96)));
+    }
+
+    #[test]
+    fn grid_covers_every_rule() {
+        // 5 files fit one back row per centre, 7 files fit 1*3 + 2*2 + 3*1
+        let dashes = [Dash::Off, Dash::NonCore, Dash::EnemyNonCore];
+        let variants = candidates(&[(5, 5), (5, 7)], &[1, 2], &dashes, &[BruteFiles::Corners, BruteFiles::Any]);
+        assert_eq!(variants.len(), (1 + 10) * CENTRES.len() * 2 * Dash::NAMES.len());
+        assert!(variants.iter().any(|v| v.to_string() == "5x5:LMCTR"));
+        assert!(variants.iter().any(|v| v.to_string() == "5x7:..LCMTR,core=1,dash=off"));
+    }
+
+    #[test]
+    fn brute_files_restrict_the_back_row() {
+        let rows = |b| candidates(&[(7, 11)], &[2], &[Dash::NonCore], &[b]).iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(rows(BruteFiles::Corners), ["7x11:L...MCT...R", "7x11:L...CMT...R"]);
+        assert_eq!(rows(BruteFiles::Beside), ["7x11:...LMCTR...", "7x11:...LCMTR..."]);
+        // the full game's rules and one change to each of them by default
+        let sizes = [(7, 11), (7, 9)];
+        assert_eq!(candidates(&sizes, &[2, 1], &[Dash::NonCore, Dash::Off], &[BruteFiles::Corners, BruteFiles::Beside]).len(), 32);
+    }
+
+    #[test]
+    fn even_until_the_interval_says_otherwise() {
+        let mut t = Tally::new(Variant::parse("5x5:LMCTR").unwrap());
+        assert!(t.could_be_even(1.96));
+        (t.first, t.second) = (6, 4);
+        assert!(t.could_be_even(1.96));
+        (t.first, t.second) = (60, 40);
+        assert!(!t.could_be_even(1.96));
+    }
+
+    #[test]
+    fn variants_play_through_mcts() {
+        // A's Core two files from B's on a 3x3 board with the Monarch beside it
+        let s = Variant::parse("3x3:CM.").unwrap().state().unwrap();
+        let cfg = MctsConfig { iterations: 200, playout_max: 20, ..MctsConfig::default() };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+        let m = mcts_action(&s, &cfg, &mut rng).best.unwrap();
+        assert_eq!(s.apply_move(&m).is_terminal(), Some('A'));
+    }
+}
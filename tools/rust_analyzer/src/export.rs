@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::net::{Sample, ACTIONS, INPUTS};
+
+// Training positions, merged by position: the same inputs (the board from the
+// side to move, so colour-swapped positions coincide) seen again add to the
+// count and their targets are averaged.
+//
+// NumPy output is a directory of .npy files with one row per position:
+//   inputs.npy        uint8   [N, 772]  the net::features encoding as 0/1
+//   legal.npy         uint8   [N, 385]  1 for the action of every legal move
+//   policy.npy        float32 [N, 385]  share of root visits per action
+//   search_value.npy  float32 [N]       MCTS value for the side to move
+//   outcome.npy       float32 [N]       final result for the side to move (1 win, 0 loss, draw value)
+//   count.npy         uint32  [N]       times the position was seen
+// CSV output has the same columns with the sparse ones as space-separated
+// "index" or "index:value" lists.
+pub struct Dataset {
+    index: HashMap<Vec<u16>, usize>,
+    rows: Vec<Row>,
+    added: usize,
+}
+
+struct Row {
+    features: Vec<u16>,
+    // summed over every occurrence until written
+    policy: HashMap<u16, f32>,
+    search_value: f32,
+    outcome: f32,
+    count: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Npy,
+    Csv,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 2] = ["npy", "csv"];
+
+    pub fn by_name(name: &str) -> Option<Format> {
+        match name { "npy" => Some(Format::Npy), "csv" => Some(Format::Csv), _ => None }
+    }
+}
+
+impl Dataset {
+    pub fn new() -> Dataset { Dataset { index: HashMap::new(), rows: Vec::new(), added: 0 } }
+
+    pub fn len(&self) -> usize { self.rows.len() }
+
+    // samples added, duplicates included
+    pub fn added(&self) -> usize { self.added }
+
+    pub fn add(&mut self, sample: &Sample) {
+        self.added += 1;
+        let mut key = sample.features.clone();
+        key.sort_unstable();
+        let next = self.rows.len();
+        let i = *self.index.entry(key.clone()).or_insert(next);
+        if i == next {
+            self.rows.push(Row { features: key, policy: HashMap::new(), search_value: 0.0, outcome: 0.0, count: 0 });
+        }
+        let row = &mut self.rows[i];
+        for (&a, &p) in sample.actions.iter().zip(&sample.policy) { *row.policy.entry(a).or_insert(0.0) += p; }
+        row.search_value += sample.search_value;
+        row.outcome += sample.value;
+        row.count += 1;
+    }
+
+    pub fn write(&self, path: &Path, format: Format) -> io::Result<()> {
+        match format { Format::Npy => self.write_npy(path), Format::Csv => self.write_csv(path) }
+    }
+
+    fn sorted_policy(row: &Row) -> Vec<(u16, f32)> {
+        let mut policy: Vec<(u16, f32)> = row.policy.iter().map(|(&a, &p)| (a, p / row.count as f32)).collect();
+        policy.sort_unstable_by_key(|&(a, _)| a);
+        policy
+    }
+
+    fn write_npy(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let n = self.rows.len();
+        let mut inputs = vec![0u8; n * INPUTS];
+        let mut legal = vec![0u8; n * ACTIONS];
+        let mut policy = vec![0f32; n * ACTIONS];
+        for (i, row) in self.rows.iter().enumerate() {
+            for &f in &row.features { inputs[i * INPUTS + f as usize] = 1; }
+            for (a, p) in Self::sorted_policy(row) {
+                legal[i * ACTIONS + a as usize] = 1;
+                policy[i * ACTIONS + a as usize] = p;
+            }
+        }
+        let mean = |f: fn(&Row) -> f32| self.rows.iter().map(|r| f(r) / r.count as f32).collect::<Vec<f32>>();
+        write_npy(&dir.join("inputs.npy"), "|u1", &[n, INPUTS], &inputs)?;
+        write_npy(&dir.join("legal.npy"), "|u1", &[n, ACTIONS], &legal)?;
+        write_npy(&dir.join("policy.npy"), "<f4", &[n, ACTIONS], &le_bytes(&policy, f32::to_le_bytes))?;
+        write_npy(&dir.join("search_value.npy"), "<f4", &[n], &le_bytes(&mean(|r| r.search_value), f32::to_le_bytes))?;
+        write_npy(&dir.join("outcome.npy"), "<f4", &[n], &le_bytes(&mean(|r| r.outcome), f32::to_le_bytes))?;
+        let counts: Vec<u32> = self.rows.iter().map(|r| r.count).collect();
+        write_npy(&dir.join("count.npy"), "<u4", &[n], &le_bytes(&counts, u32::to_le_bytes))
+    }
+
+    fn write_csv(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "inputs,policy,search_value,outcome,count")?;
+        for row in &self.rows {
+            let inputs: Vec<String> = row.features.iter().map(|f| f.to_string()).collect();
+            let policy: Vec<String> = Self::sorted_policy(row).iter().map(|(a, p)| format!("{}:{:.4}", a, p)).collect();
+            let c = row.count as f32;
+            writeln!(out, "{},{},{:.4},{:.4},{}", inputs.join(" "), policy.join(" "), row.search_value / c, row.outcome / c, row.count)?;
+        }
+        out.flush()
+    }
+}
+
+fn le_bytes<T: Copy, const N: usize>(values: &[T], to_bytes: fn(T) -> [u8; N]) -> Vec<u8> {
+    values.iter().flat_map(|&v| to_bytes(v)).collect()
+}
+
+// NumPy format 1.0: magic, version, header length, then a Python dict literal
+// padded with spaces so the data starts on a 64-byte boundary
+fn write_npy(path: &Path, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+    let shape = match shape { [n] => format!("({},)", n), _ => format!("({})", shape.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")) };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    while (10 + header.len() + 1) % 64 != 0 { header.push(' '); }
+    header.push('\n');
+    let mut out = Vec::with_capacity(10 + header.len() + data.len());
+    out.extend_from_slice(b"\x93NUMPY\x01\x00");
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(data);
+    fs::write(path, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    fn sample(value: f32) -> Sample {
+        let state = State::default();
+        let visits: Vec<_> = state.legal_moves().into_iter().zip([3, 1].into_iter().chain(std::iter::repeat(0))).collect();
+        Sample::new(&state, &visits, 0.5, value)
+    }
+
+    #[test]
+    fn duplicates_are_merged() {
+        let mut data = Dataset::new();
+        data.add(&sample(1.0));
+        data.add(&sample(0.0));
+        data.add(&sample(1.0).mirrored().mirrored());
+        // the Monarch and Tank flank the Core unevenly, so the mirror image is a new position
+        data.add(&sample(1.0).mirrored());
+        assert_eq!((data.added(), data.len()), (4, 2));
+        let row = &data.rows[0];
+        assert_eq!(row.count, 3);
+        assert!((row.outcome / 3.0 - 2.0 / 3.0).abs() < 1e-6);
+        let policy = Dataset::sorted_policy(row);
+        assert!((policy.iter().map(|&(_, p)| p).sum::<f32>() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn npy_headers_are_aligned() {
+        let mut data = Dataset::new();
+        data.add(&sample(1.0));
+        let dir = std::env::temp_dir().join(format!("cb-export-test-{}", std::process::id()));
+        data.write(&dir, Format::Npy).unwrap();
+        let bytes = fs::read(dir.join("policy.npy")).unwrap();
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(std::str::from_utf8(&bytes[10..10 + header_len]).unwrap().contains("'shape': (1, 385)"));
+        assert_eq!(bytes.len(), 10 + header_len + 4 * ACTIONS);
+        let outcome = fs::read(dir.join("outcome.npy")).unwrap();
+        assert!(outcome.ends_with(&1.0f32.to_le_bytes()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::mcts::{MctsConfig, Proof, SearchResult};
+use crate::state::{idx, Move, MoveKind, Piece, PieceKind, Ruleset, State, COLS, ROWS};
+
+// A game record file holds one block per game:
+//
+//   game self-play 1
+//   seed 42
+//   config A iterations=200 c=1.4 playout=Uniform playout_max=100 draw_value=0.5/0.5 rave=off tablebases=no network=no
+//   config B ...
+//   start A - L...MCT...R/.........../.../l...mct...r
+//   move 5 17 - visits=14/200 value=0.4950 proof=unknown ms=11.3
+//   ...
+//   result B
+//   end
+//
+// Moves are the kind, actor, destination and captured square with - for
+// none, squares are row * 11 + column. The start is the side to move, the
+// last Core mover and the board from row 0 with A upper case.
+
+// A move as played, with the search that picked it
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedMove {
+    pub mv: Move,
+    pub visits: u32,
+    pub iterations: u32,
+    pub value: f64,
+    pub proof: Proof,
+    pub millis: f64,
+}
+
+impl LoggedMove {
+    pub fn new(mv: Move, result: &SearchResult, millis: f64) -> LoggedMove {
+        let visits = result.visits.iter().find(|(m, _)| *m == mv).map_or(0, |&(_, n)| n);
+        LoggedMove { mv, visits, iterations: result.tree.iterations as u32, value: result.value, proof: result.proof, millis }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameLog {
+    pub label: String,
+    pub seed: u64,
+    // the settings for A and B
+    pub configs: [String; 2],
+    pub start: State,
+    pub moves: Vec<LoggedMove>,
+    pub winner: Option<char>,
+}
+
+pub fn describe_config(cfg: &MctsConfig) -> String {
+    let yes = |b: bool| if b { "yes" } else { "no" };
+    format!("iterations={} c={} playout={:?} playout_max={} draw_value={}/{} rave={} tablebases={} network={}",
+        cfg.iterations, cfg.c, cfg.playout, cfg.playout_max, cfg.draw_value[0], cfg.draw_value[1],
+        cfg.rave.map_or("off".to_string(), |k| k.to_string()), yes(cfg.tablebases.is_some()), yes(cfg.network.is_some()))
+}
+
+const LETTERS: [(char, PieceKind); 5] = [('C', PieceKind::Core), ('M', PieceKind::Monarch), ('L', PieceKind::BruteL), ('R', PieceKind::BruteR), ('T', PieceKind::Tank)];
+
+fn piece_char(p: &Option<Piece>) -> char {
+    match p {
+        None => '.',
+        Some(p) => {
+            let letter = LETTERS.iter().find(|(_, k)| *k == p.kind).unwrap().0;
+            if p.owner == 'A' { letter } else { letter.to_ascii_lowercase() }
+        }
+    }
+}
+
+fn side_char(c: Option<char>) -> String { c.map_or("-".to_string(), String::from) }
+
+fn parse_side(text: &str) -> Result<Option<char>, String> {
+    match text {
+        "A" => Ok(Some('A')),
+        "B" => Ok(Some('B')),
+        "-" => Ok(None),
+        other => Err(format!("expected A, B or -, got {:?}", other)),
+    }
+}
+
+pub fn position_text(s: &State) -> String {
+    let rows: Vec<String> = (0..ROWS).map(|r| (0..COLS).map(|c| piece_char(&s.board[idx(r, c)])).collect()).collect();
+    format!("{} {} {}", s.turn, side_char(s.last_core_moved_by), rows.join("/"))
+}
+
+pub fn parse_position(text: &str) -> Result<State, String> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let [turn, last, board] = parts[..] else { return Err(format!("expected \"TURN LAST BOARD\", got {:?}", text)) };
+    let turn = parse_side(turn)?.ok_or("no side to move")?;
+    let mut s = State { board: [None; ROWS * COLS], turn, last_core_moved_by: parse_side(last)?, rules: Ruleset::default() };
+    let rows: Vec<&str> = board.split('/').collect();
+    if rows.len() != ROWS || rows.iter().any(|r| r.chars().count() != COLS) { return Err(format!("the board needs {} rows of {} squares", ROWS, COLS)) }
+    for (r, row) in rows.iter().enumerate() {
+        for (c, ch) in row.chars().enumerate() {
+            if ch == '.' { continue }
+            let &(_, kind) = LETTERS.iter().find(|(l, _)| *l == ch.to_ascii_uppercase()).ok_or_else(|| format!("unknown piece {:?}", ch))?;
+            s.board[idx(r, c)] = Some(Piece { owner: if ch.is_ascii_uppercase() { 'A' } else { 'B' }, kind });
+        }
+    }
+    Ok(s)
+}
+
+// the board with row 6 on top, as B sees it from across the table
+pub fn board_lines(s: &State) -> Vec<String> {
+    (0..ROWS).rev().map(|r| format!("{} {}", r, (0..COLS).map(|c| piece_char(&s.board[idx(r, c)])).collect::<String>())).collect()
+}
+
+fn square_text(sq: Option<u8>) -> String { sq.map_or("-".to_string(), |s| s.to_string()) }
+
+pub fn move_text(mv: &Move) -> String {
+    format!("{} {} {} {}", mv.kind.name(), mv.actor, square_text(mv.dest), square_text(mv.captured))
+}
+
+// moves of a line one after the other, as in puzzle solutions
+pub fn line_text(moves: &[Move]) -> String {
+    moves.iter().map(move_text).collect::<Vec<_>>().join(", ")
+}
+
+fn proof_text(p: Proof) -> String {
+    match p { Proof::Unknown => "unknown".to_string(), Proof::Win(n) => format!("win:{}", n), Proof::Loss(n) => format!("loss:{}", n) }
+}
+
+fn parse_proof(text: &str) -> Result<Proof, String> {
+    let bad = || format!("bad proof {:?}", text);
+    match text.split_once(':') {
+        None if text == "unknown" => Ok(Proof::Unknown),
+        Some(("win", n)) => Ok(Proof::Win(n.parse().map_err(|_| bad())?)),
+        Some(("loss", n)) => Ok(Proof::Loss(n.parse().map_err(|_| bad())?)),
+        _ => Err(bad()),
+    }
+}
+
+fn parse_move(text: &str) -> Result<LoggedMove, String> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let [kind, actor, dest, captured, stats @ ..] = &parts[..] else { return Err(format!("expected \"KIND ACTOR DEST CAPTURED STATS\", got {:?}", text)) };
+    let kind = [MoveKind::Move, MoveKind::BruteCapture, MoveKind::Dash].into_iter().find(|k| k.name() == *kind).ok_or_else(|| format!("unknown move kind {:?}", kind))?;
+    let square = |t: &str| if t == "-" { Ok(None) } else { t.parse::<u8>().map(Some).map_err(|_| format!("bad square {:?}", t)) };
+    let mv = Move { actor: actor.parse().map_err(|_| format!("bad square {:?}", actor))?, kind, dest: square(dest)?, captured: square(captured)? };
+    let mut logged = LoggedMove { mv, visits: 0, iterations: 0, value: 0.0, proof: Proof::Unknown, millis: 0.0 };
+    for stat in stats {
+        let (key, value) = stat.split_once('=').ok_or_else(|| format!("bad search stat {:?}", stat))?;
+        let bad = || format!("bad {} {:?}", key, value);
+        match key {
+            "visits" => {
+                let (n, total) = value.split_once('/').ok_or_else(bad)?;
+                logged.visits = n.parse().map_err(|_| bad())?;
+                logged.iterations = total.parse().map_err(|_| bad())?;
+            }
+            "value" => logged.value = value.parse().map_err(|_| bad())?,
+            "proof" => logged.proof = parse_proof(value)?,
+            "ms" => logged.millis = value.parse().map_err(|_| bad())?,
+            _ => return Err(format!("unknown search stat {:?}", key)),
+        }
+    }
+    Ok(logged)
+}
+
+impl GameLog {
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "game {}", self.label)?;
+        writeln!(out, "seed {}", self.seed)?;
+        writeln!(out, "config A {}", self.configs[0])?;
+        writeln!(out, "config B {}", self.configs[1])?;
+        writeln!(out, "start {}", position_text(&self.start))?;
+        for m in &self.moves {
+            writeln!(out, "{} visits={}/{} value={:.4} proof={} ms={:.1}", move_text(&m.mv), m.visits, m.iterations, m.value, proof_text(m.proof), m.millis)?;
+        }
+        writeln!(out, "result {}", side_char(self.winner))?;
+        writeln!(out, "end")
+    }
+
+    pub fn read_all(text: &str) -> Result<Vec<GameLog>, String> {
+        let mut games = Vec::new();
+        let mut current: Option<GameLog> = None;
+        for (i, line) in text.lines().enumerate() {
+            let at = |e: String| format!("line {}: {}", i + 1, e);
+            let line = line.trim();
+            if line.is_empty() { continue }
+            let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match (key, current.as_mut()) {
+                ("game", None) => current = Some(GameLog { label: rest.to_string(), seed: 0, configs: [String::new(), String::new()], start: State::default(), moves: Vec::new(), winner: None }),
+                ("game", Some(_)) => return Err(at("game before the previous one ended".into())),
+                (_, None) => return Err(at(format!("{:?} outside a game", key))),
+                ("seed", Some(g)) => g.seed = rest.parse().map_err(|_| at(format!("bad seed {:?}", rest)))?,
+                ("config", Some(g)) => match rest.split_once(' ') {
+                    Some(("A", cfg)) => g.configs[0] = cfg.to_string(),
+                    Some(("B", cfg)) => g.configs[1] = cfg.to_string(),
+                    _ => return Err(at("expected config A or config B".into())),
+                },
+                ("start", Some(g)) => g.start = parse_position(rest).map_err(at)?,
+                ("result", Some(g)) => g.winner = parse_side(rest).map_err(at)?,
+                ("end", Some(_)) => games.push(current.take().unwrap()),
+                (_, Some(g)) => g.moves.push(parse_move(line).map_err(at)?),
+            }
+        }
+        if current.is_some() { return Err("the last game has no end line".into()) }
+        Ok(games)
+    }
+
+    pub fn read_file(path: &Path) -> Result<Vec<GameLog>, String> {
+        GameLog::read_all(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+    }
+
+    // Replays the moves from the start, each must be legal and the game may
+    // only end at the last one, with the recorded result. `step` sees every
+    // position after a move. Returns the final position.
+    pub fn verify(&self, mut step: impl FnMut(usize, &LoggedMove, &State)) -> Result<State, String> {
+        let mut s = self.start.clone();
+        for (i, m) in self.moves.iter().enumerate() {
+            if let Some(w) = s.is_terminal() { return Err(format!("ply {}: the game was already won by {}", i + 1, w)) }
+            if !s.legal_moves().contains(&m.mv) { return Err(format!("ply {}: {} is not legal for {}", i + 1, move_text(&m.mv), s.turn)) }
+            s = s.apply_move(&m.mv);
+            step(i, m, &s);
+        }
+        match (s.is_terminal(), self.winner) {
+            (w, recorded) if w == recorded => Ok(s),
+            (w, recorded) => Err(format!("the moves end in {} but the record says {}", side_char(w), side_char(recorded))),
+        }
+    }
+}
+
+// --record output, each game is flushed once played so an interrupted run
+// keeps every finished game
+pub struct Recorder {
+    out: io::BufWriter<fs::File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        Ok(Recorder { out: io::BufWriter::new(fs::File::create(path)?) })
+    }
+
+    pub fn write(&mut self, game: &GameLog) -> io::Result<()> {
+        game.write(&mut self.out)?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn random_game(seed: u64) -> GameLog {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let start = State { last_core_moved_by: Some('B'), ..State::default() };
+        let mut s = start.clone();
+        let mut moves = Vec::new();
+        while s.is_terminal().is_none() && moves.len() < 60 {
+            let mv = *s.legal_moves().choose(&mut rng).unwrap();
+            moves.push(LoggedMove { mv, visits: 3, iterations: 10, value: 0.25, proof: Proof::Loss(2), millis: 1.5 });
+            s = s.apply_move(&mv);
+        }
+        GameLog { label: format!("test {}", seed), seed, configs: ["a".into(), "b c".into()], start, moves, winner: s.is_terminal() }
+    }
+
+    #[test]
+    fn games_round_trip_and_verify() {
+        let games: Vec<GameLog> = (0..5).map(random_game).collect();
+        let mut out = Vec::new();
+        for g in &games { g.write(&mut out).unwrap(); }
+        let read = GameLog::read_all(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(read.len(), games.len());
+        for (a, b) in games.iter().zip(&read) {
+            assert_eq!((&a.label, a.seed, &a.configs, &a.moves, a.winner), (&b.label, b.seed, &b.configs, &b.moves, b.winner));
+            assert_eq!(a.start.board, b.start.board);
+            assert_eq!(b.start.last_core_moved_by, Some('B'));
+            let mut plies = 0;
+            b.verify(|_, _, _| plies += 1).unwrap();
+            assert_eq!(plies, b.moves.len());
+        }
+    }
+
+    #[test]
+    fn tampered_games_fail_verification() {
+        let mut g = random_game(1);
+        g.winner = match g.winner { Some(_) => None, None => Some('A') };
+        assert!(g.verify(|_, _, _| {}).is_err());
+        let mut g = random_game(2);
+        g.moves.swap(0, 1);
+        assert!(g.verify(|_, _, _| {}).unwrap_err().starts_with("ply 1:"));
+        assert!(GameLog::read_all("game x\nseed 1\n").is_err());
+    }
+}
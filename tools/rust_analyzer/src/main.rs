@@ -1,424 +1,1104 @@
 // THIS CODE IS AI GENERATED
 
+use clap::builder::PossibleValuesParser;
 use clap::Parser;
-use rand::seq::SliceRandom;
 use rand::Rng;
 use rand::SeedableRng;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
-const ROWS: usize = 7;
-const COLS: usize = 11;
-const BOARD_SIZE: usize = ROWS * COLS;
-
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum PieceKind {
-    Core,
-    Monarch,
-    BruteL,
-    BruteR,
-    Tank,
+mod ablation;
+mod balance;
+mod export;
+mod gamelog;
+mod match_play;
+mod mcts;
+mod net;
+mod playout;
+mod puzzles;
+mod report;
+mod solver;
+mod spsa;
+mod state;
+mod sweep;
+mod tablebase;
+mod texel;
+mod touch;
+mod train;
+
+use ablation::{Handicap, Record};
+use balance::{BruteFiles, Tally};
+use export::{Dataset, Format};
+use gamelog::{GameLog, LoggedMove, Recorder};
+use match_play::{draw_outcome_by_name, MatchEquity, RoundOdds, DRAW_OUTCOME_NAMES};
+use mcts::{mcts_action, MctsConfig, Proof};
+use net::{Network, Sample};
+use playout::{EvalWeights, Playout, DEFAULT_WEIGHTS, EVAL_FEATURE_NAMES, PLAYOUT_NAMES};
+use puzzles::{Puzzle, Theme};
+use report::{GameRecord, OutputFormat, Summary};
+use rs_board::game::{DrawReason, RoundResult};
+use rs_board::r#match::{Match, MatchResult, MatchRules};
+use rs_board::types::Player;
+use solver::{Solver, Variant};
+use spsa::{Param, Spsa};
+use state::{Dash, State, COLS, ROWS};
+use tablebase::{Material, Tablebases, TbValue};
+
+// ------------ CLI ------------
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about = "Core Battle Rust Analyzer CLI")]
+struct Args {
+    /// number of MCTS iterations per move
+    #[arg(short, long, default_value_t = 1000)]
+    iters: usize,
+
+    /// random seed
+    #[arg(short, long, default_value_t = 42)]
+    seed: u64,
+
+    /// playout policy
+    #[arg(long, default_value = "uniform", value_parser = PossibleValuesParser::new(PLAYOUT_NAMES))]
+    playout: String,
+
+    /// chance of a uniform move for the epsilon-greedy playout
+    #[arg(long, default_value_t = 0.1)]
+    epsilon: f64,
+
+    /// plies before the eval-cutoff playout stops and evaluates
+    #[arg(long, default_value_t = 10)]
+    cutoff_plies: usize,
+
+    /// playout max moves
+    #[arg(long, default_value_t = 100)]
+    playout_max: usize,
+
+    /// number of self-play games to run (1 = single probe; >1 runs statistics)
+    #[arg(long, default_value_t = 1)]
+    games: usize,
+
+    /// UCT exploration constant
+    #[arg(short, long, default_value_t = 1.4)]
+    c: f64,
+
+    /// reward for a playout that ends without a winner
+    #[arg(long, default_value_t = 0.5)]
+    draw_value: f64,
+
+    /// maximum turns per game before declaring draw
+    #[arg(long, default_value_t = 1000)]
+    max_turns: usize,
+
+    /// blend RAVE (all-moves-as-first) statistics into UCT
+    #[arg(long, default_value_t = false)]
+    rave: bool,
+
+    /// RAVE equivalence parameter; larger trusts AMAF statistics for longer
+    #[arg(long, default_value_t = 500.0)]
+    rave_k: f64,
+
+    /// play the configured bot against plain UCT with the same budget, alternating colours
+    #[arg(long, default_value_t = false)]
+    vs_baseline: bool,
+
+    /// play this many first-to-N matches of the match-score-aware bot against the round-only bot
+    #[arg(long, default_value_t = 0)]
+    matches: usize,
+
+    /// points needed to win a match
+    #[arg(long, default_value_t = 3)]
+    points_to_win: u8,
+
+    /// what a drawn round is worth in a match
+    #[arg(long, default_value = "replay", value_parser = PossibleValuesParser::new(DRAW_OUTCOME_NAMES))]
+    on_draw: String,
+
+    /// self-play rounds used to estimate round outcome odds for the match equity table
+    #[arg(long, default_value_t = 20)]
+    odds_rounds: usize,
+
+    /// generate tablebases for a material set such as CM-CM (A's pieces first) and every set its captures lead to; four pieces take about a minute, five (the most) 2.6 GB of memory and an hour or more
+    #[arg(long)]
+    tb_generate: Option<String>,
+
+    /// tablebase directory, written by --tb-generate and otherwise probed during search
+    #[arg(long)]
+    tb_dir: Option<PathBuf>,
+
+    /// strongly solve a reduced variant given as ROWSxCOLS:BACKROW, e.g. 5x5:.MC.. (A's back row, mirrored for B)
+    #[arg(long)]
+    solve: Option<String>,
+
+    /// solver checkpoint file, resumed from when it exists and rewritten as the enumeration progresses
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// look for a forced Core touch within this many moves of the side to move in --position, under the analyzer's rules
+    #[arg(long)]
+    touch_in: Option<u8>,
+
+    /// position as in --record files: side to move, last Core mover or -, then the rows from A's home row with A upper case
+    #[arg(long, default_value = "A - L...MCT...R/.........../.........../.........../.........../.........../l...mct...r")]
+    position: String,
+
+    /// generate puzzles with a unique forced touch within this many moves from --games self-play games
+    #[arg(long)]
+    puzzles: Option<u8>,
+
+    /// scan the games of a --record file for --puzzles instead of playing self-play games
+    #[arg(long)]
+    puzzles_from: Option<PathBuf>,
+
+    /// file to write the puzzles to, one "<position>; <theme> in <moves>; <solution>" per line (default: stdout)
+    #[arg(long)]
+    puzzle_out: Option<PathBuf>,
+
+    /// policy/value network file to guide MCTS with (PUCT priors and leaf values instead of playouts)
+    #[arg(long)]
+    net: Option<PathBuf>,
+
+    /// run this many generations of network self-play training, starting from <net-dir>/best.cbnn if it exists
+    #[arg(long)]
+    train: Option<usize>,
+
+    /// directory for network checkpoints written by --train
+    #[arg(long, default_value = "nets")]
+    net_dir: PathBuf,
+
+    /// hidden layer size of a new network
+    #[arg(long, default_value_t = 64)]
+    hidden: usize,
+
+    /// self-play games per training generation
+    #[arg(long, default_value_t = 20)]
+    selfplay_games: usize,
+
+    /// moves at the start of each self-play game drawn by visit counts instead of picking the most visited
+    #[arg(long, default_value_t = 8)]
+    temperature_plies: usize,
+
+    /// training positions kept from recent generations
+    #[arg(long, default_value_t = 20000)]
+    buffer: usize,
+
+    #[arg(long, default_value_t = 2)]
+    epochs: usize,
+
+    #[arg(long, default_value_t = 0.01)]
+    lr: f32,
+
+    /// games between a trained network and the current best, 0 promotes every trained network without a gate
+    #[arg(long, default_value_t = 20)]
+    gate_games: usize,
+
+    /// score the trained network needs against the current best to replace it
+    #[arg(long, default_value_t = 0.55)]
+    gate_threshold: f64,
+
+    /// write the positions of --games self-play games with visit distributions, search values and outcomes here (a directory for npy)
+    #[arg(long)]
+    export: Option<PathBuf>,
+
+    #[arg(long, default_value = "npy", value_parser = PossibleValuesParser::new(Format::NAMES))]
+    export_format: String,
+
+    /// also export every position mirrored left to right, with the Brutes swapped
+    #[arg(long, default_value_t = false)]
+    mirror: bool,
+
+    /// fit the evaluation weights to the outcomes of a corpus written by --export-format csv
+    #[arg(long)]
+    tune_eval: Option<PathBuf>,
+
+    /// evaluation weights file for the eval-cutoff playout
+    #[arg(long)]
+    eval_weights: Option<PathBuf>,
+
+    /// file --tune-eval writes the tuned weights to
+    #[arg(long, default_value = "eval-weights.txt")]
+    eval_weights_out: PathBuf,
+
+    /// gradient steps for --tune-eval
+    #[arg(long, default_value_t = 1000)]
+    tune_steps: usize,
+
+    /// tune the search parameters with this many SPSA steps of self-play matches
+    #[arg(long)]
+    spsa: Option<usize>,
+
+    /// games per SPSA step, colours alternate
+    #[arg(long, default_value_t = 8)]
+    spsa_games: usize,
+
+    /// CSV file for the SPSA parameter trajectory
+    #[arg(long)]
+    spsa_log: Option<PathBuf>,
+
+    /// search parameter file of "name value" lines overriding the matching flags, --spsa starts from them
+    #[arg(long)]
+    search_config: Option<PathBuf>,
+
+    /// file --spsa writes the recommended search parameters to
+    #[arg(long, default_value = "search-config.txt")]
+    search_config_out: PathBuf,
+
+    /// search rule variants for the most even first-player win rate by self-play with the search settings below
+    #[arg(long, default_value_t = false)]
+    balance: bool,
+
+    /// board sizes for --balance as ROWSxCOLS, at most 7x11
+    #[arg(long, value_delimiter = ',', default_value = "7x11,7x9")]
+    balance_sizes: Vec<String>,
+
+    /// Core step ranges for --balance
+    #[arg(long, value_delimiter = ',', default_value = "2,1")]
+    balance_core_steps: Vec<u8>,
+
+    /// Tank dash rules for --balance
+    #[arg(long, value_delimiter = ',', default_value = "non-core,off", value_parser = PossibleValuesParser::new(Dash::NAMES))]
+    balance_dash: Vec<String>,
+
+    /// Brute files for --balance: the outer files, next to the centre pieces, or anywhere with the centre pieces anywhere too
+    #[arg(long, value_delimiter = ',', default_value = "corners,beside", value_parser = PossibleValuesParser::new(BruteFiles::NAMES))]
+    balance_brute_files: Vec<String>,
+
+    /// games per variant in the first --balance round, doubled every round
+    #[arg(long, default_value_t = 8)]
+    balance_games: usize,
+
+    /// --balance stops once this many variants or fewer are left
+    #[arg(long, default_value_t = 5)]
+    balance_keep: usize,
+
+    /// rounds --balance plays at most
+    #[arg(long, default_value_t = 5)]
+    balance_rounds: usize,
+
+    /// play --games self-play games for each handicap, one side starting without those pieces (letters from MLRT, none for a control)
+    #[arg(long, value_delimiter = ',')]
+    ablate: Vec<String>,
+
+    /// rule variant for the self-play statistics, at most 7x11 (see --solve for the syntax)
+    #[arg(long, value_parser = parse_variant)]
+    variant: Option<Variant>,
+
+    /// output of the self-play statistics: text, JSON lines (games then a summary) or CSV (games, blank line, summary); needs --games 2 or more
+    #[arg(long, default_value = "text", value_parser = PossibleValuesParser::new(OutputFormat::NAMES))]
+    format: String,
+
+    /// run the grid of configurations in a TOML experiment file, resuming from its results table
+    #[arg(long)]
+    sweep: Option<PathBuf>,
+
+    /// write every game of the statistics, --vs-baseline and --ablate runs here with its seed, settings, moves and search stats (not with --variant)
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// re-verify every game in a file written by --record against the rules
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// with --replay, step through this game (1-based) printing the board and search stats after every move
+    #[arg(long)]
+    replay_game: Option<usize>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Piece {
-    owner: char, // 'A' or 'B'
-    kind: PieceKind,
+// tablebases, evaluation weights and network named by the flags, shared by every MctsConfig made from them
+#[derive(Clone, Default)]
+struct SearchFiles {
+    tablebases: Option<Arc<Tablebases>>,
+    weights: Option<Arc<EvalWeights>>,
+    network: Option<Arc<Network>>,
 }
 
-#[derive(Clone, Debug)]
-struct Move {
-    actor: usize,
-    kind: &'static str, // "move", "brute_capture", "dash"
-    dest: Option<usize>,
-    captured: Option<usize>,
+impl Args {
+    fn match_rules(&self) -> MatchRules {
+        MatchRules { points_to_win: self.points_to_win, on_draw: draw_outcome_by_name(&self.on_draw).expect("clap checks the name"), ..MatchRules::default() }
+    }
+
+    fn mcts_config(&self, files: &SearchFiles) -> MctsConfig {
+        MctsConfig { iterations: self.iters, c: self.c, playout: Playout::by_name(&self.playout, self.epsilon, self.cutoff_plies, files.weights.clone().unwrap_or_else(|| Arc::new(DEFAULT_WEIGHTS))).expect("clap checks the name"), playout_max: self.playout_max, draw_value: [self.draw_value; 2], match_equity: None, rave: self.rave.then_some(self.rave_k), tablebases: files.tablebases.clone(), network: files.network.clone() }
+    }
 }
 
-#[derive(Clone, Debug)]
-struct State {
-    board: [Option<Piece>; BOARD_SIZE],
-    turn: char,
-    last_core_moved_by: Option<char>,
+// the numeric search flags --spsa can tune, by flag name
+const SEARCH_PARAMS: [Param; 5] = [
+    Param { name: "c", min: 0.1, max: 4.0, integer: false },
+    Param { name: "playout-max", min: 10.0, max: 400.0, integer: true },
+    Param { name: "epsilon", min: 0.0, max: 1.0, integer: false },
+    Param { name: "cutoff-plies", min: 0.0, max: 60.0, integer: true },
+    Param { name: "rave-k", min: 10.0, max: 5000.0, integer: false },
+];
+
+impl Args {
+    fn search_param(&self, name: &str) -> Option<f64> {
+        match name {
+            "c" => Some(self.c),
+            "playout-max" => Some(self.playout_max as f64),
+            "epsilon" => Some(self.epsilon),
+            "cutoff-plies" => Some(self.cutoff_plies as f64),
+            "rave-k" => Some(self.rave_k),
+            _ => None,
+        }
+    }
+
+    fn set_search_param(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "c" => self.c = value,
+            "playout-max" => self.playout_max = value as usize,
+            "epsilon" => self.epsilon = value,
+            "cutoff-plies" => self.cutoff_plies = value as usize,
+            "rave-k" => self.rave_k = value,
+            _ => return Err(format!("unknown search parameter {:?}", name)),
+        }
+        Ok(())
+    }
+
+    // the parameters that make a difference with the chosen playout and RAVE setting
+    fn tunable_params(&self) -> Vec<Param> {
+        SEARCH_PARAMS.iter().copied().filter(|p| match p.name {
+            "epsilon" => self.playout == "epsilon-greedy",
+            "cutoff-plies" => self.playout == "eval-cutoff",
+            "rave-k" => self.rave,
+            _ => true,
+        }).collect()
+    }
+
+    fn load_search_config(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        for (name, value) in playout::name_values(&text)? { self.set_search_param(name, value)?; }
+        Ok(())
+    }
+}
+
+// `cfgs` holds the settings for A and B in that order
+fn play_game(st: State, cfgs: [&MctsConfig; 2], max_turns: usize, rng: &mut impl Rng) -> Option<char> {
+    play_recorded_game(st, cfgs, max_turns, rng).0
 }
 
-impl Default for State {
-    fn default() -> Self {
-        let mut board: [Option<Piece>; BOARD_SIZE] = array_init::array_init(|_| None);
-        // Player A top row (row 0)
-        let row_a = 0;
-        board[idx(row_a, 0)] = Some(Piece { owner: 'A', kind: PieceKind::BruteL });
-        board[idx(row_a, 4)] = Some(Piece { owner: 'A', kind: PieceKind::Monarch });
-        board[idx(row_a, 5)] = Some(Piece { owner: 'A', kind: PieceKind::Core });
-        board[idx(row_a, 6)] = Some(Piece { owner: 'A', kind: PieceKind::Tank });
-        board[idx(row_a, 10)] = Some(Piece { owner: 'A', kind: PieceKind::BruteR });
-        // Player B bottom row (row 6)
-        let row_b = ROWS - 1;
-        board[idx(row_b, 0)] = Some(Piece { owner: 'B', kind: PieceKind::BruteL });
-        board[idx(row_b, 4)] = Some(Piece { owner: 'B', kind: PieceKind::Monarch });
-        board[idx(row_b, 5)] = Some(Piece { owner: 'B', kind: PieceKind::Core });
-        board[idx(row_b, 6)] = Some(Piece { owner: 'B', kind: PieceKind::Tank });
-        board[idx(row_b, 10)] = Some(Piece { owner: 'B', kind: PieceKind::BruteR });
-        State { board, turn: 'A', last_core_moved_by: None }
+// the winner, the final position and every move with the search that chose it
+fn play_recorded_game(mut st: State, cfgs: [&MctsConfig; 2], max_turns: usize, rng: &mut impl Rng) -> (Option<char>, State, Vec<LoggedMove>) {
+    let mut moves = Vec::new();
+    loop {
+        if let Some(w) = st.is_terminal() { return (Some(w), st, moves) }
+        if moves.len() >= max_turns { return (None, st, moves) }
+        let cfg = if st.turn == 'A' { cfgs[0] } else { cfgs[1] };
+        let t0 = Instant::now();
+        let result = mcts_action(&st, cfg, rng);
+        let Some(mv) = result.best else { return (None, st, moves) };
+        moves.push(LoggedMove::new(mv, &result, t0.elapsed().as_secs_f64() * 1000.0));
+        st = st.apply_move(&mv);
     }
 }
 
-fn idx(r: usize, c: usize) -> usize {
-    r * COLS + c
+// --record, None without the flag
+fn open_recorder(args: &Args) -> Option<Recorder> {
+    let path = args.record.as_ref()?;
+    Some(Recorder::create(path).unwrap_or_else(|e| { eprintln!("failed to create {}: {}", path.display(), e); std::process::exit(1) }))
 }
 
-fn rc(i: usize) -> (usize, usize) {
-    (i / COLS, i % COLS)
+// Plays one game and writes it to the recorder if there is one, `label`
+// tells the games of a run apart in the record.
+fn play_logged_game(recorder: &mut Option<Recorder>, label: String, seed: u64, start: State, cfgs: [&MctsConfig; 2], max_turns: usize) -> (Option<char>, State, usize) {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let (winner, end, moves) = play_recorded_game(start.clone(), cfgs, max_turns, &mut rng);
+    let plies = moves.len();
+    if let Some(recorder) = recorder {
+        let log = GameLog { label, seed, configs: cfgs.map(gamelog::describe_config), start, moves, winner };
+        if let Err(e) = recorder.write(&log) { eprintln!("failed to write the game record: {}", e); std::process::exit(1); }
+    }
+    (winner, end, plies)
 }
 
-impl State {
-    fn on_board_pos(r: isize, c: isize) -> bool {
-        r >= 0 && r < ROWS as isize && c >= 0 && c < COLS as isize
+fn load_search_files(args: &Args) -> SearchFiles {
+    let mut files = SearchFiles::default();
+    if let Some(dir) = &args.tb_dir {
+        match Tablebases::load(dir) {
+            Ok(tbs) => files.tablebases = Some(Arc::new(tbs)),
+            Err(e) => { eprintln!("failed to load tablebases: {}", e); std::process::exit(1); }
+        }
+    }
+    if let Some(path) = &args.eval_weights {
+        match EvalWeights::load(path) {
+            Ok(weights) => files.weights = Some(Arc::new(weights)),
+            Err(e) => { eprintln!("failed to load evaluation weights: {}", e); std::process::exit(1); }
+        }
     }
+    if let Some(path) = &args.net {
+        match Network::load(path) {
+            Ok(net) => files.network = Some(Arc::new(net)),
+            Err(e) => { eprintln!("failed to load network: {}", e); std::process::exit(1); }
+        }
+    }
+    files
+}
 
-    fn are_adjacent_pos(a: usize, b: usize) -> bool {
-        let (ar, ac) = rc(a);
-        let (br, bc) = rc(b);
-        let dr = (ar as isize - br as isize).abs();
-        let dc = (ac as isize - bc as isize).abs();
-        (dr <= 1) && (dc <= 1) && !(dr == 0 && dc == 0)
+fn main() {
+    let mut args = Args::parse();
+    // only the self-play statistics have JSON and CSV reports
+    let other_modes = [("sweep", args.sweep.is_some()), ("replay", args.replay.is_some()), ("solve", args.solve.is_some()), ("touch-in", args.touch_in.is_some()),
+        ("tb-generate", args.tb_generate.is_some()), ("tune-eval", args.tune_eval.is_some()), ("balance", args.balance), ("puzzles", args.puzzles.is_some()),
+        ("export", args.export.is_some()), ("train", args.train.is_some()), ("spsa", args.spsa.is_some()), ("ablate", !args.ablate.is_empty()),
+        ("matches", args.matches > 0), ("vs-baseline", args.vs_baseline)];
+    if let Some((mode, _)) = other_modes.iter().find(|(_, on)| *on).filter(|_| args.format != "text") {
+        eprintln!("--format {} only applies to the self-play statistics, not --{}", args.format, mode);
+        std::process::exit(2);
+    }
+    if let Some(path) = &args.sweep {
+        run_sweep(path);
+        return;
+    }
+    if let Some(path) = &args.replay {
+        replay_games(path, args.replay_game);
+        return;
     }
+    if let Some(path) = args.search_config.clone() {
+        if let Err(e) = args.load_search_config(&path) { eprintln!("failed to load {}: {}", path.display(), e); std::process::exit(1); }
+    }
+    let mut global_rng = rand::rngs::StdRng::seed_from_u64(args.seed);
 
-    fn find_piece(&self, owner: char, kind: PieceKind) -> Option<usize> {
-        for i in 0..BOARD_SIZE {
-            if let Some(p) = self.board[i] {
-                if p.owner == owner && p.kind == kind {
-                    return Some(i);
-                }
-            }
+    if let Some(variant) = &args.solve {
+        match Variant::parse(variant) {
+            Ok(variant) => solve_variant(variant, args.checkpoint.as_deref()),
+            Err(e) => { eprintln!("invalid variant {:?}: {}", variant, e); std::process::exit(2); }
         }
-        None
-    }
-
-    fn legal_moves(&self) -> Vec<Move> {
-        let mut moves: Vec<Move> = Vec::new();
-        for i in 0..BOARD_SIZE {
-            if let Some(piece) = self.board[i] {
-                if piece.owner != self.turn { continue }
-                let (r, c) = rc(i);
-                match piece.kind {
-                    PieceKind::Monarch => {
-                        let deltas = [ (0isize,-1), (0,1), (-1,-1), (-1,1), (1,-1), (1,1) ];
-                        for (dr,dc) in deltas {
-                            let nr = r as isize + dr; let nc = c as isize + dc;
-                            if !State::on_board_pos(nr,nc) { continue }
-                            let ni = idx(nr as usize, nc as usize);
-                            if self.board[ni].is_none() || self.board[ni].unwrap().owner != piece.owner {
-                                moves.push(Move{actor:i, kind:"move", dest:Some(ni), captured: if self.board[ni].is_some() {Some(ni)} else {None}});
-                            }
-                        }
-                    }
-                    PieceKind::Core => {
-                        if let Some(mon_pos) = self.find_piece(piece.owner, PieceKind::Monarch) {
-                            if !State::are_adjacent_pos(i, mon_pos) { continue }
-                        } else { continue }
-                        for dr in -1..=1 {
-                            for dc in -1..=1 {
-                                if dr==0 && dc==0 { continue }
-                                for step in 1..=2 {
-                                    let nr = r as isize + dr*step; let nc = c as isize + dc*step;
-                                    if !State::on_board_pos(nr,nc) { continue }
-                                    let ni = idx(nr as usize, nc as usize);
-                                    if step==2 {
-                                        // can hop over anything
-                                        if self.board[ni].is_none() || self.board[ni].unwrap().owner != piece.owner {
-                                            moves.push(Move{actor:i, kind:"move", dest:Some(ni), captured: if self.board[ni].is_some() {Some(ni)} else {None}});
-                                        }
-                                    } else {
-                                        if self.board[ni].is_none() || self.board[ni].unwrap().owner != piece.owner {
-                                            moves.push(Move{actor:i, kind:"move", dest:Some(ni), captured: if self.board[ni].is_some() {Some(ni)} else {None}});
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    PieceKind::BruteL | PieceKind::BruteR => {
-                        let orth = [(-1,0),(1,0),(0,-1),(0,1)];
-                        for (dr,dc) in orth {
-                            let nr = r as isize + dr; let nc = c as isize + dc;
-                            if !State::on_board_pos(nr,nc) { continue }
-                            let ni = idx(nr as usize, nc as usize);
-                            if self.board[ni].is_none() { moves.push(Move{actor:i, kind:"move", dest:Some(ni), captured:None}) }
-                        }
-                        let (fdr, fdc) = if piece.owner == 'A' { (1, 0) } else { (-1, 0) };
-                        let fr = r as isize + fdr; let fc = c as isize + fdc;
-                        if State::on_board_pos(fr, fc) {
-                            let fi = idx(fr as usize, fc as usize);
-                            if let Some(t) = self.board[fi] {
-                                if t.owner != piece.owner {
-                                    moves.push(Move{actor:i, kind:"brute_capture", dest:None, captured:Some(fi)});
-                                }
-                            }
-                        }
-                    }
-                    PieceKind::Tank => {
-                        let orth = [(-1,0),(1,0),(0,-1),(0,1)];
-                        for (dr,dc) in orth {
-                            let nr = r as isize + dr; let nc = c as isize + dc;
-                            if !State::on_board_pos(nr,nc) { continue }
-                            let ni = idx(nr as usize, nc as usize);
-                            if self.board[ni].is_none() { moves.push(Move{actor:i, kind:"move", dest:Some(ni), captured:None}) }
-                        }
-                        let (fdr, fdc) = if piece.owner == 'A' { (1, 0) } else { (-1, 0) };
-                        let fr = r as isize + fdr; let fc = c as isize + fdc;
-                        let lr = r as isize + 2*fdr; let lc = c as isize + 2*fdc;
-                        if State::on_board_pos(fr, fc) && State::on_board_pos(lr, lc) {
-                            let fi = idx(fr as usize, fc as usize);
-                            let li = idx(lr as usize, lc as usize);
-                            if let Some(mid) = self.board[fi] {
-                                if mid.kind != PieceKind::Core && self.board[li].is_none() {
-                                    moves.push(Move{actor:i, kind:"dash", dest:Some(li), captured:Some(fi)});
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        return;
+    }
+
+    if let Some(moves) = args.touch_in {
+        match gamelog::parse_position(&args.position) {
+            Ok(s) => check_touch(&s, moves),
+            Err(e) => { eprintln!("invalid position {:?}: {}", args.position, e); std::process::exit(2); }
         }
-        moves
-    }
-
-    fn apply_move(&self, m: &Move) -> State {
-        let mut s = self.clone();
-        let piece = s.board[m.actor].expect("actor must exist");
-        match m.kind {
-            "move" => {
-                if let Some(dest) = m.dest {
-                    if let Some(target) = s.board[dest] { if target.owner != piece.owner { s.board[dest] = None; } }
-                    s.board[m.actor] = None;
-                    s.board[dest] = Some(piece);
-                    if piece.kind == PieceKind::Core { s.last_core_moved_by = Some(piece.owner); }
-                }
-            }
-            "brute_capture" => {
-                if let Some(cap) = m.captured { s.board[cap] = None; }
-            }
-            "dash" => {
-                if let Some(cap) = m.captured { s.board[cap] = None; }
-                if let Some(dest) = m.dest { s.board[m.actor] = None; s.board[dest] = Some(piece); }
-            }
+        return;
+    }
+
+    if let Some(material) = &args.tb_generate {
+        let Some(material) = Material::parse(material) else {
+            eprintln!("invalid material {:?}, expected letters from CMLRT for each side like CM-CMT", material);
+            std::process::exit(2);
+        };
+        if material.count() > tablebase::MAX_PIECES { eprintln!("{} has more than {} pieces", material, tablebase::MAX_PIECES); std::process::exit(2); }
+        generate_tablebases(material, args.tb_dir.as_deref().unwrap_or(Path::new("tablebases")));
+        return;
+    }
+    if let Some(corpus) = &args.tune_eval {
+        tune_eval(&args, corpus);
+        return;
+    }
+    let files = load_search_files(&args);
+
+    if args.balance {
+        run_balance(&args, &files);
+        return;
+    }
+
+    if let Some(moves) = args.puzzles {
+        generate_puzzles(&args, &files, moves);
+        return;
+    }
+
+    if let Some(path) = &args.export {
+        export_self_play(&args, &files, path);
+        return;
+    }
+
+    if let Some(generations) = args.train {
+        run_training(&args, &files, generations);
+        return;
+    }
+
+    if let Some(iterations) = args.spsa {
+        tune_search(&args, &files, iterations);
+        return;
+    }
+
+    if !args.ablate.is_empty() {
+        run_ablation(&args, &files);
+        return;
+    }
+
+    if args.matches > 0 {
+        run_matches(&args, &files);
+        return;
+    }
+
+    if args.games <= 1 && args.variant.is_none() {
+        // the probe is a single search, not games the formats have rows for
+        if args.format != "text" { eprintln!("--format {} needs self-play statistics, run --games 2 or more", args.format); std::process::exit(2); }
+        let st = State::default();
+        let t0 = Instant::now();
+        let result = mcts_action(&st, &args.mcts_config(&files), &mut global_rng);
+        let dur = t0.elapsed();
+        let tree = result.tree;
+        if let Some(m) = result.best {
+            println!("Best move after {} iterations: actor={}, kind={}, dest={:?}, captured={:?}", tree.iterations, m.actor, m.kind.name(), m.dest, m.captured);
+        } else {
+            println!("No move found");
+        }
+        match (result.proof, result.proof_length()) {
+            (Proof::Win(_), Some(n)) => println!("Proven: forced touch in {} plies", n),
+            (Proof::Loss(_), Some(n)) => println!("Proven: opponent forces a touch in {} plies", n),
             _ => {}
         }
-        s.turn = if s.turn == 'A' { 'B' } else { 'A' };
-        s
+        println!("Elapsed: {:?}", dur);
+        println!("Tree: {} nodes, {:.1} bytes/node", tree.nodes, tree.bytes as f64 / tree.nodes as f64);
+        return;
+    }
+
+    if args.vs_baseline {
+        run_vs_baseline(&args, &files);
+        return;
+    }
+
+    let cfg = args.mcts_config(&files);
+    let format = OutputFormat::by_name(&args.format).expect("clap checks the name");
+    if format == OutputFormat::Csv { println!("{}", GameRecord::csv_header()); }
+    let mut records = Vec::with_capacity(args.games);
+    // checked before the record file is created, so no empty one is left behind
+    if args.record.is_some() && args.variant.is_some() {
+        eprintln!("--record can't be used with --variant, the record format only knows the full game");
+        std::process::exit(2);
+    }
+    let mut recorder = open_recorder(&args);
+    for g in 0..args.games {
+        let record = statistics_game(&args, &cfg, g, &mut recorder);
+        match (format, record.winner) {
+            (OutputFormat::Json, _) => println!("{}", record.json()),
+            (OutputFormat::Csv, _) => println!("{}", record.csv()),
+            (OutputFormat::Text, Some(w)) => println!("Game {}/{}: winner={}", g+1, args.games, w),
+            (OutputFormat::Text, None) => println!("Game {}/{}: draw", g+1, args.games),
+        }
+        records.push(record);
     }
 
-    fn is_terminal(&self) -> Option<char> {
-        if let (Some(a_pos), Some(b_pos)) = (self.find_piece('A', PieceKind::Core), self.find_piece('B', PieceKind::Core)) {
-            if State::are_adjacent_pos(a_pos, b_pos) {
-                return self.last_core_moved_by;
+    let summary = Summary::of(&records);
+    match format {
+        OutputFormat::Json => println!("{}", summary.json()),
+        OutputFormat::Csv => println!("\n{}\n{}", Summary::csv_header(), summary.csv()),
+        OutputFormat::Text => {
+            println!("--- Summary ({} games) ---", args.games);
+            println!("A wins: {}\nB wins: {}\nDraws: {}", summary.a_wins, summary.b_wins, summary.draws);
+            if let Some(rate) = summary.first_rate() {
+                println!("First-player win rate (A / decisive games): {:.2}", rate);
             }
         }
-        None
     }
 }
 
-// ---------------- MCTS ----------------
+// one game of the self-play statistics, on --variant if there is one (main
+// refuses --record with a variant, the record format only knows the full game)
+fn statistics_game(args: &Args, cfg: &MctsConfig, g: usize, recorder: &mut Option<Recorder>) -> GameRecord {
+    // seed each game differently for variance
+    let seed = args.seed.wrapping_add(g as u64);
+    let t0 = Instant::now();
+    let start = args.variant.as_ref().map_or_else(State::default, |v| v.state().expect("checked by parse_variant"));
+    let (winner, end, plies) = play_logged_game(recorder, format!("self-play {}", g + 1), seed, start.clone(), [cfg, cfg], args.max_turns);
+    let pieces = |s: &State| s.board.iter().flatten().copied().collect::<Vec<_>>();
+    let captured = GameRecord::losses(&pieces(&start), &pieces(&end));
+    GameRecord { game: g + 1, seed, winner, plies, captured, last_core_mover: end.last_core_moved_by, duration: t0.elapsed() }
+}
 
-struct MCTSNode {
-    state: State,
-    parent: Option<usize>,
-    children: Vec<usize>,
-    visits: u32,
-    wins: f64,
-    untried: Vec<Move>,
-    move_from_parent: Option<Move>,
+// --variant, which has to fit the analyzer's board to be played
+fn parse_variant(text: &str) -> Result<Variant, String> {
+    let variant = Variant::parse(text)?;
+    variant.state()?;
+    Ok(variant)
 }
 
-impl MCTSNode {
-    fn new(state: State, move_from_parent: Option<Move>, parent: Option<usize>) -> Self {
-        let untried = state.legal_moves();
-        MCTSNode { state, parent, children: Vec::new(), visits: 0, wins: 0.0, untried, move_from_parent }
+// challenger (the CLI config) against plain UCT; the challenger is A in even games
+fn run_vs_baseline(args: &Args, files: &SearchFiles) {
+    let challenger = args.mcts_config(files);
+    let baseline = MctsConfig { rave: None, ..challenger.clone() };
+    let (mut wins, mut losses, mut draws) = (0usize, 0usize, 0usize);
+    let t0 = Instant::now();
+    let mut recorder = open_recorder(args);
+    for g in 0..args.games {
+        let (cfgs, side) = if g % 2 == 0 { ([&challenger, &baseline], 'A') } else { ([&baseline, &challenger], 'B') };
+        let label = format!("vs-baseline {} challenger={}", g + 1, side);
+        let outcome = match play_logged_game(&mut recorder, label, args.seed.wrapping_add(g as u64), State::default(), cfgs, args.max_turns).0 {
+            Some(w) if w == side => { wins += 1; "win" }
+            Some(_) => { losses += 1; "loss" }
+            None => { draws += 1; "draw" }
+        };
+        println!("Game {}/{}: challenger as {} -> {}", g+1, args.games, side, outcome);
     }
+    let score = (wins as f64 + 0.5 * draws as f64) / args.games as f64;
+    println!("--- Challenger vs baseline ({} games, {:?}) ---", args.games, t0.elapsed());
+    println!("Wins: {}\nLosses: {}\nDraws: {}", wins, losses, draws);
+    println!("Challenger score: {:.3}", score);
 }
 
-fn chebyshev_distance(a: usize, b: usize) -> usize {
-    let (ar, ac) = rc(a);
-    let (br, bc) = rc(b);
-    let dr = if ar > br { ar - br } else { br - ar };
-    let dc = if ac > bc { ac - bc } else { bc - ac };
-    std::cmp::max(dr, dc)
+// a match that keeps drawing rounds is called off as drawn after this many
+const MAX_ROUNDS: u32 = 50;
+
+// Plays a first-to-N match between the sides of `cfgs`, scored by rs-board's
+// Match. The sides marked in `aware` score every leaf by the match equity its
+// round result leads to. Returns the match winner, None for a drawn match.
+fn play_match(cfgs: [&MctsConfig; 2], aware: [bool; 2], rules: MatchRules, equity: &MatchEquity, max_turns: usize, rng: &mut impl Rng) -> (Option<char>, [u16; 2]) {
+    let name = |p: Player| if p == Player::A { 'A' } else { 'B' };
+    let half = |m: &Match| [Player::A, Player::B].map(|p| (m.score(p) * 2.0) as u16);
+    let mut m = Match::new(rules);
+    for _ in 0..MAX_ROUNDS {
+        let first = name(m.game().turn());
+        let match_equity = Some(equity.round_values(half(&m), first));
+        let round_cfg = |i: usize| if aware[i] { MctsConfig { match_equity, ..cfgs[i].clone() } } else { cfgs[i].clone() };
+        let (cfg_a, cfg_b) = (round_cfg(0), round_cfg(1));
+        let st = State { turn: first, ..State::default() };
+        let result = match play_recorded_game(st, [&cfg_a, &cfg_b], max_turns, rng) {
+            (Some(w), _, _) => RoundResult::Touch(if w == 'A' { Player::A } else { Player::B }),
+            (None, end, _) if end.legal_moves().is_empty() => RoundResult::Draw(DrawReason::NoMoves),
+            (None, _, _) => RoundResult::Draw(DrawReason::NoProgress),
+        };
+        m.record_round(result).expect("the match isn't over");
+        match m.result() {
+            Some(MatchResult::Winner(p)) => return (Some(name(p)), half(&m)),
+            Some(MatchResult::Drawn) => return (None, half(&m)),
+            None => {}
+        }
+    }
+    (None, half(&m))
 }
 
-fn random_playout(mut s: State, mut rng: &mut impl Rng, max_moves: usize, biased: bool) -> Option<char> {
-    for _ in 0..max_moves {
-        if let Some(w) = s.is_terminal() { return Some(w); }
-        let moves = s.legal_moves();
-        if moves.is_empty() { return None; }
-        let chosen = if !biased {
-            moves.choose(&mut rng).unwrap().clone()
-        } else {
-            // bias random playout towards moves that reduce distance between cores for the current player
-            // compute weights based on resulting distance (lower distance -> higher weight)
-            let mut weights: Vec<f64> = Vec::with_capacity(moves.len());
-            for m in &moves {
-                let s2 = s.apply_move(&m);
-                // if terminal immediate, prefer it
-                if s2.is_terminal().is_some() { weights.push(100.0); continue; }
-                let a_pos = s2.find_piece('A', PieceKind::Core);
-                let b_pos = s2.find_piece('B', PieceKind::Core);
-                if a_pos.is_none() || b_pos.is_none() { weights.push(1.0); continue; }
-                let dist = chebyshev_distance(a_pos.unwrap(), b_pos.unwrap()) as f64;
-                // for player who moved next (s2.turn), we want to favor smaller distance for that player
-                // if current player is 'A', favor moves that decrease distance; otherwise similar
-                // choose weight = exp(-dist) but scaled
-                let w = (-0.5 * dist).exp();
-                weights.push(w);
-            }
-            // sample according to weights
-            let sum: f64 = weights.iter().sum();
-            let mut pick = rng.gen::<f64>() * sum;
-            let mut idx = 0usize;
-            while idx + 1 < weights.len() && pick > weights[idx] {
-                pick -= weights[idx];
-                idx += 1;
-            }
-            moves[idx].clone()
+// the match-score-aware bot against the round-only bot, the aware one is A in even matches
+fn run_matches(args: &Args, files: &SearchFiles) {
+    let cfg = args.mcts_config(files);
+    let rules = args.match_rules();
+    // rounds between round-only bots, seeded apart from the matches
+    let mut odds_rng = rand::rngs::StdRng::seed_from_u64(!args.seed);
+    let winners: Vec<Option<char>> = (0..args.odds_rounds).map(|_| play_game(State::default(), [&cfg, &cfg], args.max_turns, &mut odds_rng)).collect();
+    let odds = RoundOdds::from_rounds(&winners);
+    let equity = MatchEquity::new(rules, odds);
+    println!("Round odds over {} self-play rounds: first mover wins {:.3}, draws {:.3}", args.odds_rounds, odds.first_wins, odds.draws);
+    let lead = u16::from(rules.points_to_win.saturating_sub(1)) * 2;
+    println!("Draw value for A moving first: level {:.3}, ahead {:.3}, behind {:.3}",
+        equity.draw_values([0, 0], 'A')[0], equity.draw_values([lead, 0], 'A')[0], equity.draw_values([0, lead], 'A')[0]);
+
+    let (mut wins, mut losses, mut draws) = (0usize, 0usize, 0usize);
+    let t0 = Instant::now();
+    for g in 0..args.matches {
+        let mut game_rng = rand::rngs::StdRng::seed_from_u64(args.seed.wrapping_add(g as u64));
+        let (aware, side) = if g % 2 == 0 { ([true, false], 'A') } else { ([false, true], 'B') };
+        let (winner, half) = play_match([&cfg, &cfg], aware, rules, &equity, args.max_turns, &mut game_rng);
+        let outcome = match winner {
+            Some(w) if w == side => { wins += 1; "win" }
+            Some(_) => { losses += 1; "loss" }
+            None => { draws += 1; "draw" }
         };
-        s = s.apply_move(&chosen);
+        println!("Match {}/{}: aware bot as {} -> {} ({}-{})", g+1, args.matches, side, outcome, half[0] as f64 / 2.0, half[1] as f64 / 2.0);
     }
-    None
+    let score = (wins as f64 + 0.5 * draws as f64) / args.matches as f64;
+    println!("--- Match-aware vs round-only ({} matches, {:?}) ---", args.matches, t0.elapsed());
+    println!("Wins: {}\nLosses: {}\nDraws: {}", wins, losses, draws);
+    println!("Match-aware score: {:.3}", score);
 }
 
-fn uct_score(parent_visits: u32, child: &MCTSNode, c: f64) -> f64 {
-    if child.visits == 0 { return f64::INFINITY; }
-    (child.wins / child.visits as f64) + c * ((parent_visits as f64).ln() / child.visits as f64).sqrt()
+fn generate_tablebases(material: Material, dir: &Path) {
+    let t0 = Instant::now();
+    let mut tbs = Tablebases::new();
+    tbs.generate(material);
+    println!("Generated in {:?}", t0.elapsed());
+    for table in tbs.tables() {
+        let (wins, losses, draws, longest) = table.summary();
+        println!("{}: {} wins, {} losses, {} draws for the side to move, longest touch {} plies", table.material(), wins, losses, draws, longest);
+    }
+    if let Err(e) = tbs.save(dir) {
+        eprintln!("failed to write tablebases: {}", e);
+        std::process::exit(1);
+    }
+    println!("Written to {}", dir.display());
 }
 
-fn mcts_action(root_state: &State, iterations: usize, c: f64, rng: &mut impl Rng, biased_playout: bool, playout_max: usize) -> Option<Move> {
-    let mut nodes: Vec<MCTSNode> = Vec::new();
-    nodes.push(MCTSNode::new(root_state.clone(), None, None));
-
-    for _ in 0..iterations {
-        // selection
-        let mut node_idx = 0usize;
-        loop {
-            if !nodes[node_idx].untried.is_empty() || nodes[node_idx].children.is_empty() { break }
-            // pick child with max UCT
-            let mut best = None; let mut best_score = -1f64;
-            for &child_idx in &nodes[node_idx].children {
-                let score = uct_score(nodes[node_idx].visits, &nodes[child_idx], c);
-                if score.is_infinite() || score > best_score { best_score = score; best = Some(child_idx); }
+// positions expanded between checkpoints
+const CHECKPOINT_EVERY: usize = 1 << 20;
+
+fn solve_variant(variant: Variant, checkpoint: Option<&Path>) {
+    let t0 = Instant::now();
+    let mut solver = match checkpoint.filter(|p| p.exists()) {
+        Some(path) => {
+            let solver = Solver::load(path).unwrap_or_else(|e| { eprintln!("failed to load checkpoint: {}", e); std::process::exit(1) });
+            if *solver.variant() != variant {
+                eprintln!("checkpoint {} is for {}, not {}", path.display(), solver.variant(), variant);
+                std::process::exit(1);
             }
-            if let Some(b) = best { node_idx = b } else { break }
+            println!("Resumed from {} with {} positions", path.display(), solver.positions());
+            solver
         }
-        // expansion
-        if !nodes[node_idx].untried.is_empty() {
-            let midx = rng.gen_range(0..nodes[node_idx].untried.len());
-            let mv = nodes[node_idx].untried.remove(midx);
-            let child_state = nodes[node_idx].state.apply_move(&mv);
-            nodes.push(MCTSNode::new(child_state, Some(mv.clone()), Some(node_idx)));
-            let new_idx = nodes.len() - 1;
-            nodes[node_idx].children.push(new_idx);
-            node_idx = new_idx;
-        }
-        // simulation
-        let winner = random_playout(nodes[node_idx].state.clone(), rng, playout_max, biased_playout);
-        // backprop
-        let mut cur = Some(node_idx);
-        while let Some(ci) = cur {
-            nodes[ci].visits += 1;
-            if winner == Some(root_state.turn) { nodes[ci].wins += 1.0; }
-            else if winner.is_none() { nodes[ci].wins += 0.5; }
-            cur = nodes[ci].parent;
+        None => Solver::new(variant),
+    };
+    while !solver.is_enumerated() {
+        solver.expand(CHECKPOINT_EVERY);
+        println!("{} positions found, {:?}", solver.positions(), t0.elapsed());
+        if let Some(path) = checkpoint {
+            if let Err(e) = solver.save(path) { eprintln!("failed to write checkpoint: {}", e); }
         }
     }
+    let solution = solver.solve();
+    let (wins, losses, draws) = solution.counts();
+    let (value, moves) = solver.report(&solution);
+    println!("--- {} solved in {:?} ---", solver.variant(), t0.elapsed());
+    println!("Reachable positions: {} ({} wins, {} losses, {} draws for the side to move)", solution.positions, wins, losses, draws);
+    match value {
+        TbValue::Win(d) => println!("Value: first player wins, touch in {} plies", d),
+        TbValue::Loss(d) => println!("Value: second player wins, touch in {} plies", d),
+        TbValue::Draw => println!("Value: draw"),
+    }
+    for mv in moves { println!("Optimal first move: {}", mv); }
+}
 
-    // choose best child by visits
-    if nodes[0].children.is_empty() { return None }
-    let mut best_visits = 0u32; let mut best_move: Option<Move> = None;
-    for &ci in &nodes[0].children {
-        if nodes[ci].visits > best_visits { best_visits = nodes[ci].visits; best_move = nodes[ci].move_from_parent.clone(); }
+fn check_touch(s: &State, moves: u8) {
+    let t0 = Instant::now();
+    let found = touch::find_forced_touch(s, moves);
+    println!("--- Forced touch for {} within {} moves ({:?}) ---", s.turn, moves, t0.elapsed());
+    match found {
+        touch::ForcedTouch::Win(line) => {
+            let n = line.len().div_ceil(2);
+            println!("Touch in {}: {}", n, gamelog::line_text(&line));
+            // a puzzle needs the first move to be the only one
+            println!("Winning first moves: {}", touch::winning_moves(s, n as u8).len());
+        }
+        touch::ForcedTouch::Refuted(refutation) if refutation.is_empty() => match s.is_terminal() {
+            Some(w) => println!("No touch: the round is already over, {} touched", w),
+            None => println!("No touch: no move to search"),
+        },
+        touch::ForcedTouch::Refuted(refutation) => {
+            println!("No touch, every move is refuted:");
+            for (mv, reply) in refutation {
+                match reply {
+                    Some(reply) => println!("  {} -> {}", gamelog::move_text(&mv), gamelog::move_text(&reply)),
+                    None => println!("  {} (no touch in time)", gamelog::move_text(&mv)),
+                }
+            }
+        }
     }
-    best_move
 }
 
-// ------------ CLI ------------
+// Puzzles from the games of --puzzles-from or from --games self-play games,
+// progress goes to stderr so the puzzles can be piped from stdout.
+fn generate_puzzles(args: &Args, files: &SearchFiles, max_moves: u8) {
+    let t0 = Instant::now();
+    let logs = args.puzzles_from.as_ref().map(|path| GameLog::read_file(path).unwrap_or_else(|e| { eprintln!("invalid game record {}: {}", path.display(), e); std::process::exit(2) }));
+    let games = logs.as_ref().map_or(args.games, |l| l.len());
+    let cfg = args.mcts_config(files);
+    let mut seen = HashSet::new();
+    let mut found: Vec<Puzzle> = Vec::new();
+    for g in 0..games {
+        let (start, moves) = match &logs {
+            Some(logs) => (logs[g].start.clone(), logs[g].moves.iter().map(|m| m.mv).collect::<Vec<_>>()),
+            None => {
+                let mut game_rng = rand::rngs::StdRng::seed_from_u64(args.seed.wrapping_add(g as u64));
+                let (_, _, moves) = play_recorded_game(State::default(), [&cfg, &cfg], args.max_turns, &mut game_rng);
+                (State::default(), moves.iter().map(|m| m.mv).collect())
+            }
+        };
+        let new: Vec<Puzzle> = puzzles::puzzles_in_game(&start, &moves, max_moves).into_iter().filter(|p| seen.insert(p.position.clone())).collect();
+        eprintln!("Game {}/{}: {} moves, {} new puzzles", g+1, games, moves.len(), new.len());
+        found.extend(new);
+    }
+    // easiest first
+    found.sort_by_key(|p| p.moves);
+    let mut out: Box<dyn Write> = match &args.puzzle_out {
+        Some(path) => Box::new(std::fs::File::create(path).unwrap_or_else(|e| { eprintln!("failed to create {}: {}", path.display(), e); std::process::exit(1) })),
+        None => Box::new(std::io::stdout()),
+    };
+    for p in &found { writeln!(out, "{}", p).expect("failed to write puzzles"); }
+    eprintln!("--- {} puzzles from {} games ({:?}) ---", found.len(), games, t0.elapsed());
+    for n in 1..=max_moves {
+        let count = |theme| found.iter().filter(|p| p.moves == n && p.theme == theme).count();
+        eprintln!("Touch in {}: {} touch, {} tank dash", n, count(Theme::Touch), count(Theme::TankDash));
+    }
+}
 
-#[derive(Parser, Debug)]
-#[command(author, version, about = "Core Battle Rust Analyzer CLI")]
-struct Args {
-    /// number of MCTS iterations per move
-    #[arg(short, long, default_value_t = 1000)]
-    iters: usize,
+fn run_training(args: &Args, files: &SearchFiles, generations: usize) {
+    let t0 = Instant::now();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(args.seed);
+    let best_path = args.net_dir.join("best.cbnn");
+    let mut best = Arc::new(if best_path.exists() {
+        Network::load(&best_path).unwrap_or_else(|e| { eprintln!("failed to load network: {}", e); std::process::exit(1) })
+    } else {
+        let net = Network::new(args.hidden, &mut rng);
+        if let Err(e) = net.save(&best_path) { eprintln!("failed to write {}: {}", best_path.display(), e); }
+        net
+    });
+    println!("Training {:?} for {} generations, checkpoints in {}", best, generations, args.net_dir.display());
+    let mut buffer: std::collections::VecDeque<Sample> = std::collections::VecDeque::new();
+    for gen in 1..=generations {
+        let cfg = MctsConfig { network: Some(best.clone()), ..args.mcts_config(files) };
+        let mut results = [0usize; 3];
+        for _ in 0..args.selfplay_games {
+            let (samples, winner) = train::self_play_game(&cfg, args.max_turns, args.temperature_plies, &mut rng);
+            results[match winner { Some('A') => 0, Some(_) => 1, None => 2 }] += 1;
+            buffer.extend(samples);
+        }
+        while buffer.len() > args.buffer { buffer.pop_front(); }
+        let mut candidate = Network::clone(&best);
+        let samples: Vec<Sample> = buffer.iter().cloned().collect();
+        let (policy_loss, value_loss) = train::train(&mut candidate, &samples, args.epochs, args.lr, &mut rng);
+        let candidate = Arc::new(candidate);
+        let challenger = MctsConfig { network: Some(candidate.clone()), ..cfg.clone() };
+        let (mut wins, mut draws) = (0usize, 0usize);
+        for g in 0..args.gate_games {
+            // alternate colours so the first-move advantage cancels out
+            let (cfgs, side) = if g % 2 == 0 { ([&challenger, &cfg], 'A') } else { ([&cfg, &challenger], 'B') };
+            match play_game(State::default(), cfgs, args.max_turns, &mut rng) {
+                Some(w) if w == side => wins += 1,
+                None => draws += 1,
+                Some(_) => {}
+            }
+        }
+        let score = (args.gate_games > 0).then(|| (wins as f64 + 0.5 * draws as f64) / args.gate_games as f64);
+        let promoted = score.is_none_or(|s| s >= args.gate_threshold);
+        let gate = score.map_or("no gate".to_string(), |s| format!("gate {:.3}", s));
+        println!("Generation {}: self-play A {} / B {} / draws {}, {} positions, loss policy {:.3} value {:.3}, {}{} ({:?})",
+            gen, results[0], results[1], results[2], samples.len(), policy_loss, value_loss, gate, if promoted { " -> promoted" } else { "" }, t0.elapsed());
+        let save = |net: &Network, path: &Path| if let Err(e) = net.save(path) { eprintln!("failed to write {}: {}", path.display(), e); };
+        save(&candidate, &args.net_dir.join(format!("gen-{:03}.cbnn", gen)));
+        if promoted {
+            best = candidate;
+            save(&best, &best_path);
+        }
+    }
+}
 
-    /// random seed
-    #[arg(short, long, default_value_t = 42)]
-    seed: u64,
+fn export_self_play(args: &Args, files: &SearchFiles, path: &Path) {
+    let t0 = Instant::now();
+    let cfg = args.mcts_config(files);
+    let mut data = Dataset::new();
+    for g in 0..args.games {
+        let mut game_rng = rand::rngs::StdRng::seed_from_u64(args.seed.wrapping_add(g as u64));
+        let (samples, winner) = train::self_play_game(&cfg, args.max_turns, args.temperature_plies, &mut game_rng);
+        for s in &samples {
+            data.add(s);
+            if args.mirror { data.add(&s.mirrored()); }
+        }
+        println!("Game {}/{}: {} positions, winner {}", g+1, args.games, samples.len(), winner.map_or("none".to_string(), |w| w.to_string()));
+    }
+    let format = Format::by_name(&args.export_format).expect("clap checks the name");
+    if let Err(e) = data.write(path, format) { eprintln!("failed to write {}: {}", path.display(), e); std::process::exit(1); }
+    println!("--- Exported {} positions ({} before merging duplicates) to {} ({:?}) ---", data.len(), data.added(), path.display(), t0.elapsed());
+}
 
-    /// enable biased playouts (heuristic-weighted)
-    #[arg(long, default_value_t = false)]
-    biased_playout: bool,
+// step size of the Adam optimiser in --tune-eval, in units of a feature's RMS
+const TUNE_LR: f64 = 0.05;
 
-    /// playout max moves
-    #[arg(long, default_value_t = 100)]
-    playout_max: usize,
+fn tune_eval(args: &Args, corpus: &Path) {
+    let t0 = Instant::now();
+    let positions = texel::read_corpus(corpus).unwrap_or_else(|e| { eprintln!("failed to read corpus: {}", e); std::process::exit(1) });
+    // every tenth position is held out to check the fit generalises
+    let (held_out, fitted): (Vec<_>, Vec<_>) = positions.into_iter().enumerate().partition(|(i, _)| i % 10 == 9);
+    let held_out: Vec<texel::Position> = held_out.into_iter().map(|(_, p)| p).collect();
+    let fitted: Vec<texel::Position> = fitted.into_iter().map(|(_, p)| p).collect();
+    let start = DEFAULT_WEIGHTS;
+    let tuned = texel::tune(&fitted, start, args.tune_steps, TUNE_LR);
+    println!("--- Tuned on {} positions, {} held out ({:?}) ---", fitted.len(), held_out.len(), t0.elapsed());
+    println!("{:<14} {:>9} {:>9}", "feature", "default", "tuned");
+    for (i, name) in EVAL_FEATURE_NAMES.iter().enumerate() { println!("{:<14} {:>9.4} {:>9.4}", name, start.0[i], tuned.0[i]); }
+    println!("Mean squared error: fitted {:.5} -> {:.5}, held out {:.5} -> {:.5}",
+        texel::error(&fitted, &start), texel::error(&fitted, &tuned), texel::error(&held_out, &start), texel::error(&held_out, &tuned));
+    let path = &args.eval_weights_out;
+    if let Err(e) = tuned.save(path) { eprintln!("failed to write {}: {}", path.display(), e); std::process::exit(1); }
+    println!("Written to {}", path.display());
+}
 
-    /// number of self-play games to run (1 = single probe; >1 runs statistics)
-    #[arg(long, default_value_t = 1)]
-    games: usize,
+// perturbation and first step as shares of each parameter's range
+const SPSA_C: f64 = 0.1;
+const SPSA_STEP: f64 = 0.05;
 
-    /// maximum turns per game before declaring draw
-    #[arg(long, default_value_t = 1000)]
-    max_turns: usize,
+fn tune_search(args: &Args, files: &SearchFiles, iterations: usize) {
+    let t0 = Instant::now();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(args.seed);
+    let params = args.tunable_params();
+    let start: Vec<f64> = params.iter().map(|p| args.search_param(p.name).expect("every tunable parameter has a flag")).collect();
+    let mut spsa = Spsa::new(params, &start, iterations, SPSA_C, SPSA_STEP);
+    let names: Vec<&str> = spsa.params().iter().map(|p| p.name).collect();
+    let with = |values: &[f64]| {
+        let mut a = args.clone();
+        for (name, &v) in names.iter().zip(values) { a.set_search_param(name, v).expect("names come from SEARCH_PARAMS"); }
+        a.mcts_config(files)
+    };
+    let mut log = args.spsa_log.as_ref().map(|path| {
+        let mut f = std::fs::File::create(path).unwrap_or_else(|e| { eprintln!("failed to create {}: {}", path.display(), e); std::process::exit(1) });
+        writeln!(f, "step,score,{}", names.join(",")).expect("failed to write the SPSA log");
+        f
+    });
+    println!("SPSA over {} for {} steps of {} games", names.join(", "), iterations, args.spsa_games);
+    for k in 1..=iterations {
+        let p = spsa.perturb(&mut rng);
+        let (plus, minus) = (with(&p.plus), with(&p.minus));
+        let mut points = 0.0;
+        for g in 0..args.spsa_games {
+            let (cfgs, side) = if g % 2 == 0 { ([&plus, &minus], 'A') } else { ([&minus, &plus], 'B') };
+            points += match play_game(State::default(), cfgs, args.max_turns, &mut rng) { Some(w) if w == side => 1.0, Some(_) => 0.0, None => 0.5 };
+        }
+        let score = points / args.spsa_games.max(1) as f64;
+        spsa.update(&p, score);
+        let values = spsa.values();
+        let shown: Vec<String> = spsa.params().iter().zip(&values).map(|(p, v)| if p.integer { format!("{}={}", p.name, v) } else { format!("{}={:.4}", p.name, v) }).collect();
+        println!("Step {}/{}: plus scored {:.3}, {} ({:?})", k, iterations, score, shown.join(" "), t0.elapsed());
+        if let Some(f) = log.as_mut() {
+            let row: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            writeln!(f, "{},{},{}", k, score, row.join(",")).expect("failed to write the SPSA log");
+        }
+    }
+    let path = &args.search_config_out;
+    let mut text = format!("# search parameters from {} SPSA steps of {} games, see --search-config\n", iterations, args.spsa_games);
+    for (name, v) in names.iter().zip(spsa.values()) { text += &format!("{} {}\n", name, v); }
+    if let Err(e) = std::fs::write(path, text) { eprintln!("failed to write {}: {}", path.display(), e); std::process::exit(1); }
+    println!("--- Recommended search parameters written to {} ---", path.display());
 }
 
-fn play_game(mut st: State, iters: usize, c: f64, biased_playout: bool, playout_max: usize, max_turns: usize, rng: &mut impl Rng) -> Option<char> {
-    let mut turns = 0usize;
-    loop {
-        if let Some(w) = st.is_terminal() { return Some(w) }
-        if turns >= max_turns { return None }
-        let mv = mcts_action(&st, iters, c, rng, biased_playout, playout_max);
-        if mv.is_none() { return None }
-        st = st.apply_move(&mv.unwrap());
-        turns += 1;
+// Every round plays more games for each remaining variant, with the search
+// settings of the other modes, and drops the variants whose first-player
+// rate is no longer plausibly even. Stops once few enough are left or after
+// --balance-rounds, the survivors are listed closest to even first.
+fn run_balance(args: &Args, files: &SearchFiles) {
+    let t0 = Instant::now();
+    let mut sizes = Vec::new();
+    for size in &args.balance_sizes {
+        match size.split_once('x').and_then(|(r, c)| Some((r.parse::<usize>().ok()?, c.parse::<usize>().ok()?))) {
+            Some((rows, cols)) if rows <= ROWS && cols <= COLS => sizes.push((rows, cols)),
+            Some(_) => { eprintln!("board size {:?} doesn't fit the {}x{} board", size, ROWS, COLS); std::process::exit(2); }
+            None => { eprintln!("invalid board size {:?}, expected ROWSxCOLS", size); std::process::exit(2); }
+        }
+    }
+    let dashes: Vec<Dash> = args.balance_dash.iter().map(|d| Dash::by_name(d).expect("clap checks the name")).collect();
+    let brute_files: Vec<BruteFiles> = args.balance_brute_files.iter().map(|b| BruteFiles::by_name(b).expect("clap checks the name")).collect();
+    let cfg = args.mcts_config(files);
+    let mut tallies: Vec<Tally> = balance::candidates(&sizes, &args.balance_core_steps, &dashes, &brute_files).into_iter().map(Tally::new).collect();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(args.seed);
+    let mut games = args.balance_games.max(1);
+    println!("Balancing {} variants, {} games each in the first round", tallies.len(), games);
+    for round in 1..=args.balance_rounds {
+        for t in tallies.iter_mut() {
+            let start = t.variant.state().expect("candidates fit the board");
+            for _ in 0..games {
+                match play_game(start.clone(), [&cfg, &cfg], args.max_turns, &mut rng) {
+                    Some('A') => t.first += 1,
+                    Some(_) => t.second += 1,
+                    None => t.draws += 1,
+                }
+            }
+        }
+        tallies.retain(|t| t.could_be_even(1.96));
+        tallies.sort_by(|a, b| a.imbalance().total_cmp(&b.imbalance()));
+        match tallies.first() {
+            Some(best) => println!("Round {}: {} variants after {} games each, best {} ({:?})", round, tallies.len(), best.games(), best.variant, t0.elapsed()),
+            None => { println!("Round {}: no variant is plausibly even ({:?})", round, t0.elapsed()); break }
+        }
+        if tallies.len() <= args.balance_keep { break }
+        games *= 2;
+    }
+    println!("--- Most balanced variants ({:?}) ---", t0.elapsed());
+    println!("{:<36} {:>7} {:>15} {:>6} {:>6}", "variant", "first", "95% interval", "draws", "games");
+    for t in &tallies {
+        let (lo, hi) = balance::wilson(t.first as f64, t.decisive(), 1.96);
+        let rate = t.first_rate().map_or("-".to_string(), |r| format!("{:.3}", r));
+        println!("{:<36} {:>7} {:>15} {:>6} {:>6}", t.variant.to_string(), rate, format!("[{:.3}, {:.3}]", lo, hi), t.draws, t.games());
     }
 }
 
-fn main() {
-    let args = Args::parse();
-    let mut global_rng = rand::rngs::StdRng::seed_from_u64(args.seed);
-
-    if args.games <= 1 {
-        let st = State::default();
-        let t0 = Instant::now();
-        let mv = mcts_action(&st, args.iters, 1.4, &mut global_rng, args.biased_playout, args.playout_max);
-        let dur = t0.elapsed();
-        if let Some(m) = mv {
-            println!("Best move after {} iterations: actor={}, kind={}, dest={:?}, captured={:?}", args.iters, m.actor, m.kind, m.dest, m.captured);
-        } else {
-            println!("No move found");
+// Each handicap is played from both colours in turn so the first-move edge
+// cancels out, the impact is how far the handicapped side's score falls from even.
+fn run_ablation(args: &Args, files: &SearchFiles) {
+    let t0 = Instant::now();
+    let handicaps: Vec<Handicap> = args.ablate.iter().map(|h| Handicap::parse(h).unwrap_or_else(|e| { eprintln!("invalid handicap {:?}: {}", h, e); std::process::exit(2) })).collect();
+    let cfg = args.mcts_config(files);
+    let mut records = Vec::new();
+    let mut recorder = open_recorder(args);
+    for handicap in &handicaps {
+        let mut record = Record::default();
+        for g in 0..args.games {
+            let side = if g % 2 == 0 { 'A' } else { 'B' };
+            let label = format!("ablate {} without={} {}", g + 1, handicap, side);
+            match play_logged_game(&mut recorder, label, args.seed.wrapping_add(g as u64), handicap.apply(&State::default(), side), [&cfg, &cfg], args.max_turns).0 {
+                Some(w) if w == side => record.wins += 1,
+                Some(_) => record.losses += 1,
+                None => record.draws += 1,
+            }
         }
-        println!("Elapsed: {:?}", dur);
-        return;
+        println!("Without {}: {} wins, {} losses, {} draws for the handicapped side ({:?})", handicap, record.wins, record.losses, record.draws, t0.elapsed());
+        records.push(record);
     }
+    println!("--- Piece ablation ({} games each, {:?}) ---", args.games, t0.elapsed());
+    println!("{:<9} {:>7} {:>8} {:>15} {:>6}", "without", "score", "impact", "95% interval", "draws");
+    for (handicap, r) in handicaps.iter().zip(&records) {
+        // draws count as half a win on both sides of the interval
+        let (lo, hi) = balance::wilson(r.wins as f64 + 0.5 * r.draws as f64, r.games(), 1.96);
+        println!("{:<9} {:>7.3} {:>+8.3} {:>15} {:>6}", handicap.to_string(), r.score(), 0.5 - r.score(), format!("[{:.3}, {:.3}]", 0.5 - hi, 0.5 - lo), r.draws);
+    }
+}
 
-    let mut results = vec!['X'; args.games]; // 'A','B', or 'D' for draw
-    for g in 0..args.games {
-        // seed each game differently for variance
-        let seed = args.seed.wrapping_add(g as u64);
-        let mut game_rng = rand::rngs::StdRng::seed_from_u64(seed);
-        let st = State::default();
-        let winner = play_game(st, args.iters, 1.4, args.biased_playout, args.playout_max, args.max_turns, &mut game_rng);
-        match winner {
-            Some('A') => { results[g] = 'A'; println!("Game {}/{}: winner=A", g+1, args.games); }
-            Some('B') => { results[g] = 'B'; println!("Game {}/{}: winner=B", g+1, args.games); }
-            None => { results[g] = 'D'; println!("Game {}/{}: draw", g+1, args.games); }
-            _ => { results[g] = 'D'; println!("Game {}/{}: unknown result", g+1, args.games); }
+// Every cell is parsed as a command line of its own, so any flag can be swept
+// and clap checks the values before the first game is played.
+fn run_sweep(path: &Path) {
+    let t0 = Instant::now();
+    let exp = sweep::Experiment::load(path).unwrap_or_else(|e| { eprintln!("invalid experiment {}: {}", path.display(), e); std::process::exit(2) });
+    let axes = exp.axes();
+    let cells = exp.cells();
+    for cell in &cells {
+        if let Err(e) = sweep::check_cell(cell) { eprintln!("invalid cell {}: {}", exp.label(cell), e); std::process::exit(2); }
+    }
+    let configs: Vec<Args> = cells.iter().map(|cell| {
+        let argv = std::iter::once("core_battle_analyzer".to_string()).chain(sweep::cell_args(cell));
+        Args::try_parse_from(argv).unwrap_or_else(|e| { eprintln!("invalid cell {}: {}", exp.label(cell), e); std::process::exit(2) })
+    }).collect();
+    let mut results = sweep::Results::open(&exp.results, &axes).unwrap_or_else(|e| { eprintln!("failed to open results: {}", e); std::process::exit(1) });
+    println!("Sweeping {} cells over {}, results in {}", cells.len(), if axes.is_empty() { "nothing".to_string() } else { axes.join(", ") }, exp.results.display());
+    for (i, (cell, mut args)) in cells.iter().zip(configs).enumerate() {
+        if results.is_done(&axes, cell) { println!("Cell {}/{}: {} already done", i+1, cells.len(), exp.label(cell)); continue }
+        if let Some(path) = args.search_config.clone() {
+            if let Err(e) = args.load_search_config(&path) { eprintln!("failed to load {}: {}", path.display(), e); std::process::exit(1); }
+        }
+        let files = load_search_files(&args);
+        let t = Instant::now();
+        let cfg = args.mcts_config(&files);
+        let mut counts = [0usize; 3];
+        for g in 0..args.games {
+            counts[match statistics_game(&args, &cfg, g, &mut None).winner { Some('A') => 0, Some(_) => 1, None => 2 }] += 1;
         }
+        let decisive = counts[0] + counts[1];
+        let (lo, hi) = balance::wilson(counts[0] as f64, decisive, 1.96);
+        let rate = if decisive > 0 { format!("{:.4}", counts[0] as f64 / decisive as f64) } else { String::new() };
+        let values = [args.games.to_string(), counts[0].to_string(), counts[1].to_string(), counts[2].to_string(), rate, format!("{:.4}", lo), format!("{:.4}", hi), format!("{:.2}", t.elapsed().as_secs_f64())];
+        if let Err(e) = results.record(&axes, cell, &values) { eprintln!("failed to write results: {}", e); std::process::exit(1); }
+        println!("Cell {}/{}: {} -> A {} / B {} / draws {} ({:?})", i+1, cells.len(), exp.label(cell), counts[0], counts[1], counts[2], t0.elapsed());
     }
+    println!("--- Sweep finished ({:?}) ---", t0.elapsed());
+    match std::fs::read_to_string(&exp.results) {
+        Ok(table) => print!("{}", table),
+        Err(e) => eprintln!("failed to read {}: {}", exp.results.display(), e),
+    }
+}
 
-    let a_wins = results.iter().filter(|&&r| r == 'A').count();
-    let b_wins = results.iter().filter(|&&r| r == 'B').count();
-    let draws = results.iter().filter(|&&r| r == 'D').count();
-    println!("--- Summary ({} games) ---", args.games);
-    println!("A wins: {}\nB wins: {}\nDraws: {}", a_wins, b_wins, draws);
-    if a_wins + b_wins > 0 {
-        let rate = (a_wins as f64) / ((a_wins + b_wins) as f64);
-        println!("First-player win rate (A / decisive games): {:.2}", rate);
+// Replays every game of a --record file from its start position, checking
+// each move against the rules and the recorded result against where the moves
+// end. With `step` that game is also printed move by move.
+fn replay_games(path: &Path, step: Option<usize>) {
+    let games = GameLog::read_file(path).unwrap_or_else(|e| { eprintln!("invalid game record {}: {}", path.display(), e); std::process::exit(2) });
+    if let Some(n) = step {
+        let Some(game) = games.get(n.wrapping_sub(1)) else { eprintln!("{} holds {} games, there is no game {}", path.display(), games.len(), n); std::process::exit(2) };
+        println!("Game {} (seed {})\nA: {}\nB: {}", game.label, game.seed, game.configs[0], game.configs[1]);
+        for line in gamelog::board_lines(&game.start) { println!("  {}", line); }
+        let result = game.verify(|i, m, s| {
+            println!("Ply {}: {} {} (visits {}/{}, value {:.3}, proof {:?}, {:.1} ms)", i + 1, if s.turn == 'A' { 'B' } else { 'A' }, gamelog::move_text(&m.mv), m.visits, m.iterations, m.value, m.proof, m.millis);
+            for line in gamelog::board_lines(s) { println!("  {}", line); }
+        });
+        match result {
+            Ok(_) => println!("Result: {} after {} plies, verified", game.winner.map_or("draw".to_string(), |w| format!("{} wins", w)), game.moves.len()),
+            Err(e) => { eprintln!("game {} fails verification: {}", game.label, e); std::process::exit(1); }
+        }
+        return;
+    }
+    let mut failed = 0;
+    for game in &games {
+        match game.verify(|_, _, _| {}) {
+            Ok(_) => println!("Game {}: {} plies, {}, verified", game.label, game.moves.len(), game.winner.map_or("draw".to_string(), |w| format!("{} wins", w))),
+            Err(e) => { println!("Game {}: FAILED, {}", game.label, e); failed += 1; }
+        }
     }
+    println!("--- Replayed {} games, {} failed ---", games.len(), failed);
+    if failed > 0 { std::process::exit(1); }
 }
@@ -0,0 +1,180 @@
+use rs_board::r#match::{DrawOutcome, MatchRules};
+
+use crate::state::opponent;
+
+// --on-draw names of rs-board's DrawOutcome
+pub const DRAW_OUTCOME_NAMES: [&str; 3] = ["replay", "no-point", "split"];
+
+pub fn draw_outcome_by_name(name: &str) -> Option<DrawOutcome> {
+    match name {
+        "replay" => Some(DrawOutcome::ReplayRound),
+        "no-point" => Some(DrawOutcome::NoPoint),
+        "split" => Some(DrawOutcome::Split),
+        _ => None,
+    }
+}
+
+// outcome chances of one round, by symmetry the same whichever side moves first
+#[derive(Clone, Copy, Debug)]
+pub struct RoundOdds {
+    pub first_wins: f64,
+    pub draws: f64,
+}
+
+impl RoundOdds {
+    // from the winners of simulated rounds that A started, None for a drawn round
+    pub fn from_rounds(winners: &[Option<char>]) -> RoundOdds {
+        if winners.is_empty() { return RoundOdds { first_wins: 0.5, draws: 0.0 }; }
+        let count = |w: Option<char>| winners.iter().filter(|&&x| x == w).count() as f64 / winners.len() as f64;
+        RoundOdds { first_wins: count(Some('A')), draws: count(None) }
+    }
+
+    fn second_wins(&self) -> f64 { 1.0 - self.first_wins - self.draws }
+}
+
+// Chance that A wins the match (a drawn match counting half) from every score,
+// with half points so split draws stay exact. Half points are u16, twice
+// `points_to_win` doesn't fit a u8.
+pub struct MatchEquity {
+    rules: MatchRules,
+    // [half_a][half_b][first player is B]
+    table: Vec<[f64; 2]>,
+}
+
+impl MatchEquity {
+    pub fn new(rules: MatchRules, odds: RoundOdds) -> MatchEquity {
+        let target = rules.points_to_win as usize * 2;
+        let mut eq = MatchEquity { rules, table: vec![[0.0; 2]; target * target] };
+        // every outcome adds half points, so fill from the highest scores down
+        for ha in (0..target).rev() {
+            for hb in (0..target).rev() {
+                let half = [ha as u16, hb as u16];
+                // A's equity from the decisive outcomes of a round that `first` starts
+                let decided = |first: char| {
+                    let first_wins = if first == 'A' { [half[0] + 2, half[1]] } else { [half[0], half[1] + 2] };
+                    let second_wins = if first == 'A' { [half[0], half[1] + 2] } else { [half[0] + 2, half[1]] };
+                    let next = opponent(first);
+                    odds.first_wins * eq.value(first_wins, next) + odds.second_wins() * eq.value(second_wins, next)
+                };
+                let (da, db) = (decided('A'), decided('B'));
+                let decisive = odds.first_wins + odds.second_wins();
+                let values = match rules.on_draw {
+                    // draws only postpone the round
+                    DrawOutcome::ReplayRound if decisive > 0.0 => [da / decisive, db / decisive],
+                    DrawOutcome::ReplayRound => [0.5; 2],
+                    // v_a = da + d * v_b and v_b = db + d * v_a
+                    DrawOutcome::NoPoint => {
+                        let d = odds.draws;
+                        let denom = 1.0 - d * d;
+                        if denom > 0.0 { [(da + d * db) / denom, (db + d * da) / denom] } else { [0.5; 2] }
+                    }
+                    DrawOutcome::Split => {
+                        let split = [half[0] + 1, half[1] + 1];
+                        [da + odds.draws * eq.value(split, 'B'), db + odds.draws * eq.value(split, 'A')]
+                    }
+                };
+                eq.table[ha * target + hb] = values;
+            }
+        }
+        eq
+    }
+
+    fn target(&self) -> u16 { u16::from(self.rules.points_to_win) * 2 }
+
+    // A's match equity at these half points with `first` to move first in the next round
+    pub fn value(&self, half: [u16; 2], first: char) -> f64 {
+        let target = self.target();
+        match (half[0] >= target, half[1] >= target) {
+            (true, true) => 0.5,
+            (true, false) => 1.0,
+            (false, true) => 0.0,
+            (false, false) => self.table[half[0] as usize * target as usize + half[1] as usize][(first == 'B') as usize],
+        }
+    }
+
+    // A's equity after the round `first` starts at these half points ends in
+    // A's touch, B's touch or a draw, for MctsConfig::match_equity
+    pub fn round_values(&self, half: [u16; 2], first: char) -> [f64; 3] {
+        let next = opponent(first);
+        let drawn = match self.rules.on_draw {
+            DrawOutcome::ReplayRound => self.value(half, first),
+            DrawOutcome::NoPoint => self.value(half, next),
+            DrawOutcome::Split => self.value([half[0] + 1, half[1] + 1], next),
+        };
+        [self.value([half[0] + 2, half[1]], next), self.value([half[0], half[1] + 2], next), drawn]
+    }
+
+    // Round rewards for A and B that rank a drawn round between losing (0) and
+    // winning (1) it by the match equity each leaves.
+    pub fn draw_values(&self, half: [u16; 2], first: char) -> [f64; 2] {
+        let [a_wins, b_wins, drawn] = self.round_values(half, first);
+        if a_wins <= b_wins { return [0.5; 2]; }
+        let a = ((drawn - b_wins) / (a_wins - b_wins)).clamp(0.0, 1.0);
+        [a, 1.0 - a]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(points_to_win: u8, on_draw: DrawOutcome) -> MatchRules {
+        MatchRules { points_to_win, on_draw, ..MatchRules::default() }
+    }
+
+    fn equity(on_draw: DrawOutcome, first_wins: f64, draws: f64) -> MatchEquity {
+        MatchEquity::new(rules(3, on_draw), RoundOdds { first_wins, draws })
+    }
+
+    #[test]
+    fn even_rounds_give_even_matches() {
+        for on_draw in [DrawOutcome::ReplayRound, DrawOutcome::NoPoint, DrawOutcome::Split] {
+            let eq = equity(on_draw, 0.4, 0.2);
+            assert!((eq.value([0, 0], 'A') + eq.value([0, 0], 'B') - 1.0).abs() < 1e-12, "{:?}", on_draw);
+            assert!((eq.value([2, 2], 'A') + eq.value([2, 2], 'B') - 1.0).abs() < 1e-12, "{:?}", on_draw);
+            assert!(eq.value([4, 0], 'B') > eq.value([2, 0], 'B'));
+        }
+        assert_eq!(equity(DrawOutcome::Split, 0.4, 0.2).value([6, 6], 'A'), 0.5);
+    }
+
+    #[test]
+    fn odds_from_rounds() {
+        let odds = RoundOdds::from_rounds(&[Some('A'), Some('B'), None, Some('A')]);
+        assert_eq!((odds.first_wins, odds.draws, odds.second_wins()), (0.5, 0.25, 0.25));
+    }
+
+    #[test]
+    fn replay_matches_a_direct_recursion() {
+        // first to 1 point: A moves first and wins the match with the first decided round
+        let eq = MatchEquity::new(rules(1, DrawOutcome::ReplayRound), RoundOdds { first_wins: 0.6, draws: 0.2 });
+        assert!((eq.value([0, 0], 'A') - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn round_values_are_the_equity_after_each_result() {
+        let eq = equity(DrawOutcome::NoPoint, 0.45, 0.1);
+        let [a_wins, b_wins, drawn] = eq.round_values([2, 0], 'B');
+        assert_eq!((a_wins, b_wins, drawn), (eq.value([4, 0], 'A'), eq.value([2, 2], 'A'), eq.value([2, 0], 'A')));
+        assert!(a_wins > drawn && drawn > b_wins);
+    }
+
+    #[test]
+    fn long_matches_do_not_overflow() {
+        let eq = MatchEquity::new(rules(200, DrawOutcome::Split), RoundOdds { first_wins: 0.5, draws: 0.1 });
+        assert_eq!(eq.value([400, 0], 'A'), 1.0);
+        assert!(eq.value([398, 0], 'A') > 0.9);
+        assert!((0.0..=1.0).contains(&eq.draw_values([398, 398], 'A')[0]));
+    }
+
+    #[test]
+    fn split_draws_are_worth_more_to_the_leader() {
+        let eq = equity(DrawOutcome::Split, 0.45, 0.1);
+        let ahead = eq.draw_values([4, 0], 'A');
+        let behind = eq.draw_values([0, 4], 'A');
+        assert!(ahead[0] > 0.55 && behind[0] < 0.45, "{:?} {:?}", ahead, behind);
+        assert!((ahead[0] + ahead[1] - 1.0).abs() < 1e-12);
+        // a replayed draw changes nothing, whatever the score
+        let eq = equity(DrawOutcome::ReplayRound, 0.45, 0.1);
+        assert!((eq.draw_values([4, 0], 'A')[0] - eq.draw_values([0, 4], 'A')[0]).abs() < 1e-12);
+    }
+}
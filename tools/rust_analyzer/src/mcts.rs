@@ -0,0 +1,477 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::net::Network;
+use crate::playout::{run_playout, Playout, PlayoutResult};
+use crate::state::{opponent, Move, State};
+use crate::tablebase::{TbValue, Tablebases};
+
+const NO_NODE: u32 = u32::MAX;
+const NOT_EXPANDED: u16 = u16::MAX;
+
+// Nodes only keep the move leading to them plus statistics, children are a
+// linked list of siblings and states are rebuilt by replaying moves from the
+// root during selection. At 100k iterations from the opening position this is
+// 40 bytes/node (~52 with Vec slack) against ~1800 bytes/node when every node
+// cloned its State and kept a Vec of untried moves.
+struct MCTSNode {
+    move_from_parent: Option<Move>,
+    parent: u32,
+    first_child: u32,
+    next_sibling: u32,
+    visits: u32,
+    // summed rewards from the perspective of the player who made `move_from_parent`
+    wins: f64,
+    // legal moves not yet expanded into children, NOT_EXPANDED until first reached
+    untried: u16,
+    proof: Proof,
+}
+
+impl MCTSNode {
+    fn new(move_from_parent: Option<Move>, parent: u32) -> Self {
+        MCTSNode { move_from_parent, parent, first_child: NO_NODE, next_sibling: NO_NODE, visits: 0, wins: 0.0, untried: NOT_EXPANDED, proof: Proof::Unknown }
+    }
+}
+
+// MCTS-Solver result for a node, from the perspective of the player who moved
+// into it. The number is how many plies after the node the touch happens.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Proof {
+    Unknown,
+    Win(u16),
+    Loss(u16),
+}
+
+struct Children<'a> {
+    nodes: &'a [MCTSNode],
+    next: u32,
+}
+
+impl Iterator for Children<'_> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        if self.next == NO_NODE { return None }
+        let cur = self.next as usize;
+        self.next = self.nodes[cur].next_sibling;
+        Some(cur)
+    }
+}
+
+fn children(nodes: &[MCTSNode], idx: usize) -> Children<'_> {
+    Children { nodes, next: nodes[idx].first_child }
+}
+
+#[derive(Clone, Debug)]
+pub struct MctsConfig {
+    pub iterations: usize,
+    pub c: f64,
+    pub playout: Playout,
+    pub playout_max: usize,
+    // rewards credited to A and B when a playout ends without a winner
+    pub draw_value: [f64; 2],
+    // A's match equity after A's touch, B's touch and a drawn round; when set
+    // a leaf's reward is the equity it leads to instead of its round result
+    pub match_equity: Option<[f64; 3]>,
+    // RAVE equivalence parameter k, blending weight is sqrt(k / (3n + k)) for a child with n visits
+    pub rave: Option<f64>,
+    // exact results for positions with few pieces left, probed at expansion and before playouts
+    pub tablebases: Option<Arc<Tablebases>>,
+    // policy/value network: children get its priors and are picked by PUCT with
+    // `c` as the exploration constant, leaves are scored by its value instead of a playout
+    pub network: Option<Arc<Network>>,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig { iterations: 1000, c: 1.4, playout: Playout::Uniform, playout_max: 100, draw_value: [0.5; 2], match_equity: None, rave: None, tablebases: None, network: None }
+    }
+}
+
+// ---------------- RAVE ----------------
+
+// all-moves-as-first statistics, kept beside the tree and only when RAVE is on
+#[derive(Clone, Copy, Default)]
+struct RaveStats {
+    key: u16,
+    visits: u32,
+    // from the perspective of the player who made the move, like MCTSNode::wins
+    wins: f32,
+}
+
+const RAVE_KEYS: usize = 1 << 11;
+
+// identity of a move for AMAF: owner, piece kind and the square it goes to
+// (or captures on), independent of where the piece started
+fn rave_key(state: &State, mv: &Move) -> u16 {
+    let piece = state.board[mv.actor as usize].expect("actor must exist");
+    let square = mv.dest.or(mv.captured).expect("every move has a target square") as u16;
+    ((piece.owner == 'B') as u16) << 10 | (piece.kind as u16) << 7 | square
+}
+
+struct KeySet([u64; RAVE_KEYS / 64]);
+
+impl KeySet {
+    fn insert(&mut self, key: u16) { self.0[key as usize / 64] |= 1 << (key % 64); }
+    fn contains(&self, key: u16) -> bool { self.0[key as usize / 64] & (1 << (key % 64)) != 0 }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TreeStats {
+    pub nodes: usize,
+    // bytes held by the node arena, including Vec slack
+    pub bytes: usize,
+    pub iterations: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best: Option<Move>,
+    // proof of `best` for the player to move at the root
+    pub proof: Proof,
+    pub tree: TreeStats,
+    // every expanded root move with its visit count, the training target for the policy
+    pub visits: Vec<(Move, u32)>,
+    // mean reward of the root player over all iterations
+    pub value: f64,
+}
+
+impl SearchResult {
+    // plies from the root until the proven touch, counting `best` itself
+    pub fn proof_length(&self) -> Option<u16> {
+        match self.proof { Proof::Win(n) | Proof::Loss(n) => Some(n + 1), Proof::Unknown => None }
+    }
+}
+
+// Solver rules: one winning reply proves the node lost for its mover, and a
+// fully expanded node whose replies all lose is won. Lengths assume the winner
+// hurries and the loser stalls.
+fn update_proof(nodes: &mut [MCTSNode], idx: usize) -> bool {
+    if nodes[idx].proof != Proof::Unknown { return false }
+    let mut fastest_win: Option<u16> = None;
+    let mut slowest_loss: Option<u16> = Some(0);
+    for ci in children(nodes, idx) {
+        match nodes[ci].proof {
+            Proof::Win(n) => fastest_win = Some(fastest_win.map_or(n, |w| w.min(n))),
+            Proof::Loss(n) => slowest_loss = slowest_loss.map(|l| l.max(n)),
+            Proof::Unknown => slowest_loss = None,
+        }
+    }
+    nodes[idx].proof = match (fastest_win, slowest_loss) {
+        (Some(n), _) => Proof::Loss(n + 1),
+        (None, Some(n)) if nodes[idx].untried == 0 && nodes[idx].first_child != NO_NODE => Proof::Win(n + 1),
+        _ => return false,
+    };
+    true
+}
+
+// Credits every child whose move its player also made later in the iteration,
+// walking up from the leaf so each node only sees the moves played below it.
+fn update_rave(nodes: &[MCTSNode], rave: &mut [RaveStats], leaf: usize, mut depth: usize, root_turn: char, trace: &[u16], reward: impl Fn(char) -> f64) {
+    let mut seen = KeySet([0; RAVE_KEYS / 64]);
+    for &key in trace { seen.insert(key); }
+    let mut cur = leaf as u32;
+    while cur != NO_NODE {
+        // children of a node at depth d are entered by the root player when d is even
+        let child_mover = if depth.is_multiple_of(2) { root_turn } else { opponent(root_turn) };
+        for ci in children(nodes, cur as usize) {
+            if seen.contains(rave[ci].key) {
+                rave[ci].visits += 1;
+                rave[ci].wins += reward(child_mover) as f32;
+            }
+        }
+        if cur != 0 { seen.insert(rave[cur as usize].key); }
+        cur = nodes[cur as usize].parent;
+        depth = depth.saturating_sub(1);
+    }
+}
+
+fn best_root_child(nodes: &[MCTSNode]) -> Option<usize> {
+    let rank = |ci: usize| match nodes[ci].proof {
+        // fastest forced win first, then most visited, then the loss that holds out longest
+        Proof::Win(n) => (2, u32::MAX - n as u32),
+        Proof::Unknown => (1, nodes[ci].visits),
+        Proof::Loss(n) => (0, n as u32),
+    };
+    children(nodes, 0).max_by_key(|&ci| rank(ci))
+}
+
+fn uct_score(parent_visits: u32, child: &MCTSNode, rave: Option<(&RaveStats, f64)>, c: f64) -> f64 {
+    if child.visits == 0 { return f64::INFINITY; }
+    let n = child.visits as f64;
+    rave_blend(child.wins / n, n, rave) + c * ((parent_visits as f64).ln() / n).sqrt()
+}
+
+// a visited child's value mixed with its AMAF value, which counts less as visits grow
+fn rave_blend(q: f64, n: f64, rave: Option<(&RaveStats, f64)>) -> f64 {
+    match rave {
+        Some((stats, k)) if stats.visits > 0 => {
+            let beta = (k / (3.0 * n + k)).sqrt();
+            (1.0 - beta) * q + beta * (stats.wins as f64 / stats.visits as f64)
+        }
+        _ => q,
+    }
+}
+
+// AlphaZero's PUCT, unvisited children count as the parent's value for their mover
+fn puct_score(parent: &MCTSNode, child: &MCTSNode, prior: f32, rave: Option<(&RaveStats, f64)>, c: f64) -> f64 {
+    let q = if child.visits > 0 { rave_blend(child.wins / child.visits as f64, child.visits as f64, rave) } else if parent.visits > 0 { 1.0 - parent.wins / parent.visits as f64 } else { 0.5 };
+    q + c * prior as f64 * (parent.visits as f64).sqrt() / (1.0 + child.visits as f64)
+}
+
+// Adds every legal move as a child at once with the network's priors and
+// returns the network's value of `state` for its side to move.
+fn expand_with_network(nodes: &mut Vec<MCTSNode>, priors: &mut Vec<f32>, mut rave: Option<&mut Vec<RaveStats>>, node_idx: usize, state: &State, net: &Network, tablebases: Option<&Tablebases>) -> f32 {
+    let moves = state.legal_moves();
+    let (p, value) = net.evaluate(state, &moves);
+    for (mv, prior) in moves.into_iter().zip(p) {
+        if let Some(rave) = rave.as_deref_mut() { rave.push(RaveStats { key: rave_key(state, &mv), ..RaveStats::default() }); }
+        let next = state.apply_move(&mv);
+        let mut child = MCTSNode::new(Some(mv), node_idx as u32);
+        child.next_sibling = nodes[node_idx].first_child;
+        child.proof = child_proof(&next, state.turn, tablebases);
+        nodes[node_idx].first_child = nodes.len() as u32;
+        nodes.push(child);
+        priors.push(prior);
+    }
+    nodes[node_idx].untried = 0;
+    value
+}
+
+// proof of a freshly added child for `mover`, who made the move into `next`
+fn child_proof(next: &State, mover: char, tablebases: Option<&Tablebases>) -> Proof {
+    if let Some(w) = next.is_terminal() { return if w == mover { Proof::Win(0) } else { Proof::Loss(0) }; }
+    // the table scores the side to move in the child, the mover's opponent
+    match tablebases.and_then(|tb| tb.probe(next)) { Some(TbValue::Loss(d)) => Proof::Win(d), Some(TbValue::Win(d)) => Proof::Loss(d), _ => Proof::Unknown }
+}
+
+pub fn mcts_action(root_state: &State, cfg: &MctsConfig, rng: &mut impl Rng) -> SearchResult {
+    let mut nodes: Vec<MCTSNode> = vec![MCTSNode::new(None, NO_NODE)];
+    let mut rave: Vec<RaveStats> = if cfg.rave.is_some() { vec![RaveStats::default()] } else { Vec::new() };
+    let mut trace: Vec<u16> = Vec::new();
+    // prior of every node's move, only kept with a network
+    let mut priors: Vec<f32> = if cfg.network.is_some() { vec![1.0] } else { Vec::new() };
+    let policy = cfg.playout.policy();
+    let mut iterations = 0;
+
+    while iterations < cfg.iterations && nodes[0].proof == Proof::Unknown {
+        iterations += 1;
+        // selection, replaying moves to rebuild the state of the selected node
+        let mut state = root_state.clone();
+        let mut node_idx = 0usize;
+        let mut depth = 0usize;
+        let mut net_value = None;
+        loop {
+            if nodes[node_idx].proof != Proof::Unknown { break }
+            if nodes[node_idx].untried == NOT_EXPANDED {
+                if let Some(net) = &cfg.network {
+                    net_value = Some(expand_with_network(&mut nodes, &mut priors, cfg.rave.is_some().then_some(&mut rave), node_idx, &state, net, cfg.tablebases.as_deref()));
+                    update_proof(&mut nodes, node_idx);
+                    break;
+                }
+                nodes[node_idx].untried = state.legal_moves().len() as u16;
+            }
+            if nodes[node_idx].untried > 0 || nodes[node_idx].first_child == NO_NODE { break }
+            // pick child with max UCT, never one already proven lost for the player choosing it
+            let mut best = None; let mut best_score = -1f64;
+            for child_idx in children(&nodes, node_idx) {
+                if matches!(nodes[child_idx].proof, Proof::Loss(_)) { continue }
+                let score = match cfg.network {
+                    Some(_) => puct_score(&nodes[node_idx], &nodes[child_idx], priors[child_idx], cfg.rave.map(|k| (&rave[child_idx], k)), cfg.c),
+                    None => uct_score(nodes[node_idx].visits, &nodes[child_idx], cfg.rave.map(|k| (&rave[child_idx], k)), cfg.c),
+                };
+                if score.is_infinite() || score > best_score { best_score = score; best = Some(child_idx); }
+            }
+            if let Some(b) = best {
+                node_idx = b;
+                depth += 1;
+                state = state.apply_move(&nodes[b].move_from_parent.expect("non-root node has a move"));
+            } else { break }
+        }
+        // expansion
+        if nodes[node_idx].proof == Proof::Unknown && nodes[node_idx].untried > 0 {
+            let tried: Vec<Move> = children(&nodes, node_idx).filter_map(|ci| nodes[ci].move_from_parent).collect();
+            let untried: Vec<Move> = state.legal_moves().into_iter().filter(|m| !tried.contains(m)).collect();
+            let mv = untried[rng.gen_range(0..untried.len())];
+            let mover = state.turn;
+            let key = if cfg.rave.is_some() { rave_key(&state, &mv) } else { 0 };
+            state = state.apply_move(&mv);
+            let new_idx = nodes.len();
+            let mut child = MCTSNode::new(Some(mv), node_idx as u32);
+            child.next_sibling = nodes[node_idx].first_child;
+            child.proof = child_proof(&state, mover, cfg.tablebases.as_deref());
+            if cfg.rave.is_some() { rave.push(RaveStats { key, ..RaveStats::default() }); }
+            nodes.push(child);
+            nodes[node_idx].first_child = new_idx as u32;
+            nodes[node_idx].untried -= 1;
+            node_idx = new_idx;
+            depth += 1;
+        }
+        // simulation, proven nodes already know their outcome
+        let leaf_mover = if depth % 2 == 1 { root_state.turn } else { opponent(root_state.turn) };
+        trace.clear();
+        let outcome = match nodes[node_idx].proof {
+            Proof::Win(_) => PlayoutResult::Win(leaf_mover),
+            Proof::Loss(_) => PlayoutResult::Win(opponent(leaf_mover)),
+            Proof::Unknown if cfg.tablebases.as_ref().and_then(|tb| tb.probe(&state)) == Some(TbValue::Draw) => PlayoutResult::Draw,
+            // a position without moves is a draw, which the network wasn't trained on
+            Proof::Unknown if cfg.network.is_some() && nodes[node_idx].first_child == NO_NODE => PlayoutResult::Draw,
+            Proof::Unknown if net_value.is_some() => {
+                let v = net_value.unwrap() as f64;
+                PlayoutResult::Eval(if state.turn == 'A' { v } else { 1.0 - v })
+            }
+            Proof::Unknown => run_playout(policy.as_ref(), state, rng, cfg.playout_max, |s, m| if cfg.rave.is_some() { trace.push(rave_key(s, m)) }),
+        };
+        let reward = |mover: char| match (outcome, cfg.match_equity) {
+            (_, Some([a_wins, b_wins, drawn])) => {
+                let a = match outcome { PlayoutResult::Win(w) => if w == 'A' { a_wins } else { b_wins }, PlayoutResult::Draw => drawn, PlayoutResult::Eval(a) => a * a_wins + (1.0 - a) * b_wins };
+                if mover == 'A' { a } else { 1.0 - a }
+            }
+            (PlayoutResult::Win(w), None) => if w == mover { 1.0 } else { 0.0 },
+            (PlayoutResult::Draw, None) => if mover == 'A' { cfg.draw_value[0] } else { cfg.draw_value[1] },
+            (PlayoutResult::Eval(a), None) => if mover == 'A' { a } else { 1.0 - a },
+        };
+        if cfg.rave.is_some() { update_rave(&nodes, &mut rave, node_idx, depth, root_state.turn, &trace, reward); }
+        // backprop, moves alternate so nodes at odd depth were entered by the root player
+        let mut cur = node_idx as u32;
+        let mut proving = nodes[node_idx].proof != Proof::Unknown;
+        while cur != NO_NODE {
+            let mover = if depth % 2 == 1 { root_state.turn } else { opponent(root_state.turn) };
+            if proving && cur as usize != node_idx { proving = update_proof(&mut nodes, cur as usize); }
+            let node = &mut nodes[cur as usize];
+            node.visits += 1;
+            node.wins += reward(mover);
+            cur = node.parent;
+            depth = depth.saturating_sub(1);
+        }
+        // a forced win for the root player needs no further search
+        if children(&nodes, 0).any(|ci| matches!(nodes[ci].proof, Proof::Win(_))) { break }
+    }
+
+    let tree = TreeStats { nodes: nodes.len(), bytes: nodes.capacity() * std::mem::size_of::<MCTSNode>(), iterations };
+    let visits = children(&nodes, 0).map(|ci| (nodes[ci].move_from_parent.expect("non-root node has a move"), nodes[ci].visits)).collect();
+    // the root's wins are credited to the player who moved into it, the root player's opponent
+    let value = if nodes[0].visits > 0 { 1.0 - nodes[0].wins / nodes[0].visits as f64 } else { 0.5 };
+    match best_root_child(&nodes) {
+        Some(ci) => SearchResult { best: nodes[ci].move_from_parent, proof: nodes[ci].proof, tree, visits, value },
+        None => SearchResult { best: None, proof: Proof::Unknown, tree, visits, value },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{position, MoveKind, PieceKind};
+    use rand::SeedableRng;
+
+    fn finds_with(state: &State, rave: Option<f64>, expected: Move) -> SearchResult {
+        let cfg = MctsConfig { iterations: 2000, playout_max: 20, rave, ..MctsConfig::default() };
+        let mut result = None;
+        for seed in 0..4 {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let found = mcts_action(state, &cfg, &mut rng);
+            assert_eq!(found.best, Some(expected), "seed {}", seed);
+            result = Some(found);
+        }
+        result.unwrap()
+    }
+
+    fn finds(state: &State, expected: Move) -> SearchResult { finds_with(state, None, expected) }
+
+    // Both Cores start frozen. Stepping the Monarch next to its Core lets A
+    // touch next turn whatever B does; the other moves only win if B
+    // cooperates, which is what root-perspective backprop used to assume.
+    #[test]
+    fn finds_forced_touch_for_a() {
+        let state = position('A', &[
+            (21, 'B', PieceKind::Core), (30, 'A', PieceKind::Core), (32, 'A', PieceKind::Monarch),
+            (39, 'A', PieceKind::BruteR), (41, 'B', PieceKind::Monarch),
+        ]);
+        let result = finds(&state, Move { actor: 32, kind: MoveKind::Move, dest: Some(20), captured: None });
+        assert_eq!(result.proof, Proof::Win(2));
+        assert_eq!(result.proof_length(), Some(3));
+        assert!(result.tree.iterations < 2000);
+    }
+
+    // the same position mirrored top to bottom with the colours swapped
+    #[test]
+    fn finds_forced_touch_for_b() {
+        let state = position('B', &[
+            (65, 'A', PieceKind::Core), (52, 'B', PieceKind::Core), (54, 'B', PieceKind::Monarch),
+            (39, 'B', PieceKind::BruteR), (41, 'A', PieceKind::Monarch),
+        ]);
+        let result = finds(&state, Move { actor: 54, kind: MoveKind::Move, dest: Some(64), captured: None });
+        assert_eq!(result.proof_length(), Some(3));
+    }
+
+    #[test]
+    fn rave_still_finds_forced_touch() {
+        let state = position('A', &[
+            (21, 'B', PieceKind::Core), (30, 'A', PieceKind::Core), (32, 'A', PieceKind::Monarch),
+            (39, 'A', PieceKind::BruteR), (41, 'B', PieceKind::Monarch),
+        ]);
+        let result = finds_with(&state, Some(500.0), Move { actor: 32, kind: MoveKind::Move, dest: Some(20), captured: None });
+        assert_eq!(result.proof, Proof::Win(2));
+    }
+
+    #[test]
+    fn network_search_still_finds_forced_touch() {
+        let state = position('A', &[
+            (21, 'B', PieceKind::Core), (30, 'A', PieceKind::Core), (32, 'A', PieceKind::Monarch),
+            (39, 'A', PieceKind::BruteR), (41, 'B', PieceKind::Monarch),
+        ]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        // an untrained network, the proof has to come from the search itself
+        let net = Arc::new(Network::new(16, &mut rng));
+        // RAVE keeps statistics for the children the network adds too
+        for rave in [None, Some(500.0)] {
+            let cfg = MctsConfig { iterations: 2000, network: Some(net.clone()), rave, ..MctsConfig::default() };
+            let result = mcts_action(&state, &cfg, &mut rng);
+            assert_eq!(result.best, Some(Move { actor: 32, kind: MoveKind::Move, dest: Some(20), captured: None }));
+            assert_eq!(result.proof, Proof::Win(2));
+            assert_eq!(result.visits.len(), state.legal_moves().len());
+        }
+    }
+
+    #[test]
+    fn rave_key_ignores_origin_square() {
+        let state = position('A', &[(30, 'A', PieceKind::Core), (32, 'A', PieceKind::Monarch), (20, 'B', PieceKind::Monarch)]);
+        let mv = |actor, dest| Move { actor, kind: MoveKind::Move, dest: Some(dest), captured: None };
+        let moved = position('A', &[(30, 'A', PieceKind::Core), (22, 'A', PieceKind::Monarch)]);
+        assert_eq!(rave_key(&state, &mv(32, 21)), rave_key(&moved, &mv(22, 21)));
+        assert_ne!(rave_key(&state, &mv(32, 21)), rave_key(&state, &mv(32, 31)));
+        // same square, other player's Monarch
+        let theirs = position('B', &[(20, 'B', PieceKind::Monarch)]);
+        assert_ne!(rave_key(&state, &mv(32, 21)), rave_key(&theirs, &mv(20, 21)));
+    }
+
+    #[test]
+    fn returns_immediate_touch_at_once() {
+        // A's Core hops two squares next to B's Core
+        let state = position('A', &[
+            (16, 'A', PieceKind::Core), (15, 'A', PieceKind::Monarch), (49, 'B', PieceKind::Core), (50, 'B', PieceKind::Monarch),
+        ]);
+        let cfg = MctsConfig { iterations: 10000, playout_max: 20, ..MctsConfig::default() };
+        let result = mcts_action(&state, &cfg, &mut rand::rngs::StdRng::seed_from_u64(0));
+        let touch = state.apply_move(&result.best.unwrap()).is_terminal();
+        assert_eq!(touch, Some('A'));
+        assert_eq!(result.proof, Proof::Win(0));
+        assert_eq!(result.proof_length(), Some(1));
+        assert!(result.tree.iterations <= state.legal_moves().len());
+    }
+
+    #[test]
+    fn avoids_proven_losses() {
+        // B's Core can hop next to A's frozen Core; every A move except the
+        // Monarch capture that freezes B's Core loses immediately
+        let state = position('A', &[
+            (5, 'A', PieceKind::Core), (29, 'A', PieceKind::Monarch), (38, 'B', PieceKind::Core), (39, 'B', PieceKind::Monarch),
+        ]);
+        let cfg = MctsConfig { iterations: 500, playout_max: 20, ..MctsConfig::default() };
+        for seed in 0..4 {
+            let result = mcts_action(&state, &cfg, &mut rand::rngs::StdRng::seed_from_u64(seed));
+            assert_eq!(result.best, Some(Move { actor: 29, kind: MoveKind::Move, dest: Some(39), captured: Some(39) }));
+            assert_eq!(result.proof, Proof::Unknown);
+        }
+    }
+}
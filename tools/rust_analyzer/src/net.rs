@@ -0,0 +1,357 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use rand::Rng;
+
+use crate::state::{idx, rc, Move, Piece, PieceKind, Ruleset, State, BOARD_SIZE, COLS, ROWS};
+
+const MAGIC: &[u8; 4] = b"CBNN";
+const VERSION: u8 = 1;
+
+// Inputs are 10 piece planes over the board seen from the side to move (its own
+// pieces in planes 0-4 by PieceKind, the opponent's in 5-9, rows flipped for B
+// so both sides move towards higher rows) and two flags for who moved a Core last.
+pub const INPUTS: usize = 10 * BOARD_SIZE + 2;
+// one output per piece kind and target square, which tells every legal move apart
+pub const ACTIONS: usize = 5 * BOARD_SIZE;
+
+fn view(state: &State, square: usize) -> usize {
+    if state.turn == 'A' { return square }
+    let (r, c) = rc(square);
+    idx(ROWS - 1 - r, c)
+}
+
+// indices of the inputs that are 1, all others are 0
+pub fn features(state: &State) -> Vec<u16> {
+    let mut active = Vec::with_capacity(12);
+    for (i, p) in state.board.iter().enumerate() {
+        if let Some(p) = p {
+            let plane = p.kind as usize + if p.owner == state.turn { 0 } else { 5 };
+            active.push((plane * BOARD_SIZE + view(state, i)) as u16);
+        }
+    }
+    match state.last_core_moved_by {
+        Some(w) if w == state.turn => active.push((INPUTS - 2) as u16),
+        Some(_) => active.push((INPUTS - 1) as u16),
+        None => {}
+    }
+    active
+}
+
+// PieceKind in declaration order, which is the plane order
+const KINDS: [PieceKind; 5] = [PieceKind::Core, PieceKind::Monarch, PieceKind::BruteL, PieceKind::BruteR, PieceKind::Tank];
+
+// The position behind `features` as the side to move saw it, with that side
+// as A. None if an index is out of range or two pieces share a square.
+pub fn state_from_features(active: &[u16]) -> Option<State> {
+    let mut state = State { board: [None; BOARD_SIZE], turn: 'A', last_core_moved_by: None, rules: Ruleset::default() };
+    for &i in active {
+        let i = i as usize;
+        match i {
+            _ if i == INPUTS - 2 => state.last_core_moved_by = Some('A'),
+            _ if i == INPUTS - 1 => state.last_core_moved_by = Some('B'),
+            _ if i < 10 * BOARD_SIZE => {
+                let (plane, square) = (i / BOARD_SIZE, i % BOARD_SIZE);
+                let piece = Piece { owner: if plane < 5 { 'A' } else { 'B' }, kind: KINDS[plane % 5] };
+                if state.board[square].replace(piece).is_some() { return None }
+            }
+            _ => return None,
+        }
+    }
+    Some(state)
+}
+
+pub fn action_index(state: &State, mv: &Move) -> u16 {
+    let piece = state.board[mv.actor as usize].expect("actor must exist");
+    let target = mv.dest.or(mv.captured).expect("every move has a target square") as usize;
+    (piece.kind as usize * BOARD_SIZE + view(state, target)) as u16
+}
+
+// A searched position: the legal moves as action indices with the share of
+// root visits each got, the search's own value and the final result, both for
+// the side to move.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub features: Vec<u16>,
+    pub actions: Vec<u16>,
+    pub policy: Vec<f32>,
+    pub search_value: f32,
+    pub value: f32,
+}
+
+impl Sample {
+    pub fn new(state: &State, visits: &[(Move, u32)], search_value: f32, value: f32) -> Sample {
+        let total = visits.iter().map(|&(_, n)| n).sum::<u32>().max(1) as f32;
+        Sample {
+            features: features(state),
+            actions: visits.iter().map(|(m, _)| action_index(state, m)).collect(),
+            policy: visits.iter().map(|&(_, n)| n as f32 / total).collect(),
+            search_value,
+            value,
+        }
+    }
+}
+
+// left-right mirror of an input or action index: the rules don't change when
+// the files are reversed as long as the two Brutes swap roles
+fn mirror_index(i: u16) -> u16 {
+    let i = i as usize;
+    if i >= 10 * BOARD_SIZE { return i as u16 }
+    let (plane, square) = (i / BOARD_SIZE, i % BOARD_SIZE);
+    let plane = match plane % 5 { 2 => plane + 1, 3 => plane - 1, _ => plane };
+    let (r, c) = rc(square);
+    (plane * BOARD_SIZE + idx(r, COLS - 1 - c)) as u16
+}
+
+impl Sample {
+    pub fn mirrored(&self) -> Sample {
+        let mut features: Vec<u16> = self.features.iter().map(|&f| mirror_index(f)).collect();
+        features.sort_unstable();
+        Sample { features, actions: self.actions.iter().map(|&a| mirror_index(a)).collect(), ..self.clone() }
+    }
+}
+
+fn sigmoid(x: f32) -> f32 { 1.0 / (1.0 + (-x).exp()) }
+
+// One hidden ReLU layer shared by a policy head (softmax over the legal moves)
+// and a value head (sigmoid, the side to move's chance of winning). Inputs are
+// sparse, so the first layer is a sum of the rows of the active inputs.
+#[derive(Clone)]
+pub struct Network {
+    hidden: usize,
+    // INPUTS rows of `hidden` weights
+    w1: Vec<f32>,
+    b1: Vec<f32>,
+    // ACTIONS rows of `hidden` weights
+    wp: Vec<f32>,
+    bp: Vec<f32>,
+    wv: Vec<f32>,
+    bv: f32,
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Network {{ {} -> {} -> {} + 1 }}", INPUTS, self.hidden, ACTIONS)
+    }
+}
+
+impl Network {
+    pub fn new(hidden: usize, rng: &mut impl Rng) -> Network {
+        // about a dozen inputs are active, so scale the first layer for that fan-in
+        let mut init = |n: usize, scale: f32| (0..n).map(|_| rng.gen_range(-scale..scale)).collect::<Vec<f32>>();
+        let w1 = init(INPUTS * hidden, 0.3);
+        let head = 1.0 / (hidden as f32).sqrt();
+        let wp = init(ACTIONS * hidden, head * 0.1);
+        let wv = init(hidden, head);
+        Network { hidden, w1, b1: vec![0.0; hidden], wp, bp: vec![0.0; ACTIONS], wv, bv: 0.0 }
+    }
+
+    fn hidden_layer(&self, features: &[u16]) -> Vec<f32> {
+        let mut h = self.b1.clone();
+        for &f in features {
+            let row = &self.w1[f as usize * self.hidden..(f as usize + 1) * self.hidden];
+            for (x, w) in h.iter_mut().zip(row) { *x += w; }
+        }
+        for x in h.iter_mut() { *x = x.max(0.0); }
+        h
+    }
+
+    fn logit(&self, h: &[f32], action: u16) -> f32 {
+        let row = &self.wp[action as usize * self.hidden..(action as usize + 1) * self.hidden];
+        self.bp[action as usize] + row.iter().zip(h).map(|(w, x)| w * x).sum::<f32>()
+    }
+
+    fn value_logit(&self, h: &[f32]) -> f32 { self.bv + self.wv.iter().zip(h).map(|(w, x)| w * x).sum::<f32>() }
+
+    fn softmax(&self, h: &[f32], actions: &[u16]) -> Vec<f32> {
+        let logits: Vec<f32> = actions.iter().map(|&a| self.logit(h, a)).collect();
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let exp: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+        let sum: f32 = exp.iter().sum();
+        exp.into_iter().map(|e| e / sum).collect()
+    }
+
+    // priors for `moves` in the same order, and the side to move's winning chance
+    pub fn evaluate(&self, state: &State, moves: &[Move]) -> (Vec<f32>, f32) {
+        let h = self.hidden_layer(&features(state));
+        let actions: Vec<u16> = moves.iter().map(|m| action_index(state, m)).collect();
+        let priors = if actions.is_empty() { Vec::new() } else { self.softmax(&h, &actions) };
+        (priors, sigmoid(self.value_logit(&h)))
+    }
+
+    // One SGD step on cross-entropy for the policy and the value. Returns both losses.
+    pub fn train_step(&mut self, sample: &Sample, lr: f32) -> (f32, f32) {
+        let hd = self.hidden;
+        let h = self.hidden_layer(&sample.features);
+        let mut dh = vec![0.0f32; hd];
+        let mut policy_loss = 0.0;
+        if !sample.actions.is_empty() {
+            let p = self.softmax(&h, &sample.actions);
+            for ((&a, &pi), &target) in sample.actions.iter().zip(&p).zip(&sample.policy) {
+                if target > 0.0 { policy_loss -= target * pi.max(1e-7).ln(); }
+                let g = pi - target;
+                let row = &mut self.wp[a as usize * hd..(a as usize + 1) * hd];
+                for ((w, d), x) in row.iter_mut().zip(dh.iter_mut()).zip(&h) {
+                    *d += g * *w;
+                    *w -= lr * g * x;
+                }
+                self.bp[a as usize] -= lr * g;
+            }
+        }
+        let v = sigmoid(self.value_logit(&h));
+        let value_loss = -(sample.value * v.max(1e-7).ln() + (1.0 - sample.value) * (1.0 - v).max(1e-7).ln());
+        let g = v - sample.value;
+        for ((w, d), x) in self.wv.iter_mut().zip(dh.iter_mut()).zip(&h) {
+            *d += g * *w;
+            *w -= lr * g * x;
+        }
+        self.bv -= lr * g;
+        // back through the ReLU into the rows of the active inputs
+        for (d, x) in dh.iter_mut().zip(&h) { if *x <= 0.0 { *d = 0.0; } }
+        for (b, d) in self.b1.iter_mut().zip(&dh) { *b -= lr * d; }
+        for &f in &sample.features {
+            let row = &mut self.w1[f as usize * hd..(f as usize + 1) * hd];
+            for (w, d) in row.iter_mut().zip(&dh) { *w -= lr * d; }
+        }
+        (policy_loss, value_loss)
+    }
+
+    // File layout: "CBNN", version, input/action/hidden sizes as u32, then the
+    // weights as little-endian f32 in field order.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = Vec::with_capacity(17 + 4 * (self.w1.len() + self.wp.len() + ACTIONS + 3 * self.hidden + 1));
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        for n in [INPUTS, ACTIONS, self.hidden] { out.extend_from_slice(&(n as u32).to_le_bytes()); }
+        for part in [&self.w1, &self.b1, &self.wp, &self.bp, &self.wv] {
+            for w in part.iter() { out.extend_from_slice(&w.to_le_bytes()); }
+        }
+        out.extend_from_slice(&self.bv.to_le_bytes());
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+        fs::write(path, out)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Network> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), what));
+        let mut bytes = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        if bytes.len() < 17 || &bytes[..4] != MAGIC { return Err(invalid("not a network file")); }
+        let size = |i: usize| u32::from_le_bytes(bytes[5 + 4 * i..9 + 4 * i].try_into().unwrap()) as usize;
+        if bytes[4] != VERSION || size(0) != INPUTS || size(1) != ACTIONS { return Err(invalid("version or input encoding differs")); }
+        let hidden = size(2);
+        let floats = INPUTS * hidden + hidden + ACTIONS * hidden + ACTIONS + hidden + 1;
+        if bytes.len() != 17 + 4 * floats { return Err(invalid("truncated weights")); }
+        let mut values = bytes[17..].chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap()));
+        let mut take = |n: usize| values.by_ref().take(n).collect::<Vec<f32>>();
+        let (w1, b1, wp, bp, wv) = (take(INPUTS * hidden), take(hidden), take(ACTIONS * hidden), take(ACTIONS), take(hidden));
+        let bv = take(1)[0];
+        Ok(Network { hidden, w1, b1, wp, bp, wv, bv })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Piece, PieceKind};
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn action_indices_tell_legal_moves_apart() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut state = State::default();
+        for _ in 0..200 {
+            if state.is_terminal().is_some() { state = State::default(); }
+            let moves = state.legal_moves();
+            if moves.is_empty() { state = State::default(); continue; }
+            let indices: HashSet<u16> = moves.iter().map(|m| action_index(&state, m)).collect();
+            assert_eq!(indices.len(), moves.len());
+            assert!(indices.iter().all(|&a| (a as usize) < ACTIONS));
+            state = state.apply_move(moves.choose(&mut rng).unwrap());
+        }
+    }
+
+    #[test]
+    fn both_sides_see_the_start_alike() {
+        let start = State::default();
+        let b_to_move = State { turn: 'B', ..start.clone() };
+        let mut a = features(&start);
+        let mut b = features(&b_to_move);
+        a.sort();
+        b.sort();
+        // B's pieces mirror A's across the middle row
+        assert_eq!(a, b);
+        assert!(features(&State { last_core_moved_by: Some('A'), ..start.clone() }).contains(&((INPUTS - 2) as u16)));
+    }
+
+    #[test]
+    fn features_decode_to_the_position() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+        let mut state = State::default();
+        for _ in 0..7 { let moves = state.legal_moves(); state = state.apply_move(moves.choose(&mut rng).unwrap()); }
+        assert_eq!(state.turn, 'B');
+        let decoded = state_from_features(&features(&state)).unwrap();
+        let mut a = features(&decoded);
+        let mut b = features(&state);
+        a.sort();
+        b.sort();
+        assert_eq!(a, b);
+        assert_eq!(decoded.legal_moves().len(), state.legal_moves().len());
+        assert!(state_from_features(&[0, 0]).is_none());
+    }
+
+    #[test]
+    fn mirrored_samples_match_mirrored_positions() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(9);
+        let mut state = State::default();
+        for _ in 0..6 { let moves = state.legal_moves(); state = state.apply_move(moves.choose(&mut rng).unwrap()); }
+        let mirror = |i: usize| { let (r, c) = rc(i); idx(r, COLS - 1 - c) };
+        let mut flipped = State { board: [None; BOARD_SIZE], ..state.clone() };
+        for (i, p) in state.board.iter().enumerate() {
+            flipped.board[mirror(i)] = p.map(|p| Piece { kind: match p.kind { PieceKind::BruteL => PieceKind::BruteR, PieceKind::BruteR => PieceKind::BruteL, k => k }, ..p });
+        }
+        let visits: Vec<(Move, u32)> = state.legal_moves().into_iter().zip(1..).collect();
+        let flipped_visits: Vec<(Move, u32)> = visits.iter().map(|&(m, n)| (Move { actor: mirror(m.actor as usize) as u8, dest: m.dest.map(|d| mirror(d as usize) as u8), captured: m.captured.map(|c| mirror(c as usize) as u8), ..m }, n)).collect();
+        let sample = Sample::new(&state, &visits, 0.5, 1.0).mirrored();
+        let expected = Sample::new(&flipped, &flipped_visits, 0.5, 1.0);
+        let mut features = expected.features.clone();
+        features.sort_unstable();
+        assert_eq!(sample.features, features);
+        assert_eq!(sample.actions, expected.actions);
+        assert_eq!(flipped.legal_moves().len(), visits.len());
+    }
+
+    #[test]
+    fn training_fits_a_sample() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut net = Network::new(16, &mut rng);
+        let state = State::default();
+        let moves = state.legal_moves();
+        let visits: Vec<(Move, u32)> = moves.iter().enumerate().map(|(i, &m)| (m, if i == 2 { 90 } else { 1 })).collect();
+        let sample = Sample::new(&state, &visits, 0.5, 1.0);
+        let (p0, v0) = net.train_step(&sample, 0.0);
+        for _ in 0..200 { net.train_step(&sample, 0.05); }
+        let (p1, v1) = net.train_step(&sample, 0.0);
+        assert!(p1 < p0 && v1 < v0, "{} {} {} {}", p0, p1, v0, v1);
+        let (priors, value) = net.evaluate(&state, &moves);
+        assert_eq!(priors.iter().copied().fold(0.0, f32::max), priors[2]);
+        assert!(value > 0.9);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let net = Network::new(8, &mut rng);
+        let path = std::env::temp_dir().join(format!("cbnn-test-{}.cbnn", std::process::id()));
+        net.save(&path).unwrap();
+        let loaded = Network::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mut state = State::default();
+        state.board[idx(3, 3)] = Some(Piece { owner: 'B', kind: PieceKind::Tank });
+        let moves = state.legal_moves();
+        assert_eq!(net.evaluate(&state, &moves), loaded.evaluate(&state, &moves));
+    }
+}
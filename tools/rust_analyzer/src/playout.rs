@@ -0,0 +1,373 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+
+use crate::state::{chebyshev_distance, opponent, Move, PieceKind, State};
+
+// how a playout ended; Eval is an estimate of A's score when a policy cut it short
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlayoutResult {
+    Win(char),
+    Draw,
+    Eval(f64),
+}
+
+// Picks moves for both sides during MCTS simulations. `moves` is never empty.
+pub trait PlayoutPolicy {
+    fn choose(&self, s: &State, moves: &[Move], rng: &mut dyn RngCore) -> Move;
+
+    // checked before every ply, Some(score for A) ends the playout there
+    fn cutoff(&self, _s: &State, _ply: usize) -> Option<f64> { None }
+}
+
+// names accepted by `Playout::by_name`, in the order the CLI lists them
+pub const PLAYOUT_NAMES: [&str; 5] = ["uniform", "epsilon-greedy", "capture-first", "core-approach", "eval-cutoff"];
+
+// Description of a policy, cheap to clone, so MctsConfig stays plain data
+#[derive(Clone, PartialEq, Debug)]
+pub enum Playout {
+    Uniform,
+    // greedy on `move_score`, uniform with probability epsilon
+    EpsilonGreedy(f64),
+    CaptureFirst,
+    CoreApproach,
+    // uniform for this many plies, then `evaluate` with the weights
+    EvalCutoff(usize, Arc<EvalWeights>),
+}
+
+impl Playout {
+    pub fn by_name(name: &str, epsilon: f64, cutoff_plies: usize, weights: Arc<EvalWeights>) -> Option<Playout> {
+        match name {
+            "uniform" => Some(Playout::Uniform),
+            "epsilon-greedy" => Some(Playout::EpsilonGreedy(epsilon)),
+            "capture-first" => Some(Playout::CaptureFirst),
+            "core-approach" => Some(Playout::CoreApproach),
+            "eval-cutoff" => Some(Playout::EvalCutoff(cutoff_plies, weights)),
+            _ => None,
+        }
+    }
+
+    pub fn policy(&self) -> Box<dyn PlayoutPolicy> {
+        match self {
+            Playout::Uniform => Box::new(Uniform),
+            Playout::EpsilonGreedy(epsilon) => Box::new(EpsilonGreedy { epsilon: *epsilon }),
+            Playout::CaptureFirst => Box::new(CaptureFirst),
+            Playout::CoreApproach => Box::new(CoreApproach),
+            Playout::EvalCutoff(plies, weights) => Box::new(EvalCutoff { inner: Uniform, plies: *plies, weights: weights.clone() }),
+        }
+    }
+}
+
+// `trace` collects every move played together with the state it was played from
+pub fn run_playout(policy: &dyn PlayoutPolicy, mut s: State, rng: &mut dyn RngCore, max_moves: usize, mut trace: impl FnMut(&State, &Move)) -> PlayoutResult {
+    for ply in 0..max_moves {
+        if let Some(w) = s.is_terminal() { return PlayoutResult::Win(w); }
+        if let Some(v) = policy.cutoff(&s, ply) { return PlayoutResult::Eval(v); }
+        let moves = s.legal_moves();
+        if moves.is_empty() { return PlayoutResult::Draw; }
+        let chosen = policy.choose(&s, &moves, rng);
+        trace(&s, &chosen);
+        s = s.apply_move(&chosen);
+    }
+    match s.is_terminal() { Some(w) => PlayoutResult::Win(w), None => PlayoutResult::Draw }
+}
+
+// ---------------- move features ----------------
+
+// Core squares seen from the player to move, looked up once per ply so moves
+// can be scored without applying them
+struct Cores {
+    mine: Option<usize>,
+    theirs: Option<usize>,
+}
+
+impl Cores {
+    fn of(s: &State) -> Cores {
+        Cores { mine: s.find_piece(s.turn, PieceKind::Core), theirs: s.find_piece(opponent(s.turn), PieceKind::Core) }
+    }
+
+    // only a Core move can bring the Cores together
+    fn touches(&self, m: &Move) -> bool {
+        match (self.mine, self.theirs, m.dest) {
+            (Some(mine), Some(theirs), Some(dest)) => m.actor as usize == mine && dest as usize != theirs && chebyshev_distance(dest as usize, theirs) == 1,
+            _ => false,
+        }
+    }
+
+    // distance between the Cores after `m`, None once either is gone
+    fn distance_after(&self, m: &Move) -> Option<usize> {
+        let (mine, theirs) = (self.mine?, self.theirs?);
+        if m.captured == Some(theirs as u8) { return None; }
+        let mine = if m.actor as usize == mine { m.dest.map_or(mine, usize::from) } else { mine };
+        Some(chebyshev_distance(mine, theirs))
+    }
+}
+
+fn piece_value(kind: PieceKind) -> f64 {
+    match kind { PieceKind::Core => 0.0, PieceKind::Monarch => 3.0, PieceKind::BruteL | PieceKind::BruteR | PieceKind::Tank => 1.0 }
+}
+
+// greedy preference used by EpsilonGreedy: touch, take the enemy Core, win material, approach
+fn move_score(s: &State, cores: &Cores, m: &Move) -> f64 {
+    if cores.touches(m) { return 1000.0; }
+    if m.captured.is_some() && m.captured.map(usize::from) == cores.theirs { return 100.0; }
+    let material = m.captured.and_then(|c| s.board[c as usize]).map_or(0.0, |p| 10.0 * piece_value(p.kind));
+    let approach = match (cores.mine.zip(cores.theirs), cores.distance_after(m)) {
+        (Some((mine, theirs)), Some(after)) => chebyshev_distance(mine, theirs) as f64 - after as f64,
+        _ => 0.0,
+    };
+    material + approach
+}
+
+// ---------------- policies ----------------
+
+pub struct Uniform;
+
+impl PlayoutPolicy for Uniform {
+    fn choose(&self, _s: &State, moves: &[Move], rng: &mut dyn RngCore) -> Move {
+        *moves.choose(rng).unwrap()
+    }
+}
+
+pub struct EpsilonGreedy {
+    pub epsilon: f64,
+}
+
+impl PlayoutPolicy for EpsilonGreedy {
+    fn choose(&self, s: &State, moves: &[Move], rng: &mut dyn RngCore) -> Move {
+        if rng.gen::<f64>() < self.epsilon { return *moves.choose(rng).unwrap(); }
+        let cores = Cores::of(s);
+        // ties are broken uniformly by reservoir sampling
+        let (mut best, mut best_score, mut ties) = (moves[0], f64::NEG_INFINITY, 0u32);
+        for m in moves {
+            let score = move_score(s, &cores, m);
+            if score > best_score { best = *m; best_score = score; ties = 1; }
+            else if score == best_score { ties += 1; if rng.gen_range(0..ties) == 0 { best = *m; } }
+        }
+        best
+    }
+}
+
+// a touch if there is one, otherwise any capture, otherwise anything
+pub struct CaptureFirst;
+
+impl PlayoutPolicy for CaptureFirst {
+    fn choose(&self, s: &State, moves: &[Move], rng: &mut dyn RngCore) -> Move {
+        let cores = Cores::of(s);
+        if let Some(m) = moves.iter().find(|m| cores.touches(m)) { return *m; }
+        let captures: Vec<&Move> = moves.iter().filter(|m| m.captured.is_some()).collect();
+        match captures.choose(rng) { Some(m) => **m, None => *moves.choose(rng).unwrap() }
+    }
+}
+
+// samples moves with weight exp(-dist / 2) on the Core distance they leave, a touch weighs 100
+pub struct CoreApproach;
+
+impl PlayoutPolicy for CoreApproach {
+    fn choose(&self, s: &State, moves: &[Move], rng: &mut dyn RngCore) -> Move {
+        let cores = Cores::of(s);
+        let weight = |m: &Move| {
+            if cores.touches(m) { return 100.0; }
+            cores.distance_after(m).map_or(1.0, |d| (-0.5 * d as f64).exp())
+        };
+        *moves.choose_weighted(rng, weight).unwrap()
+    }
+}
+
+pub struct EvalCutoff<P> {
+    pub inner: P,
+    pub plies: usize,
+    pub weights: Arc<EvalWeights>,
+}
+
+impl<P: PlayoutPolicy> PlayoutPolicy for EvalCutoff<P> {
+    fn choose(&self, s: &State, moves: &[Move], rng: &mut dyn RngCore) -> Move {
+        self.inner.choose(s, moves, rng)
+    }
+
+    fn cutoff(&self, s: &State, ply: usize) -> Option<f64> {
+        (ply >= self.plies).then(|| evaluate(s, &self.weights))
+    }
+}
+
+// ---------------- evaluation ----------------
+
+// Each feature is A's count minus B's, except the Core distance, which is
+// signed by the side to move since the Cores are the same distance apart for
+// both. A side without its Core can no longer touch at all, a Core away from
+// its Monarch can't move until the Monarch comes back.
+pub const EVAL_FEATURES: usize = 7;
+pub const EVAL_FEATURE_NAMES: [&str; EVAL_FEATURES] = ["monarch", "tank", "brute", "missing_core", "frozen_core", "core_distance", "mobility"];
+const MOBILITY: usize = 6;
+
+// "name value" lines in file order, '#' starts a comment; the format of the
+// evaluation weights and of the search parameters --spsa writes
+pub fn name_values(text: &str) -> Result<Vec<(&str, f64)>, String> {
+    let mut values = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() { continue }
+        let (name, value) = line.split_once(char::is_whitespace).ok_or_else(|| format!("expected \"name value\", got {:?}", line))?;
+        values.push((name, value.trim().parse().map_err(|_| format!("invalid value {:?} for {}", value.trim(), name))?));
+    }
+    Ok(values)
+}
+
+// Linear weights on the features, the evaluation is the logistic of their sum.
+// Written by the Texel tuner as "name value" lines.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EvalWeights(pub [f64; EVAL_FEATURES]);
+
+// hand-picked: material at Monarch 3 and 1 for the rest, a lost Core 10, a frozen Core 0.5, all halved
+pub static DEFAULT_WEIGHTS: EvalWeights = EvalWeights([1.5, 0.5, 0.5, -5.0, -0.25, 0.0, 0.0]);
+
+impl Default for EvalWeights {
+    fn default() -> Self { DEFAULT_WEIGHTS }
+}
+
+impl EvalWeights {
+    // names left out keep their default weight
+    pub fn parse(text: &str) -> Result<EvalWeights, String> {
+        let mut weights = EvalWeights::default();
+        for (name, value) in name_values(text)? {
+            let i = EVAL_FEATURE_NAMES.iter().position(|&n| n == name).ok_or_else(|| format!("unknown feature {:?}", name))?;
+            weights.0[i] = value;
+        }
+        Ok(weights)
+    }
+
+    pub fn load(path: &Path) -> io::Result<EvalWeights> {
+        EvalWeights::parse(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::from("# evaluation weights, see playout::EVAL_FEATURE_NAMES\n");
+        for (name, w) in EVAL_FEATURE_NAMES.iter().zip(self.0) { text += &format!("{} {:.6}\n", name, w); }
+        fs::write(path, text)
+    }
+}
+
+fn side_features(s: &State, owner: char, f: &mut [f64; EVAL_FEATURES], sign: f64) {
+    for p in s.board.iter().flatten().filter(|p| p.owner == owner) {
+        match p.kind {
+            PieceKind::Monarch => f[0] += sign,
+            PieceKind::Tank => f[1] += sign,
+            PieceKind::BruteL | PieceKind::BruteR => f[2] += sign,
+            PieceKind::Core => {}
+        }
+    }
+    match (s.find_piece(owner, PieceKind::Core), s.find_piece(owner, PieceKind::Monarch)) {
+        (None, _) => f[3] += sign,
+        (Some(core), Some(monarch)) if State::are_adjacent_pos(core, monarch) => {}
+        _ => f[4] += sign,
+    }
+}
+
+// mobility needs move generation for both sides, so it is only counted when asked for
+pub fn eval_features(s: &State, mobility: bool) -> [f64; EVAL_FEATURES] {
+    let mut f = [0.0; EVAL_FEATURES];
+    side_features(s, 'A', &mut f, 1.0);
+    side_features(s, 'B', &mut f, -1.0);
+    if let (Some(a), Some(b)) = (s.find_piece('A', PieceKind::Core), s.find_piece('B', PieceKind::Core)) {
+        f[5] = chebyshev_distance(a, b) as f64 * if s.turn == 'A' { 1.0 } else { -1.0 };
+    }
+    if mobility {
+        let other = State { turn: opponent(s.turn), ..s.clone() };
+        let (mine, theirs) = (s.legal_moves().len() as f64, other.legal_moves().len() as f64);
+        f[MOBILITY] = if s.turn == 'A' { mine - theirs } else { theirs - mine };
+    }
+    f
+}
+
+pub fn eval_logit(f: &[f64; EVAL_FEATURES], weights: &EvalWeights) -> f64 {
+    f.iter().zip(weights.0).map(|(x, w)| x * w).sum()
+}
+
+// expected score for A in [0, 1], symmetric under swapping the sides
+pub fn evaluate(s: &State, weights: &EvalWeights) -> f64 {
+    if let Some(w) = s.is_terminal() { return if w == 'A' { 1.0 } else { 0.0 }; }
+    let x = eval_logit(&eval_features(s, weights.0[MOBILITY] != 0.0), weights);
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{position, MoveKind};
+    use rand::SeedableRng;
+
+    // A's Core on 16 next to its Monarch can hop to 38 next to B's Core on 49,
+    // or its Monarch can take B's Tank on 27
+    fn touch_or_capture() -> State {
+        position('A', &[
+            (16, 'A', PieceKind::Core), (15, 'A', PieceKind::Monarch), (27, 'B', PieceKind::Tank),
+            (49, 'B', PieceKind::Core), (50, 'B', PieceKind::Monarch),
+        ])
+    }
+
+    #[test]
+    fn greedy_policies_take_the_touch() {
+        let s = touch_or_capture();
+        let moves = s.legal_moves();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for name in ["epsilon-greedy", "capture-first"] {
+            let policy = Playout::by_name(name, 0.0, 0, Arc::new(DEFAULT_WEIGHTS)).unwrap().policy();
+            for _ in 0..20 {
+                let m = policy.choose(&s, &moves, &mut rng);
+                assert_eq!(s.apply_move(&m).is_terminal(), Some('A'), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn move_features_match_applied_moves() {
+        let s = touch_or_capture();
+        let cores = Cores::of(&s);
+        for m in s.legal_moves() {
+            let after = s.apply_move(&m);
+            assert_eq!(cores.touches(&m), after.is_terminal() == Some('A'), "{:?}", m);
+            let dist = after.find_piece('A', PieceKind::Core).zip(after.find_piece('B', PieceKind::Core)).map(|(a, b)| chebyshev_distance(a, b));
+            assert_eq!(cores.distance_after(&m), dist, "{:?}", m);
+        }
+        let capture = Move { actor: 15, kind: MoveKind::Move, dest: Some(27), captured: Some(27) };
+        assert_eq!(move_score(&s, &cores, &capture), 10.0);
+    }
+
+    #[test]
+    fn eval_cutoff_stops_after_its_plies() {
+        let policy = Playout::EvalCutoff(4, Arc::new(DEFAULT_WEIGHTS)).policy();
+        let mut plies = 0;
+        let result = run_playout(policy.as_ref(), State::default(), &mut rand::rngs::StdRng::seed_from_u64(1), 100, |_, _| plies += 1);
+        assert_eq!(plies, 4);
+        assert!(matches!(result, PlayoutResult::Eval(v) if (0.0..=1.0).contains(&v)));
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        let weights = EvalWeights([1.5, 0.5, 0.5, -5.0, -0.25, 0.1, 0.05]);
+        assert_eq!(evaluate(&State::default(), &DEFAULT_WEIGHTS), 0.5);
+        // only the side to move differs, which the Core distance and mobility weigh
+        let b_to_move = State { turn: 'B', ..State::default() };
+        assert!((evaluate(&State::default(), &weights) + evaluate(&b_to_move, &weights) - 1.0).abs() < 1e-12);
+        // B is a Monarch down, then the same position with colours and rows swapped
+        let s = position('A', &[(5, 'A', PieceKind::Core), (4, 'A', PieceKind::Monarch), (71, 'B', PieceKind::Core), (0, 'A', PieceKind::Tank)]);
+        let mirrored = position('B', &[(71, 'B', PieceKind::Core), (70, 'B', PieceKind::Monarch), (5, 'A', PieceKind::Core), (66, 'B', PieceKind::Tank)]);
+        assert!(evaluate(&s, &weights) > 0.9);
+        assert!((evaluate(&s, &weights) + evaluate(&mirrored, &weights) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn weights_round_trip_through_text() {
+        let weights = EvalWeights([1.25, 0.5, -0.75, -4.0, -0.5, 0.125, 0.03125]);
+        let path = std::env::temp_dir().join(format!("cb-weights-test-{}", std::process::id()));
+        weights.save(&path).unwrap();
+        assert_eq!(EvalWeights::load(&path).unwrap(), weights);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(EvalWeights::parse("# only one\ntank 2").unwrap().0[1], 2.0);
+        assert!(EvalWeights::parse("queen 9").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use crate::gamelog::{line_text, position_text};
+use crate::state::{Move, MoveKind, State};
+use crate::touch::{find_forced_touch, winning_moves, ForcedTouch};
+
+// The puzzle generator, on touch.rs's search and so under the analyzer's
+// rules, the ones its self-play and --record games are played by.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Touch,
+    // the attacker's line needs a Tank dash
+    TankDash,
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self { Theme::Touch => write!(f, "touch"), Theme::TankDash => write!(f, "tank dash") }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Puzzle {
+    // as in --record files, the side to move is the one that touches
+    pub position: String,
+    pub theme: Theme,
+    // the attacker's moves in the solution, used as the difficulty
+    pub moves: u8,
+    // the attacker's moves with the longest defence in between
+    pub solution: Vec<Move>,
+}
+
+// one line per puzzle: "<position>; <theme> in <moves>; <solution>"
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}; {} in {}; {}", self.position, self.theme, self.moves, line_text(&self.solution))
+    }
+}
+
+// A puzzle needs a forced touch within `max_moves` that only one first move
+// achieves in the fewest moves; a position with several such moves isn't one.
+pub fn find_puzzle(s: &State, max_moves: u8) -> Option<Puzzle> {
+    let moves = (1..=max_moves).find(|&n| !winning_moves(s, n).is_empty())?;
+    if winning_moves(s, moves).len() != 1 { return None }
+    let ForcedTouch::Win(solution) = find_forced_touch(s, moves) else { unreachable!("a winning move was found") };
+    let theme = if solution.iter().step_by(2).any(|m| m.kind == MoveKind::Dash) { Theme::TankDash } else { Theme::Touch };
+    Some(Puzzle { position: position_text(s), theme, moves, solution })
+}
+
+// Looks for puzzles before every move of a game played from `start`. Once a
+// puzzle is found, the same side's next positions in the game are usually
+// just its continuation and are skipped while they stay puzzles.
+pub fn puzzles_in_game(start: &State, moves: &[Move], max_moves: u8) -> Vec<Puzzle> {
+    let mut puzzles = Vec::new();
+    let mut s = start.clone();
+    // whether the position two plies back, with the same side to move, was a puzzle
+    let mut continuation = [false; 2];
+    for m in moves {
+        if s.is_terminal().is_some() || !s.legal_moves().contains(m) { break }
+        let found = find_puzzle(&s, max_moves);
+        let side = (s.turn == 'B') as usize;
+        if let (Some(puzzle), false) = (&found, continuation[side]) { puzzles.push(puzzle.clone()); }
+        continuation[side] = found.is_some();
+        s = s.apply_move(m);
+    }
+    puzzles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{idx, position, PieceKind};
+
+    fn touch_in_two() -> State {
+        position('A', &[(idx(0, 10), 'A', PieceKind::Monarch), (idx(1, 10), 'B', PieceKind::Tank), (idx(2, 9), 'A', PieceKind::Core), (idx(5, 9), 'B', PieceKind::Core)])
+    }
+
+    #[test]
+    fn touch_in_two_is_a_puzzle() {
+        let s = touch_in_two();
+        let puzzle = find_puzzle(&s, 2).unwrap();
+        assert_eq!((puzzle.theme, puzzle.moves), (Theme::Touch, 2));
+        assert_eq!(puzzle.to_string(), "A - ..........M/..........t/.........C./.........../.........../.........c./...........; touch in 2; move 10 20 -, move 21 32 -, move 31 53 -");
+        assert_eq!(find_puzzle(&s, 1), None);
+    }
+
+    #[test]
+    fn tank_dash_puzzle() {
+        // the dash takes B's Monarch, without it B's Core can't come over and touch first
+        let s = position('A', &[(idx(0, 6), 'A', PieceKind::Core), (idx(0, 8), 'A', PieceKind::Monarch), (idx(2, 5), 'A', PieceKind::Tank),
+            (idx(3, 5), 'B', PieceKind::Monarch), (idx(3, 6), 'B', PieceKind::Core), (idx(6, 0), 'B', PieceKind::BruteL)]);
+        let puzzle = find_puzzle(&s, 3).unwrap();
+        assert_eq!((puzzle.theme, puzzle.moves), (Theme::TankDash, 3));
+        assert_eq!(puzzle.solution[0].kind, MoveKind::Dash);
+    }
+
+    #[test]
+    fn continuations_are_not_repeated() {
+        let s = touch_in_two();
+        let puzzle = find_puzzle(&s, 2).unwrap();
+        assert_eq!(puzzles_in_game(&s, &puzzle.solution, 2), [puzzle]);
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use crate::balance::wilson;
+use crate::state::{Piece, PieceKind};
+
+// --format for the self-play statistics: the readable lines, one JSON object
+// per line (every game, then the summary, told apart by "type"), or CSV with
+// the game table, a blank line and the summary table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 3] = ["text", "json", "csv"];
+
+    pub fn by_name(name: &str) -> Option<OutputFormat> {
+        match name { "text" => Some(OutputFormat::Text), "json" => Some(OutputFormat::Json), "csv" => Some(OutputFormat::Csv), _ => None }
+    }
+}
+
+// PieceKind in declaration order, which indexes `captured`
+const KIND_NAMES: [&str; 5] = ["core", "monarch", "brute_l", "brute_r", "tank"];
+const OWNERS: [char; 2] = ['A', 'B'];
+
+fn kind_index(kind: PieceKind) -> usize { kind as usize }
+
+fn json_char(c: Option<char>) -> String { c.map_or("null".to_string(), |c| format!("\"{}\"", c)) }
+
+// 95% interval
+const Z: f64 = 1.96;
+
+pub struct GameRecord {
+    pub game: usize,
+    pub seed: u64,
+    pub winner: Option<char>,
+    pub plies: usize,
+    // pieces each side lost, by owner and kind
+    pub captured: [[u32; 5]; 2],
+    pub last_core_mover: Option<char>,
+    pub duration: Duration,
+}
+
+impl GameRecord {
+    // the pieces in `start` missing from `end`
+    pub fn losses(start: &[Piece], end: &[Piece]) -> [[u32; 5]; 2] {
+        let mut lost = [[0u32; 5]; 2];
+        for p in start { lost[(p.owner == 'B') as usize][kind_index(p.kind)] += 1; }
+        for p in end { lost[(p.owner == 'B') as usize][kind_index(p.kind)] -= 1; }
+        lost
+    }
+
+    pub fn json(&self) -> String {
+        let side = |i: usize| KIND_NAMES.iter().zip(self.captured[i]).map(|(k, n)| format!("\"{}\":{}", k, n)).collect::<Vec<_>>().join(",");
+        format!("{{\"type\":\"game\",\"game\":{},\"seed\":{},\"winner\":{},\"plies\":{},\"captured\":{{\"A\":{{{}}},\"B\":{{{}}}}},\"last_core_mover\":{},\"seconds\":{:.6}}}",
+            self.game, self.seed, json_char(self.winner), self.plies, side(0), side(1), json_char(self.last_core_mover), self.duration.as_secs_f64())
+    }
+
+    pub fn csv_header() -> String {
+        let captured: Vec<String> = OWNERS.iter().flat_map(|o| KIND_NAMES.iter().map(move |k| format!("captured_{}_{}", o.to_ascii_lowercase(), k))).collect();
+        format!("game,seed,winner,plies,{},last_core_mover,seconds", captured.join(","))
+    }
+
+    pub fn csv(&self) -> String {
+        let captured: Vec<String> = self.captured.iter().flatten().map(|n| n.to_string()).collect();
+        let c = |c: Option<char>| c.map_or(String::new(), String::from);
+        format!("{},{},{},{},{},{},{:.6}", self.game, self.seed, c(self.winner), self.plies, captured.join(","), c(self.last_core_mover), self.duration.as_secs_f64())
+    }
+}
+
+pub struct Summary {
+    pub games: usize,
+    pub a_wins: usize,
+    pub b_wins: usize,
+    pub draws: usize,
+    pub mean_plies: f64,
+    pub seconds: f64,
+}
+
+impl Summary {
+    pub fn of(records: &[GameRecord]) -> Summary {
+        let count = |w: Option<char>| records.iter().filter(|r| r.winner == w).count();
+        Summary {
+            games: records.len(),
+            a_wins: count(Some('A')),
+            b_wins: count(Some('B')),
+            draws: count(None),
+            mean_plies: records.iter().map(|r| r.plies).sum::<usize>() as f64 / records.len().max(1) as f64,
+            seconds: records.iter().map(|r| r.duration.as_secs_f64()).sum(),
+        }
+    }
+
+    // A's share of the decisive games, as the text summary reports it
+    pub fn first_rate(&self) -> Option<f64> {
+        let decisive = self.a_wins + self.b_wins;
+        (decisive > 0).then(|| self.a_wins as f64 / decisive as f64)
+    }
+
+    fn fields(&self) -> Vec<String> {
+        let (lo, hi) = wilson(self.a_wins as f64, self.a_wins + self.b_wins, Z);
+        let (draw_lo, draw_hi) = wilson(self.draws as f64, self.games, Z);
+        let draw_rate = self.draws as f64 / self.games.max(1) as f64;
+        vec![self.games.to_string(), self.a_wins.to_string(), self.b_wins.to_string(), self.draws.to_string(),
+            self.first_rate().map_or("null".to_string(), |r| format!("{:.6}", r)), format!("{:.6}", lo), format!("{:.6}", hi),
+            format!("{:.6}", draw_rate), format!("{:.6}", draw_lo), format!("{:.6}", draw_hi), format!("{:.3}", self.mean_plies), format!("{:.6}", self.seconds)]
+    }
+
+    const COLUMNS: [&'static str; 12] = ["games", "a_wins", "b_wins", "draws", "first_rate", "first_rate_low", "first_rate_high", "draw_rate", "draw_rate_low", "draw_rate_high", "mean_plies", "seconds"];
+
+    pub fn json(&self) -> String {
+        let fields: Vec<String> = Summary::COLUMNS.iter().zip(self.fields()).map(|(k, v)| format!("\"{}\":{}", k, v)).collect();
+        format!("{{\"type\":\"summary\",{}}}", fields.join(","))
+    }
+
+    pub fn csv_header() -> String { Summary::COLUMNS.join(",") }
+
+    pub fn csv(&self) -> String {
+        self.fields().iter().map(|v| if v == "null" { "" } else { v.as_str() }).collect::<Vec<_>>().join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    fn record(game: usize, winner: Option<char>) -> GameRecord {
+        let start: Vec<Piece> = State::default().board.iter().flatten().copied().collect();
+        let end: Vec<Piece> = start.iter().filter(|p| !(p.owner == 'B' && p.kind == PieceKind::Tank)).copied().collect();
+        GameRecord { game, seed: 42 + game as u64, winner, plies: 10 * (game + 1), captured: GameRecord::losses(&start, &end), last_core_mover: winner, duration: Duration::from_millis(5) }
+    }
+
+    #[test]
+    fn records_have_a_column_per_header_field() {
+        let r = record(0, Some('A'));
+        assert_eq!(r.captured[1], [0, 0, 0, 0, 1]);
+        assert_eq!(GameRecord::csv_header().split(',').count(), r.csv().split(',').count());
+        assert!(r.json().contains("\"B\":{\"core\":0,\"monarch\":0,\"brute_l\":0,\"brute_r\":0,\"tank\":1}"));
+        assert!(record(1, None).json().contains("\"winner\":null"));
+    }
+
+    #[test]
+    fn summary_counts_and_intervals() {
+        let records = [record(0, Some('A')), record(1, Some('A')), record(2, Some('B')), record(3, None)];
+        let s = Summary::of(&records);
+        assert_eq!((s.games, s.a_wins, s.b_wins, s.draws), (4, 2, 1, 1));
+        assert_eq!(s.mean_plies, 25.0);
+        assert_eq!(Summary::csv_header().split(',').count(), s.csv().split(',').count());
+        let json = s.json();
+        assert!(json.starts_with("{\"type\":\"summary\",\"games\":4,") && json.contains("\"first_rate\":0.666667"));
+        assert!(Summary::of(&[record(0, None)]).json().contains("\"first_rate\":null"));
+    }
+}
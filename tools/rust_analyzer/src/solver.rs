@@ -0,0 +1,531 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::state::{idx, Dash, Piece, Ruleset, State, BOARD_SIZE, COLS, ROWS};
+use crate::state::PieceKind;
+use crate::tablebase::TbValue;
+
+// pieces are packed 8 bits each into a u128 key together with the turn
+pub const MAX_PIECES: usize = 15;
+const CAPTURED: u8 = u8::MAX;
+const EMPTY: u8 = u8::MAX;
+const MAX_SQUARES: usize = 128;
+
+const MAGIC: &[u8; 4] = b"CBSV";
+const VERSION: u8 = 1;
+
+const CORE_STEPS: u8 = 2;
+
+// A board size with the back row both players start from, A on row 0 and B
+// on the last row in the same columns, like the full game's "L...MCT...R".
+// Written "5x5:.MCT." with rows first, rule changes follow as ",core=1" for
+// how far a Core moves and ",dash=off" or ",dash=enemy".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variant {
+    pub rows: usize,
+    pub cols: usize,
+    back_row: Vec<Option<PieceKind>>,
+    pub core_steps: u8,
+    pub dash: Dash,
+}
+
+impl Variant {
+    pub fn parse(text: &str) -> Result<Variant, String> {
+        let mut options = text.split(',');
+        let text = options.next().unwrap_or("");
+        let (mut core_steps, mut dash) = (CORE_STEPS, Dash::NonCore);
+        for option in options {
+            match option.split_once('=') {
+                Some(("core", n)) => core_steps = n.parse().ok().filter(|n| (1..=3).contains(n)).ok_or("core steps should be 1 to 3")?,
+                Some(("dash", name)) => dash = Dash::by_name(name).ok_or_else(|| format!("dash should be one of {}", Dash::NAMES.join(", ")))?,
+                _ => return Err(format!("unknown rule {:?}, expected core=N or dash=NAME", option)),
+            }
+        }
+        let (size, row) = text.split_once(':').ok_or("expected ROWSxCOLS:BACKROW, e.g. 5x5:.MCT.")?;
+        let (rows, cols) = size.split_once('x').ok_or("board size should look like 5x5")?;
+        let rows: usize = rows.parse().map_err(|_| "invalid row count")?;
+        let cols: usize = cols.parse().map_err(|_| "invalid column count")?;
+        let back_row = row.chars().map(|ch| match ch.to_ascii_uppercase() {
+            '.' => Ok(None),
+            'C' => Ok(Some(PieceKind::Core)),
+            'M' => Ok(Some(PieceKind::Monarch)),
+            'L' => Ok(Some(PieceKind::BruteL)),
+            'R' => Ok(Some(PieceKind::BruteR)),
+            'T' => Ok(Some(PieceKind::Tank)),
+            other => Err(format!("unknown piece {:?}", other)),
+        }).collect::<Result<Vec<_>, _>>()?;
+        // with fewer than 3 rows the Cores would start touching
+        if rows < 3 || rows * cols > MAX_SQUARES { return Err(format!("board needs at least 3 rows and at most {} squares", MAX_SQUARES)); }
+        if back_row.len() != cols { return Err(format!("back row has {} squares for {} columns", back_row.len(), cols)); }
+        let count = |kind| back_row.iter().filter(|&&k| k == Some(kind)).count();
+        if [PieceKind::Core, PieceKind::Monarch, PieceKind::BruteL, PieceKind::BruteR, PieceKind::Tank].iter().any(|&k| count(k) > 1) {
+            return Err("each side has at most one piece of each kind".into());
+        }
+        if count(PieceKind::Core) != 1 { return Err("each side needs its Core".into()); }
+        Ok(Variant { rows, cols, back_row, core_steps, dash })
+    }
+
+    fn start(&self) -> Pos {
+        let mut squares = [CAPTURED; MAX_PIECES];
+        let placed = self.pieces();
+        assert!(placed.len() <= MAX_PIECES);
+        for (i, (_, sq)) in placed.iter().enumerate() { squares[i] = *sq; }
+        Pos { squares, b_to_move: false }
+    }
+
+    // The start on the analyzer's board, so the variant can be played with
+    // mcts_action. It has to fit the full board.
+    pub fn state(&self) -> Result<State, String> {
+        if self.rows > ROWS || self.cols > COLS { return Err(format!("{} doesn't fit the {}x{} board", self, ROWS, COLS)); }
+        let mut board: [Option<Piece>; BOARD_SIZE] = [None; BOARD_SIZE];
+        for (piece, sq) in self.pieces() { board[self.square(sq)] = Some(piece); }
+        let rules = Ruleset { rows: self.rows, cols: self.cols, core_steps: self.core_steps, dash: self.dash };
+        Ok(State { board, turn: 'A', last_core_moved_by: None, rules })
+    }
+
+    // a solver square on the analyzer's board
+    fn square(&self, sq: u8) -> usize {
+        idx(sq as usize / self.cols, sq as usize % self.cols)
+    }
+
+    // A's pieces left to right, then B's
+    fn pieces(&self) -> Vec<(Piece, u8)> {
+        let mut out = Vec::new();
+        for (owner, row) in [('A', 0), ('B', self.rows - 1)] {
+            for (c, kind) in self.back_row.iter().enumerate() {
+                if let Some(kind) = kind { out.push((Piece { owner, kind: *kind }, (row * self.cols + c) as u8)); }
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = |k: &Option<PieceKind>| match k {
+            None => '.',
+            Some(PieceKind::Core) => 'C',
+            Some(PieceKind::Monarch) => 'M',
+            Some(PieceKind::BruteL) => 'L',
+            Some(PieceKind::BruteR) => 'R',
+            Some(PieceKind::Tank) => 'T',
+        };
+        write!(f, "{}x{}:{}", self.rows, self.cols, self.back_row.iter().map(letter).collect::<String>())?;
+        if self.core_steps != CORE_STEPS { write!(f, ",core={}", self.core_steps)?; }
+        if self.dash != Dash::NonCore { write!(f, ",dash={}", self.dash.name())?; }
+        Ok(())
+    }
+}
+
+// square of every piece (CAPTURED once taken) and whether B is to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Pos {
+    squares: [u8; MAX_PIECES],
+    b_to_move: bool,
+}
+
+impl Pos {
+    fn key(&self, k: usize) -> u128 {
+        let mut key = self.b_to_move as u128;
+        for &sq in &self.squares[..k] { key = key << 8 | sq as u128; }
+        key
+    }
+
+    fn from_key(mut key: u128, k: usize) -> Pos {
+        let mut squares = [CAPTURED; MAX_PIECES];
+        for sq in squares[..k].iter_mut().rev() { *sq = key as u8; key >>= 8; }
+        Pos { squares, b_to_move: key & 1 == 1 }
+    }
+}
+
+// The rules of state::State::legal_moves on an arbitrary board size, with the
+// variant's Core range and dash rule.
+struct Rules {
+    rows: isize,
+    cols: isize,
+    pieces: Vec<Piece>,
+    cores: [usize; 2],
+    monarchs: [Option<usize>; 2],
+    core_steps: isize,
+    dash: Dash,
+}
+
+impl Rules {
+    fn new(variant: &Variant) -> Rules {
+        let pieces: Vec<Piece> = variant.pieces().into_iter().map(|(p, _)| p).collect();
+        let find = |owner, kind| pieces.iter().position(|p| p.owner == owner && p.kind == kind);
+        Rules {
+            rows: variant.rows as isize,
+            cols: variant.cols as isize,
+            cores: [find('A', PieceKind::Core).unwrap(), find('B', PieceKind::Core).unwrap()],
+            monarchs: [find('A', PieceKind::Monarch), find('B', PieceKind::Monarch)],
+            pieces,
+            core_steps: variant.core_steps as isize,
+            dash: variant.dash,
+        }
+    }
+
+    fn adjacent(&self, a: u8, b: u8) -> bool {
+        let (a, b) = (a as isize, b as isize);
+        a != b && ((a / self.cols) - (b / self.cols)).abs() <= 1 && ((a % self.cols) - (b % self.cols)).abs() <= 1
+    }
+
+    // only reachable by a Core move, which ends the round for the mover
+    fn cores_touch(&self, pos: &Pos) -> bool {
+        let (a, b) = (pos.squares[self.cores[0]], pos.squares[self.cores[1]]);
+        a != CAPTURED && b != CAPTURED && self.adjacent(a, b)
+    }
+
+    fn successors(&self, pos: &Pos, out: &mut Vec<Pos>) {
+        out.clear();
+        if self.cores_touch(pos) { return; }
+        let mut board = [EMPTY; MAX_SQUARES];
+        for (i, &sq) in pos.squares[..self.pieces.len()].iter().enumerate() {
+            if sq != CAPTURED { board[sq as usize] = i as u8; }
+        }
+        let turn = if pos.b_to_move { 'B' } else { 'A' };
+        let side = pos.b_to_move as usize;
+        let at = |r: isize, c: isize| (r >= 0 && r < self.rows && c >= 0 && c < self.cols).then(|| (r * self.cols + c) as usize);
+        let enemy_or_empty = |sq: usize| board[sq] == EMPTY || self.pieces[board[sq] as usize].owner != turn;
+        let mut push = |actor: usize, dest: Option<usize>, captured: Option<usize>| {
+            let mut next = *pos;
+            if let Some(c) = captured { next.squares[board[c] as usize] = CAPTURED; }
+            if let Some(d) = dest { next.squares[actor] = d as u8; }
+            next.b_to_move = !pos.b_to_move;
+            out.push(next);
+        };
+        let forward = if turn == 'A' { 1 } else { -1 };
+        for (i, piece) in self.pieces.iter().enumerate() {
+            let sq = pos.squares[i];
+            if piece.owner != turn || sq == CAPTURED { continue; }
+            let (r, c) = (sq as isize / self.cols, sq as isize % self.cols);
+            match piece.kind {
+                PieceKind::Monarch => {
+                    for (dr, dc) in [(0, -1), (0, 1), (-1, -1), (-1, 1), (1, -1), (1, 1)] {
+                        if let Some(n) = at(r + dr, c + dc) {
+                            if enemy_or_empty(n) { push(i, Some(n), (board[n] != EMPTY).then_some(n)); }
+                        }
+                    }
+                }
+                PieceKind::Core => {
+                    let Some(m) = self.monarchs[side] else { continue };
+                    if pos.squares[m] == CAPTURED || !self.adjacent(sq, pos.squares[m]) { continue; }
+                    for dr in -1..=1 {
+                        for dc in -1..=1 {
+                            if dr == 0 && dc == 0 { continue; }
+                            for step in 1..=self.core_steps {
+                                if let Some(n) = at(r + dr * step, c + dc * step) {
+                                    if enemy_or_empty(n) { push(i, Some(n), (board[n] != EMPTY).then_some(n)); }
+                                }
+                            }
+                        }
+                    }
+                }
+                PieceKind::BruteL | PieceKind::BruteR | PieceKind::Tank => {
+                    for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                        if let Some(n) = at(r + dr, c + dc) {
+                            if board[n] == EMPTY { push(i, Some(n), None); }
+                        }
+                    }
+                    let Some(f) = at(r + forward, c) else { continue };
+                    if board[f] == EMPTY { continue; }
+                    if piece.kind == PieceKind::Tank {
+                        let jumped = self.pieces[board[f] as usize];
+                        let allowed = match self.dash { Dash::Off => false, Dash::NonCore => true, Dash::EnemyNonCore => jumped.owner != turn };
+                        if let Some(l) = at(r + 2 * forward, c) {
+                            if allowed && jumped.kind != PieceKind::Core && board[l] == EMPTY { push(i, Some(l), Some(f)); }
+                        }
+                    } else if self.pieces[board[f] as usize].owner != turn {
+                        push(i, None, Some(f));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Enumerates every position reachable from the start, then settles them all
+// by retrograde propagation, so positions that can repeat forever end up drawn.
+pub struct Solver {
+    variant: Variant,
+    rules: Rules,
+    keys: Vec<u128>,
+    index: HashMap<u128, u32>,
+    // successors of keys[..expanded], edges[offsets[i]..offsets[i + 1]]
+    offsets: Vec<u64>,
+    edges: Vec<u32>,
+}
+
+pub struct Solution {
+    pub positions: usize,
+    // per position, for the side to move
+    values: Vec<TbValue>,
+}
+
+impl Solution {
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let count = |f: fn(&TbValue) -> bool| self.values.iter().filter(|v| f(v)).count();
+        (count(|v| matches!(v, TbValue::Win(_))), count(|v| matches!(v, TbValue::Loss(_))), count(|v| *v == TbValue::Draw))
+    }
+}
+
+impl Solver {
+    pub fn new(variant: Variant) -> Solver {
+        let rules = Rules::new(&variant);
+        let start = variant.start().key(rules.pieces.len());
+        Solver { variant, rules, keys: vec![start], index: HashMap::from([(start, 0)]), offsets: vec![0], edges: Vec::new() }
+    }
+
+    pub fn variant(&self) -> &Variant { &self.variant }
+
+    pub fn positions(&self) -> usize { self.keys.len() }
+
+    pub fn is_enumerated(&self) -> bool { self.expanded() == self.keys.len() }
+
+    fn expanded(&self) -> usize { self.offsets.len() - 1 }
+
+    // expands up to `limit` more positions in breadth-first order
+    pub fn expand(&mut self, limit: usize) {
+        let k = self.rules.pieces.len();
+        let mut next = Vec::new();
+        for _ in 0..limit {
+            let Some(&key) = self.keys.get(self.expanded()) else { break };
+            self.rules.successors(&Pos::from_key(key, k), &mut next);
+            for child in &next {
+                let child = child.key(k);
+                let id = *self.index.entry(child).or_insert_with(|| { self.keys.push(child); self.keys.len() as u32 - 1 });
+                self.edges.push(id);
+            }
+            self.offsets.push(self.edges.len() as u64);
+        }
+    }
+
+    fn successors(&self, i: usize) -> &[u32] { &self.edges[self.offsets[i] as usize..self.offsets[i + 1] as usize] }
+
+    pub fn solve(&self) -> Solution {
+        assert!(self.is_enumerated(), "enumerate every position before solving");
+        let n = self.keys.len();
+        let k = self.rules.pieces.len();
+        let mut pred_offsets = vec![0u64; n + 1];
+        for &e in &self.edges { pred_offsets[e as usize + 1] += 1; }
+        for i in 0..n { pred_offsets[i + 1] += pred_offsets[i]; }
+        let mut fill = pred_offsets.clone();
+        let mut preds = vec![0u32; self.edges.len()];
+        for i in 0..n {
+            for &e in self.successors(i) { preds[fill[e as usize] as usize] = i as u32; fill[e as usize] += 1; }
+        }
+
+        let mut values = vec![TbValue::Draw; n];
+        let mut open: Vec<u32> = (0..n).map(|i| self.successors(i).len() as u32).collect();
+        let mut queue = VecDeque::new();
+        for (i, &key) in self.keys.iter().enumerate() {
+            if self.rules.cores_touch(&Pos::from_key(key, k)) { values[i] = TbValue::Loss(0); queue.push_back(i); }
+        }
+        // FIFO keeps distances non-decreasing, so the last child to settle a loss is the longest
+        let mut settled = vec![false; n];
+        for &i in &queue { settled[i] = true; }
+        while let Some(c) = queue.pop_front() {
+            for &p in &preds[pred_offsets[c] as usize..pred_offsets[c + 1] as usize] {
+                let p = p as usize;
+                if settled[p] { continue; }
+                match values[c] {
+                    TbValue::Loss(d) => { values[p] = TbValue::Win(d + 1); settled[p] = true; queue.push_back(p); }
+                    TbValue::Win(d) => {
+                        open[p] -= 1;
+                        if open[p] == 0 { values[p] = TbValue::Loss(d + 1); settled[p] = true; queue.push_back(p); }
+                    }
+                    TbValue::Draw => unreachable!(),
+                }
+            }
+        }
+        Solution { positions: n, values }
+    }
+
+    // value of the start for the first player, and the moves that achieve it
+    pub fn report(&self, solution: &Solution) -> (TbValue, Vec<String>) {
+        let value = solution.values[0];
+        let rank = |v: TbValue| match v {
+            // the child is scored for the opponent
+            TbValue::Loss(d) => (2, -(d as i32)),
+            TbValue::Draw => (1, 0),
+            TbValue::Win(d) => (0, d as i32),
+        };
+        let children = self.successors(0);
+        let best = children.iter().map(|&c| rank(solution.values[c as usize])).max();
+        let moves = children.iter().filter(|&&c| Some(rank(solution.values[c as usize])) == best).map(|&c| self.describe(0, c as usize)).collect();
+        (value, moves)
+    }
+
+    fn describe(&self, from: usize, to: usize) -> String {
+        let k = self.rules.pieces.len();
+        let (a, b) = (Pos::from_key(self.keys[from], k), Pos::from_key(self.keys[to], k));
+        let cols = self.variant.cols;
+        let name = |sq: u8| format!("({},{})", sq as usize / cols, sq as usize % cols);
+        let captured = (0..k).find(|&i| a.squares[i] != CAPTURED && b.squares[i] == CAPTURED);
+        let moved = (0..k).find(|&i| a.squares[i] != b.squares[i] && b.squares[i] != CAPTURED);
+        let mut out = match moved {
+            Some(i) => format!("{} {:?} {} -> {}", self.rules.pieces[i].owner, self.rules.pieces[i].kind, name(a.squares[i]), name(b.squares[i])),
+            // nothing moved, so a Brute took the piece in front of it
+            None => {
+                let victim = a.squares[captured.expect("every move changes the position")] as usize;
+                let behind = if a.b_to_move { victim + cols } else { victim - cols };
+                let brute = (0..k).find(|&i| a.squares[i] as usize == behind).expect("a Brute made the capture");
+                format!("{} {:?} {}", self.rules.pieces[brute].owner, self.rules.pieces[brute].kind, name(a.squares[brute]))
+            }
+        };
+        if let Some(i) = captured { out += &format!(" takes {:?} on {}", self.rules.pieces[i].kind, name(a.squares[i])); }
+        out
+    }
+
+    // ---------------- checkpoints ----------------
+
+    // variant, positions found so far and the successors of those expanded
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = self.variant.to_string();
+        let mut out = Vec::with_capacity(32 + self.keys.len() * 16 + self.edges.len() * 4 + self.offsets.len() * 8);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(text.len() as u8);
+        out.extend_from_slice(text.as_bytes());
+        out.extend_from_slice(&(self.keys.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(self.expanded() as u64).to_le_bytes());
+        for key in &self.keys { out.extend_from_slice(&key.to_le_bytes()); }
+        for off in &self.offsets[1..] { out.extend_from_slice(&off.to_le_bytes()); }
+        for e in &self.edges { out.extend_from_slice(&e.to_le_bytes()); }
+        // write then rename so an interrupted save keeps the previous checkpoint
+        let tmp = path.with_extension("tmp");
+        fs::File::create(&tmp)?.write_all(&out)?;
+        fs::rename(tmp, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Solver> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), what));
+        let mut bytes = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut bytes)?;
+        let mut at = 0usize;
+        let mut take = |n: usize| -> io::Result<&[u8]> {
+            let slice = bytes.get(at..at + n).ok_or_else(|| invalid("truncated checkpoint"))?;
+            at += n;
+            Ok(slice)
+        };
+        if take(4)? != MAGIC || take(1)?[0] != VERSION { return Err(invalid("not a solver checkpoint")); }
+        let len = take(1)?[0] as usize;
+        let text = String::from_utf8(take(len)?.to_vec()).map_err(|_| invalid("bad variant"))?;
+        let variant = Variant::parse(&text).map_err(|e| invalid(&e))?;
+        let u64_at = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap());
+        let positions = u64_at(take(8)?) as usize;
+        let expanded = u64_at(take(8)?) as usize;
+        let mut solver = Solver::new(variant);
+        solver.keys = take(positions * 16)?.chunks_exact(16).map(|b| u128::from_le_bytes(b.try_into().unwrap())).collect();
+        solver.offsets.extend(take(expanded * 8)?.chunks_exact(8).map(u64_at));
+        let edges = *solver.offsets.last().unwrap() as usize;
+        solver.edges = take(edges * 4)?.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect();
+        solver.index = solver.keys.iter().enumerate().map(|(i, &key)| (key, i as u32)).collect();
+        Ok(solver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn to_state(variant: &Variant, rules: &Rules, pos: &Pos) -> State {
+        let mut s = variant.state().unwrap();
+        s.board = [None; BOARD_SIZE];
+        for (p, &sq) in rules.pieces.iter().zip(&pos.squares) {
+            if sq != CAPTURED { s.board[variant.square(sq)] = Some(*p); }
+        }
+        s.turn = if pos.b_to_move { 'B' } else { 'A' };
+        s
+    }
+
+    #[test]
+    fn variant_names() {
+        let v = Variant::parse("5x5:.mct.").unwrap();
+        assert_eq!(v.to_string(), "5x5:.MCT.");
+        assert_eq!(v.pieces().len(), 6);
+        assert!(Variant::parse("5x5:.MT..").is_err());
+        assert!(Variant::parse("5x4:.MCT.").is_err());
+        assert!(Variant::parse("5x5:.MCC.").is_err());
+        let v = Variant::parse("7x11:L...MCT...R,dash=enemy,core=1").unwrap();
+        assert_eq!((v.core_steps, v.dash), (1, Dash::EnemyNonCore));
+        assert_eq!(Variant::parse(&v.to_string()), Ok(v));
+        assert_eq!(Variant::parse("5x5:.MCT.,core=2,dash=non-core").unwrap().to_string(), "5x5:.MCT.");
+        assert!(Variant::parse("5x5:.MCT.,core=4").is_err());
+    }
+
+    // the solver's rules give the same moves as State under the variant's rules
+    #[test]
+    fn rules_match_the_analyzer() {
+        assert_eq!(Variant::parse("7x11:L...MCT...R").unwrap().state().unwrap().board, State::default().board);
+        assert!(Variant::parse("4x12:.....MCT....").unwrap().state().is_err());
+        for text in ["7x11:L...MCT...R", "6x9:L.CMT...R,core=3,dash=enemy", "5x7:..MCT.R,core=1,dash=off"] {
+            let variant = Variant::parse(text).unwrap();
+            let solver = Solver::new(variant.clone());
+            check_rules(&variant, &solver.rules, Pos::from_key(solver.keys[0], solver.rules.pieces.len()));
+        }
+    }
+
+    fn check_rules(variant: &Variant, rules: &Rules, start: Pos) {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut next = Vec::new();
+        for _ in 0..20 {
+            let mut pos = start;
+            assert_eq!(to_state(variant, rules, &pos).board, variant.state().unwrap().board);
+            for _ in 0..200 {
+                let state = to_state(variant, rules, &pos);
+                rules.successors(&pos, &mut next);
+                let mut expected: Vec<_> = state.legal_moves().iter().map(|m| state.apply_move(m).board).collect();
+                let mut got: Vec<_> = next.iter().map(|p| to_state(variant, rules, p).board).collect();
+                if rules.cores_touch(&pos) { expected.clear(); }
+                let order = |b: &[Option<Piece>; BOARD_SIZE]| format!("{:?}", b);
+                expected.sort_by_key(order);
+                got.sort_by_key(order);
+                assert_eq!(got, expected);
+                let Some(p) = next.choose(&mut rng) else { break };
+                pos = *p;
+            }
+        }
+    }
+
+    #[test]
+    fn solution_agrees_with_every_move() {
+        let mut solver = Solver::new(Variant::parse("4x3:MC.").unwrap());
+        solver.expand(usize::MAX);
+        let solution = solver.solve();
+        for i in 0..solver.positions() {
+            let children: Vec<TbValue> = solver.successors(i).iter().map(|&c| solution.values[c as usize]).collect();
+            match solution.values[i] {
+                TbValue::Win(d) => assert!(children.contains(&TbValue::Loss(d - 1)) && !children.iter().any(|v| matches!(v, TbValue::Loss(e) if *e + 1 < d))),
+                TbValue::Loss(0) => assert!(children.is_empty()),
+                TbValue::Loss(d) => assert!(children.iter().all(|v| matches!(v, TbValue::Win(w) if *w < d))),
+                TbValue::Draw => assert!(!children.iter().any(|v| matches!(v, TbValue::Loss(_)))),
+            }
+        }
+        let (value, moves) = solver.report(&solution);
+        assert!(!moves.is_empty(), "{:?}", value);
+    }
+
+    #[test]
+    fn resumes_from_a_checkpoint() {
+        let variant = Variant::parse("4x3:MC.").unwrap();
+        let mut whole = Solver::new(variant.clone());
+        whole.expand(usize::MAX);
+        let mut part = Solver::new(variant);
+        part.expand(500);
+        let path = std::env::temp_dir().join(format!("cbsv-test-{}", std::process::id()));
+        part.save(&path).unwrap();
+        let mut resumed = Solver::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.expanded(), 500);
+        resumed.expand(usize::MAX);
+        assert_eq!(resumed.keys, whole.keys);
+        assert_eq!(resumed.edges, whole.edges);
+        assert_eq!(resumed.solve().values, whole.solve().values);
+    }
+}
@@ -0,0 +1,113 @@
+use rand::Rng;
+
+// A tuned parameter and the range it is kept in. Integer parameters are
+// rounded whenever a value is handed out.
+#[derive(Clone, Copy, Debug)]
+pub struct Param {
+    pub name: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub integer: bool,
+}
+
+impl Param {
+    fn value(&self, x: f64) -> f64 {
+        let v = self.min + x.clamp(0.0, 1.0) * (self.max - self.min);
+        if self.integer { v.round() } else { v }
+    }
+
+    fn normalized(&self, v: f64) -> f64 {
+        if self.max == self.min { 0.0 } else { ((v - self.min) / (self.max - self.min)).clamp(0.0, 1.0) }
+    }
+}
+
+// Spall's recommended gain decay exponents
+const ALPHA: f64 = 0.602;
+const GAMMA: f64 = 0.101;
+
+// The two parameter sets of one SPSA step, played against each other
+pub struct Perturbation {
+    pub plus: Vec<f64>,
+    pub minus: Vec<f64>,
+    delta: Vec<f64>,
+}
+
+// Simultaneous perturbation stochastic approximation on a match score. Every
+// step moves all parameters at once by a random ±c_k, plays the two sides
+// against each other and steps along the sign pattern by how lopsided the
+// result was. Parameters live in [0, 1] over their range so one step size
+// serves them all.
+pub struct Spsa {
+    params: Vec<Param>,
+    theta: Vec<f64>,
+    k: usize,
+    a: f64,
+    c: f64,
+    // stability constant, a tenth of the planned steps
+    big_a: f64,
+}
+
+impl Spsa {
+    // `c` is the perturbation and `step` the first update when the plus side
+    // wins every game, both as shares of each range
+    pub fn new(params: Vec<Param>, start: &[f64], iterations: usize, c: f64, step: f64) -> Spsa {
+        let theta = params.iter().zip(start).map(|(p, &v)| p.normalized(v)).collect();
+        let big_a = iterations as f64 / 10.0;
+        // a_0 * 0.5 / c = step
+        let a = step * c / 0.5 * (1.0 + big_a).powf(ALPHA);
+        Spsa { params, theta, k: 0, a, c, big_a }
+    }
+
+    pub fn params(&self) -> &[Param] { &self.params }
+
+    pub fn values(&self) -> Vec<f64> {
+        self.params.iter().zip(&self.theta).map(|(p, &x)| p.value(x)).collect()
+    }
+
+    fn c_k(&self) -> f64 { self.c / (self.k as f64 + 1.0).powf(GAMMA) }
+
+    pub fn perturb(&self, rng: &mut impl Rng) -> Perturbation {
+        let c_k = self.c_k();
+        let delta: Vec<f64> = self.params.iter().map(|_| if rng.gen::<bool>() { 1.0 } else { -1.0 }).collect();
+        let side = |sign: f64| self.params.iter().zip(&self.theta).zip(&delta).map(|((p, &x), &d)| p.value(x + sign * c_k * d)).collect();
+        Perturbation { plus: side(1.0), minus: side(-1.0), delta }
+    }
+
+    // `score` is the plus side's match score against the minus side in [0, 1]
+    pub fn update(&mut self, p: &Perturbation, score: f64) {
+        let a_k = self.a / (self.k as f64 + 1.0 + self.big_a).powf(ALPHA);
+        let c_k = self.c_k();
+        for (x, d) in self.theta.iter_mut().zip(&p.delta) {
+            *x = (*x + a_k * (score - 0.5) / (c_k * d)).clamp(0.0, 1.0);
+        }
+        self.k += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn climbs_a_noisy_hill() {
+        let params = vec![
+            Param { name: "x", min: 0.0, max: 10.0, integer: false },
+            Param { name: "n", min: 0.0, max: 100.0, integer: true },
+        ];
+        // the better side wins more often the closer it is to x = 7, n = 20
+        let strength = |v: &[f64]| -((v[0] - 7.0) / 10.0).powi(2) - ((v[1] - 20.0) / 100.0).powi(2);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut spsa = Spsa::new(params, &[2.0, 90.0], 500, 0.1, 0.1);
+        for _ in 0..500 {
+            let p = spsa.perturb(&mut rng);
+            let edge = strength(&p.plus) - strength(&p.minus);
+            let wins = (0..20).filter(|_| rng.gen::<f64>() < 0.5 + 2.0 * edge).count();
+            spsa.update(&p, wins as f64 / 20.0);
+        }
+        let v = spsa.values();
+        assert!((v[0] - 7.0).abs() < 1.0, "{:?}", v);
+        assert!((v[1] - 20.0).abs() < 15.0, "{:?}", v);
+        assert_eq!(v[1], v[1].round());
+    }
+}
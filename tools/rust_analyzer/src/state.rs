@@ -0,0 +1,289 @@
+use array_init::array_init;
+
+pub const ROWS: usize = 7;
+pub const COLS: usize = 11;
+pub const BOARD_SIZE: usize = ROWS * COLS;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceKind {
+    Core,
+    Monarch,
+    BruteL,
+    BruteR,
+    Tank,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Piece {
+    pub owner: char, // 'A' or 'B'
+    pub kind: PieceKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MoveKind {
+    Move,
+    BruteCapture,
+    Dash,
+}
+
+impl MoveKind {
+    pub fn name(self) -> &'static str {
+        match self { MoveKind::Move => "move", MoveKind::BruteCapture => "brute_capture", MoveKind::Dash => "dash" }
+    }
+}
+
+// what a Tank may dash over, the jumped piece is taken either way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dash {
+    Off,
+    // any piece but a Core, the full game's rule
+    NonCore,
+    EnemyNonCore,
+}
+
+impl Dash {
+    pub const NAMES: [&'static str; 3] = ["off", "non-core", "enemy"];
+
+    pub fn by_name(name: &str) -> Option<Dash> {
+        match name { "off" => Some(Dash::Off), "non-core" => Some(Dash::NonCore), "enemy" => Some(Dash::EnemyNonCore), _ => None }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self { Dash::Off => "off", Dash::NonCore => "non-core", Dash::EnemyNonCore => "enemy" }
+    }
+}
+
+// Board size and rule changes of the game being played, the full game by
+// default. Squares keep the full board's numbering, a smaller board is its
+// first rows and files.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ruleset {
+    pub rows: usize,
+    pub cols: usize,
+    // how far a Core moves
+    pub core_steps: u8,
+    pub dash: Dash,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self { Ruleset { rows: ROWS, cols: COLS, core_steps: 2, dash: Dash::NonCore } }
+}
+
+// squares are board indices (< BOARD_SIZE), so a move packs into 6 bytes
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Move {
+    pub actor: u8,
+    pub kind: MoveKind,
+    pub dest: Option<u8>,
+    pub captured: Option<u8>,
+}
+
+impl Move {
+    fn new(actor: usize, kind: MoveKind, dest: Option<usize>, captured: Option<usize>) -> Self {
+        Move { actor: actor as u8, kind, dest: dest.map(|d| d as u8), captured: captured.map(|c| c as u8) }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct State {
+    pub board: [Option<Piece>; BOARD_SIZE],
+    pub turn: char,
+    pub last_core_moved_by: Option<char>,
+    pub rules: Ruleset,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let mut board: [Option<Piece>; BOARD_SIZE] = array_init(|_| None);
+        // Player A top row (row 0)
+        let row_a = 0;
+        board[idx(row_a, 0)] = Some(Piece { owner: 'A', kind: PieceKind::BruteL });
+        board[idx(row_a, 4)] = Some(Piece { owner: 'A', kind: PieceKind::Monarch });
+        board[idx(row_a, 5)] = Some(Piece { owner: 'A', kind: PieceKind::Core });
+        board[idx(row_a, 6)] = Some(Piece { owner: 'A', kind: PieceKind::Tank });
+        board[idx(row_a, 10)] = Some(Piece { owner: 'A', kind: PieceKind::BruteR });
+        // Player B bottom row (row 6)
+        let row_b = ROWS - 1;
+        board[idx(row_b, 0)] = Some(Piece { owner: 'B', kind: PieceKind::BruteL });
+        board[idx(row_b, 4)] = Some(Piece { owner: 'B', kind: PieceKind::Monarch });
+        board[idx(row_b, 5)] = Some(Piece { owner: 'B', kind: PieceKind::Core });
+        board[idx(row_b, 6)] = Some(Piece { owner: 'B', kind: PieceKind::Tank });
+        board[idx(row_b, 10)] = Some(Piece { owner: 'B', kind: PieceKind::BruteR });
+        State { board, turn: 'A', last_core_moved_by: None, rules: Ruleset::default() }
+    }
+}
+
+pub fn idx(r: usize, c: usize) -> usize {
+    r * COLS + c
+}
+
+pub fn opponent(owner: char) -> char {
+    if owner == 'A' { 'B' } else { 'A' }
+}
+
+pub fn rc(i: usize) -> (usize, usize) {
+    (i / COLS, i % COLS)
+}
+
+impl State {
+    pub fn on_board_pos(&self, r: isize, c: isize) -> bool {
+        r >= 0 && r < self.rules.rows as isize && c >= 0 && c < self.rules.cols as isize
+    }
+
+    pub fn are_adjacent_pos(a: usize, b: usize) -> bool {
+        let (ar, ac) = rc(a);
+        let (br, bc) = rc(b);
+        let dr = (ar as isize - br as isize).abs();
+        let dc = (ac as isize - bc as isize).abs();
+        (dr <= 1) && (dc <= 1) && !(dr == 0 && dc == 0)
+    }
+
+    pub fn find_piece(&self, owner: char, kind: PieceKind) -> Option<usize> {
+        for i in 0..BOARD_SIZE {
+            if let Some(p) = self.board[i] {
+                if p.owner == owner && p.kind == kind {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
+        self.legal_moves_into(&mut moves);
+        moves
+    }
+
+    // legal_moves into a buffer the caller reuses
+    pub fn legal_moves_into(&self, moves: &mut Vec<Move>) {
+        moves.clear();
+        for i in 0..BOARD_SIZE {
+            if let Some(piece) = self.board[i] {
+                if piece.owner != self.turn { continue }
+                let (r, c) = rc(i);
+                match piece.kind {
+                    PieceKind::Monarch => {
+                        let deltas = [ (0isize,-1), (0,1), (-1,-1), (-1,1), (1,-1), (1,1) ];
+                        for (dr,dc) in deltas {
+                            let nr = r as isize + dr; let nc = c as isize + dc;
+                            if !self.on_board_pos(nr,nc) { continue }
+                            let ni = idx(nr as usize, nc as usize);
+                            if self.board[ni].is_none() || self.board[ni].unwrap().owner != piece.owner {
+                                moves.push(Move::new(i, MoveKind::Move, Some(ni),  if self.board[ni].is_some() {Some(ni)} else {None}));
+                            }
+                        }
+                    }
+                    PieceKind::Core => {
+                        if let Some(mon_pos) = self.find_piece(piece.owner, PieceKind::Monarch) {
+                            if !State::are_adjacent_pos(i, mon_pos) { continue }
+                        } else { continue }
+                        for dr in -1..=1 {
+                            for dc in -1..=1 {
+                                if dr==0 && dc==0 { continue }
+                                // longer steps hop over anything in between
+                                for step in 1..=self.rules.core_steps as isize {
+                                    let nr = r as isize + dr*step; let nc = c as isize + dc*step;
+                                    if !self.on_board_pos(nr,nc) { continue }
+                                    let ni = idx(nr as usize, nc as usize);
+                                    if self.board[ni].is_none() || self.board[ni].unwrap().owner != piece.owner {
+                                        moves.push(Move::new(i, MoveKind::Move, Some(ni),  if self.board[ni].is_some() {Some(ni)} else {None}));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    PieceKind::BruteL | PieceKind::BruteR => {
+                        let orth = [(-1,0),(1,0),(0,-1),(0,1)];
+                        for (dr,dc) in orth {
+                            let nr = r as isize + dr; let nc = c as isize + dc;
+                            if !self.on_board_pos(nr,nc) { continue }
+                            let ni = idx(nr as usize, nc as usize);
+                            if self.board[ni].is_none() { moves.push(Move::new(i, MoveKind::Move, Some(ni), None)) }
+                        }
+                        let (fdr, fdc) = if piece.owner == 'A' { (1, 0) } else { (-1, 0) };
+                        let fr = r as isize + fdr; let fc = c as isize + fdc;
+                        if self.on_board_pos(fr, fc) {
+                            let fi = idx(fr as usize, fc as usize);
+                            if let Some(t) = self.board[fi] {
+                                if t.owner != piece.owner {
+                                    moves.push(Move::new(i, MoveKind::BruteCapture, None, Some(fi)));
+                                }
+                            }
+                        }
+                    }
+                    PieceKind::Tank => {
+                        let orth = [(-1,0),(1,0),(0,-1),(0,1)];
+                        for (dr,dc) in orth {
+                            let nr = r as isize + dr; let nc = c as isize + dc;
+                            if !self.on_board_pos(nr,nc) { continue }
+                            let ni = idx(nr as usize, nc as usize);
+                            if self.board[ni].is_none() { moves.push(Move::new(i, MoveKind::Move, Some(ni), None)) }
+                        }
+                        let (fdr, fdc) = if piece.owner == 'A' { (1, 0) } else { (-1, 0) };
+                        let fr = r as isize + fdr; let fc = c as isize + fdc;
+                        let lr = r as isize + 2*fdr; let lc = c as isize + 2*fdc;
+                        if self.on_board_pos(fr, fc) && self.on_board_pos(lr, lc) {
+                            let fi = idx(fr as usize, fc as usize);
+                            let li = idx(lr as usize, lc as usize);
+                            if let Some(mid) = self.board[fi] {
+                                let allowed = match self.rules.dash { Dash::Off => false, Dash::NonCore => true, Dash::EnemyNonCore => mid.owner != piece.owner };
+                                if allowed && mid.kind != PieceKind::Core && self.board[li].is_none() {
+                                    moves.push(Move::new(i, MoveKind::Dash, Some(li), Some(fi)));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn apply_move(&self, m: &Move) -> State {
+        let mut s = self.clone();
+        let actor = m.actor as usize;
+        let piece = s.board[actor].expect("actor must exist");
+        match m.kind {
+            MoveKind::Move => {
+                if let Some(dest) = m.dest.map(usize::from) {
+                    if let Some(target) = s.board[dest] { if target.owner != piece.owner { s.board[dest] = None; } }
+                    s.board[actor] = None;
+                    s.board[dest] = Some(piece);
+                    if piece.kind == PieceKind::Core { s.last_core_moved_by = Some(piece.owner); }
+                }
+            }
+            MoveKind::BruteCapture => {
+                if let Some(cap) = m.captured { s.board[cap as usize] = None; }
+            }
+            MoveKind::Dash => {
+                if let Some(cap) = m.captured { s.board[cap as usize] = None; }
+                if let Some(dest) = m.dest { s.board[actor] = None; s.board[dest as usize] = Some(piece); }
+            }
+        }
+        s.turn = opponent(s.turn);
+        s
+    }
+
+    pub fn is_terminal(&self) -> Option<char> {
+        if let (Some(a_pos), Some(b_pos)) = (self.find_piece('A', PieceKind::Core), self.find_piece('B', PieceKind::Core)) {
+            if State::are_adjacent_pos(a_pos, b_pos) {
+                return self.last_core_moved_by;
+            }
+        }
+        None
+    }
+}
+
+// a position with only `pieces` on the board, the fixture of the search tests
+#[cfg(test)]
+pub fn position(turn: char, pieces: &[(usize, char, PieceKind)]) -> State {
+    let mut board: [Option<Piece>; BOARD_SIZE] = [None; BOARD_SIZE];
+    for &(i, owner, kind) in pieces { board[i] = Some(Piece { owner, kind }); }
+    State { board, turn, last_core_moved_by: None, rules: Ruleset::default() }
+}
+
+pub fn chebyshev_distance(a: usize, b: usize) -> usize {
+    let (ar, ac) = rc(a);
+    let (br, bc) = rc(b);
+    std::cmp::max(ar.abs_diff(br), ac.abs_diff(bc))
+}
@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// An experiment file is TOML with analyzer flags as keys, written with
+// underscores or dashes. A list makes the flag an axis of the grid, every
+// combination of the axes is one cell:
+//
+//   games = 20
+//   iters = [200, 1000]
+//   c = [0.7, 1.4]
+//   playout = ["uniform", "core-approach"]
+//   variant = ["7x11:L...MCT...R", "7x9:L..MCT..R,core=1"]
+//   seed = [1, 2]
+//
+// `results` names the results table, by default next to the file. Cells only
+// play self-play games, see check_cell for the flags they take.
+pub struct Experiment {
+    // flag name and its values in file order, single values included
+    settings: Vec<(String, Vec<String>)>,
+    pub results: PathBuf,
+}
+
+// one combination of settings, in the same order as the experiment's
+pub type Cell = Vec<(String, String)>;
+
+fn value_text(key: &str, value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        other => Err(format!("{}: expected a string, number or boolean, got {}", key, other.type_str())),
+    }
+}
+
+impl Experiment {
+    pub fn parse(text: &str, path: &Path) -> Result<Experiment, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut results = path.with_extension("results.csv");
+        let mut settings = Vec::new();
+        for (key, value) in &table {
+            let key = key.replace('_', "-");
+            let values = match value {
+                toml::Value::Array(items) if items.is_empty() => return Err(format!("{}: empty list", key)),
+                toml::Value::Array(items) => items.iter().map(|v| value_text(&key, v)).collect::<Result<Vec<_>, _>>()?,
+                v => vec![value_text(&key, v)?],
+            };
+            if key == "results" { results = path.with_file_name(&values[0]); continue }
+            settings.push((key, values));
+        }
+        Ok(Experiment { settings, results })
+    }
+
+    pub fn load(path: &Path) -> Result<Experiment, String> {
+        Experiment::parse(&fs::read_to_string(path).map_err(|e| e.to_string())?, path)
+    }
+
+    // the flags with more than one value, which the results table has a column for
+    pub fn axes(&self) -> Vec<&str> {
+        self.settings.iter().filter(|(_, v)| v.len() > 1).map(|(k, _)| k.as_str()).collect()
+    }
+
+    // the grid with the last axis changing fastest
+    pub fn cells(&self) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = vec![Vec::new()];
+        for (key, values) in &self.settings {
+            cells = cells.into_iter().flat_map(|cell| values.iter().map(move |v| {
+                let mut cell = cell.clone();
+                cell.push((key.clone(), v.clone()));
+                cell
+            })).collect();
+        }
+        cells
+    }
+
+    pub fn label(&self, cell: &Cell) -> String {
+        let axes = self.axes();
+        cell.iter().filter(|(k, _)| axes.contains(&k.as_str())).map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+// the flags the self-play games of a cell depend on
+const CELL_FLAGS: [&str; 17] = ["games", "seed", "iters", "c", "playout", "epsilon", "cutoff-plies", "playout-max", "draw-value",
+    "max-turns", "rave", "rave-k", "tb-dir", "net", "eval-weights", "search-config", "variant"];
+
+// Rejects a cell with a flag its games wouldn't use, like another mode's flags
+// or the settings of a playout the cell doesn't play, so the table gets no
+// rows that differ only in a setting that did nothing.
+pub fn check_cell(cell: &Cell) -> Result<(), String> {
+    let get = |key: &str| cell.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+    if let Some((key, _)) = cell.iter().find(|(k, _)| !CELL_FLAGS.contains(&k.as_str())) {
+        return Err(format!("--{} has no effect in a sweep, cells only play self-play games", key));
+    }
+    let playout = get("playout").unwrap_or("uniform");
+    let unused = [
+        ("epsilon", playout != "epsilon-greedy", "only the epsilon-greedy playout uses it"),
+        ("cutoff-plies", playout != "eval-cutoff", "only the eval-cutoff playout uses it"),
+        ("eval-weights", playout != "eval-cutoff", "only the eval-cutoff playout uses it"),
+        ("rave-k", get("rave") != Some("true"), "it needs --rave"),
+        ("playout", get("net").is_some(), "the network replaces playouts"),
+        ("tb-dir", get("variant").is_some(), "tablebases are only probed on the full board"),
+    ];
+    for (key, ignored, why) in unused {
+        if ignored && get(key).is_some() { return Err(format!("--{} has no effect in this cell, {}", key, why)); }
+    }
+    Ok(())
+}
+
+// command line for a cell, booleans become bare switches
+pub fn cell_args(cell: &Cell) -> Vec<String> {
+    let mut args = Vec::new();
+    for (key, value) in cell {
+        match value.as_str() {
+            "true" => args.push(format!("--{}", key)),
+            "false" => {}
+            _ => { args.push(format!("--{}", key)); args.push(value.clone()); }
+        }
+    }
+    args
+}
+
+// quoted when it holds a comma, like variants with rule changes
+fn csv_field(v: &str) -> String {
+    if v.contains(',') || v.contains('"') { format!("\"{}\"", v.replace('"', "\"\"")) } else { v.to_string() }
+}
+
+fn csv_fields(line: &str) -> Vec<String> {
+    let (mut fields, mut field, mut quoted) = (Vec::new(), String::new(), false);
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (ch, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => { field.push('"'); chars.next(); }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// Completed cells are appended to the results table as they finish, so a
+// rerun skips every cell already in it.
+pub struct Results {
+    file: fs::File,
+    done: HashSet<String>,
+}
+
+pub const RESULT_COLUMNS: [&str; 8] = ["games", "a_wins", "b_wins", "draws", "first_rate", "ci_low", "ci_high", "seconds"];
+
+impl Results {
+    pub fn open(path: &Path, axes: &[&str]) -> io::Result<Results> {
+        let header = axes.iter().copied().chain(RESULT_COLUMNS).collect::<Vec<_>>().join(",");
+        let mut done = HashSet::new();
+        if path.exists() {
+            let text = fs::read_to_string(path)?;
+            let mut lines = text.lines();
+            if lines.next() != Some(header.as_str()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} has other columns than this experiment, move it away to start over", path.display())));
+            }
+            for line in lines {
+                let cols = csv_fields(line);
+                // a line cut short by an interruption is run again
+                if cols.len() == axes.len() + RESULT_COLUMNS.len() { done.insert(cols[..axes.len()].join(",")); }
+            }
+        }
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let text = fs::read(path)?;
+        if text.is_empty() { writeln!(file, "{}", header)?; }
+        else if !text.ends_with(b"\n") { writeln!(file)?; }
+        Ok(Results { file, done })
+    }
+
+    fn values<'a>(axes: &[&str], cell: &'a Cell) -> Vec<&'a str> {
+        axes.iter().map(|a| cell.iter().find(|(k, _)| k == a).map_or("", |(_, v)| v.as_str())).collect()
+    }
+
+    fn key(axes: &[&str], cell: &Cell) -> String { Results::values(axes, cell).join(",") }
+
+    pub fn is_done(&self, axes: &[&str], cell: &Cell) -> bool { self.done.contains(&Results::key(axes, cell)) }
+
+    pub fn record(&mut self, axes: &[&str], cell: &Cell, values: &[String]) -> io::Result<()> {
+        let fields: Vec<String> = Results::values(axes, cell).into_iter().map(csv_field).collect();
+        writeln!(self.file, "{},{}", fields.join(","), values.join(","))?;
+        self.file.flush()?;
+        self.done.insert(Results::key(axes, cell));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_become_axes() {
+        let text = "games = 4\niters = [100, 200]\nplayout = [\"uniform\", \"eval-cutoff\"]\nrave = true\nresults = \"out.csv\"\n";
+        let exp = Experiment::parse(text, Path::new("dir/exp.toml")).unwrap();
+        assert_eq!(exp.results, Path::new("dir/out.csv"));
+        let cells = exp.cells();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(exp.axes().len(), 2);
+        assert!(cells.iter().any(|c| exp.label(c) == "iters=200 playout=uniform"));
+        let args = cell_args(&cells[0]);
+        assert!(args.contains(&"--rave".to_string()) && args.contains(&"--games".to_string()));
+        assert!(!args.contains(&"true".to_string()));
+        assert!(Experiment::parse("iters = []", Path::new("x.toml")).is_err());
+    }
+
+    #[test]
+    fn cells_reject_flags_without_effect() {
+        let check = |text: &str| Experiment::parse(text, Path::new("x.toml")).unwrap().cells().iter().try_for_each(check_cell);
+        assert!(check("iters = [100, 200]\nplayout = \"eval-cutoff\"\ncutoff-plies = [5, 10]\nvariant = \"5x5:.MCT.\"").is_ok());
+        assert!(check("games = 4\nbalance = true").unwrap_err().contains("--balance"));
+        assert!(check("format = \"json\"").is_err());
+        assert!(check("epsilon = [0.1, 0.2]").unwrap_err().contains("epsilon-greedy"));
+        assert!(check("rave = [true, false]\nrave-k = 100").is_err());
+        assert!(check("tb-dir = \"tb\"\nvariant = \"5x5:.MCT.\"").is_err());
+    }
+
+    #[test]
+    fn finished_cells_are_skipped_on_resume() {
+        let exp = Experiment::parse("c = [1.0, 2.0]\nvariant = [\"5x5:.MCT.\", \"5x5:.MCT.,core=1\"]", Path::new("x.toml")).unwrap();
+        let axes = exp.axes();
+        let cells = exp.cells();
+        let path = std::env::temp_dir().join(format!("cb-sweep-test-{}.csv", std::process::id()));
+        let row: Vec<String> = RESULT_COLUMNS.iter().map(|_| "0".to_string()).collect();
+        {
+            let mut results = Results::open(&path, &axes).unwrap();
+            results.record(&axes, &cells[1], &row).unwrap();
+        }
+        // an interrupted write leaves a short line behind
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"2,5x5:.MCT.,4").unwrap();
+        let results = Results::open(&path, &axes).unwrap();
+        assert_eq!(cells.iter().filter(|c| results.is_done(&axes, c)).count(), 1);
+        assert!(results.is_done(&axes, &cells[1]));
+        assert!(results.is_done(&axes, &cells[1]) && exp.label(&cells[1]).ends_with("core=1"));
+        assert!(Results::open(&path, &["c"]).is_err());
+        assert_eq!(csv_fields("1,\"a,b\",\"x\"\"y\""), ["1", "a,b", "x\"y"]);
+        fs::remove_file(&path).unwrap();
+    }
+}
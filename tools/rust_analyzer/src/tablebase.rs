@@ -0,0 +1,537 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::state::{chebyshev_distance, idx, rc, Move, Piece, PieceKind, Ruleset, State, BOARD_SIZE, COLS, ROWS};
+
+const KINDS: [PieceKind; 5] = [PieceKind::Core, PieceKind::Monarch, PieceKind::BruteL, PieceKind::BruteR, PieceKind::Tank];
+const LETTERS: [char; 5] = ['C', 'M', 'L', 'R', 'T'];
+const OWNERS: [char; 2] = ['A', 'B'];
+
+// Tables index placements on distinct squares only, and no rule tells left
+// from right, so A's Core (always the first piece) stays on the left half or
+// the middle file. CM-CMT is 2 * 42 * 76 * 75 * 74 * 73 entries, about 2.6 GB.
+//
+// Generation keeps the table and every smaller one in memory. In a release
+// build three pieces take under a second and four, such as CM-CM at 35 MB,
+// about a minute. Five pieces need the 2.6 GB and about 73 times the work of
+// four, an hour or more. Larger sets are refused.
+pub const MAX_PIECES: usize = 5;
+const HALF_COLS: usize = COLS / 2 + 1;
+
+const MAGIC: &[u8; 4] = b"CBTB";
+const VERSION: u8 = 2;
+
+// Which pieces each side still has, one bit per kind in KINDS order.
+// Written like "CM-CMT", A's pieces before the dash.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Material(pub [u8; 2]);
+
+impl Material {
+    pub fn of(s: &State) -> Material {
+        let mut m = [0u8; 2];
+        for p in s.board.iter().flatten() { m[(p.owner == 'B') as usize] |= 1 << kind_bit(p.kind); }
+        Material(m)
+    }
+
+    pub fn parse(text: &str) -> Option<Material> {
+        let (a, b) = text.split_once('-')?;
+        let side = |letters: &str| {
+            let mut bits = 0u8;
+            for ch in letters.chars() {
+                let bit = LETTERS.iter().position(|&l| l == ch.to_ascii_uppercase())?;
+                if bits & 1 << bit != 0 { return None; }
+                bits |= 1 << bit;
+            }
+            Some(bits)
+        };
+        Some(Material([side(a)?, side(b)?]))
+    }
+
+    pub fn count(self) -> usize { self.0.iter().map(|bits| bits.count_ones() as usize).sum() }
+
+    // in index order, A's pieces by kind then B's
+    fn pieces(self) -> Vec<Piece> {
+        let mut out = Vec::new();
+        for (side, owner) in OWNERS.iter().enumerate() {
+            for (bit, kind) in KINDS.iter().enumerate() {
+                if self.0[side] & 1 << bit != 0 { out.push(Piece { owner: *owner, kind: *kind }); }
+            }
+        }
+        out
+    }
+
+    // without both Cores nobody can touch, so every such position is drawn
+    fn has_cores(self) -> bool { self.0[0] & self.0[1] & 1 != 0 }
+
+    fn without(self, p: Piece) -> Material {
+        let mut m = self.0;
+        m[(p.owner == 'B') as usize] &= !(1 << kind_bit(p.kind));
+        Material(m)
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (side, bits) in self.0.iter().enumerate() {
+            if side == 1 { write!(f, "-")?; }
+            for (bit, letter) in LETTERS.iter().enumerate() {
+                if bits & 1 << bit != 0 { write!(f, "{}", letter)?; }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn kind_bit(kind: PieceKind) -> usize {
+    KINDS.iter().position(|&k| k == kind).unwrap()
+}
+
+// result for the side to move with the number of plies until the touch
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TbValue {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+
+// One byte per entry: 0 is a draw, otherwise 1 + plies
+// to the touch. An odd number of plies is a win for the side to move.
+fn decode(b: u8) -> TbValue {
+    match b {
+        0 => TbValue::Draw,
+        _ if (b - 1) % 2 == 1 => TbValue::Win(b as u16 - 1),
+        _ => TbValue::Loss(b as u16 - 1),
+    }
+}
+
+fn is_loss(b: u8) -> bool { b != 0 && (b - 1).is_multiple_of(2) }
+
+const MONARCH_STEPS: [(isize, isize); 6] = [(0, -1), (0, 1), (-1, -1), (-1, 1), (1, -1), (1, 1)];
+const CORE_STEPS: [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+const ORTHOGONAL_STEPS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+fn offset(sq: u8, dr: isize, dc: isize) -> Option<u8> {
+    let (r, c) = rc(sq as usize);
+    let (r, c) = (r as isize + dr, c as isize + dc);
+    (0..ROWS as isize).contains(&r).then_some(())?;
+    (0..COLS as isize).contains(&c).then(|| idx(r as usize, c as usize) as u8)
+}
+
+// squares a piece of `kind` reaches `at` from in one move, each step set
+// holds every step's reverse too
+fn sources(kind: PieceKind, at: u8) -> impl Iterator<Item = u8> {
+    let (steps, reach): (&'static [(isize, isize)], isize) = match kind {
+        PieceKind::Monarch => (&MONARCH_STEPS, 1),
+        PieceKind::Core => (&CORE_STEPS, Ruleset::default().core_steps as isize),
+        _ => (&ORTHOGONAL_STEPS, 1),
+    };
+    steps.iter().flat_map(move |&(dr, dc)| (1..=reach).filter_map(move |n| offset(at, dr * n, dc * n)))
+}
+
+pub struct Table {
+    material: Material,
+    pieces: Vec<Piece>,
+    data: Vec<u8>,
+}
+
+// squares of `pieces` in order, and whether B is to move
+type Placement = ([u8; MAX_PIECES], bool);
+
+fn mirror(sq: u8) -> u8 {
+    let (r, c) = rc(sq as usize);
+    idx(r, COLS - 1 - c) as u8
+}
+
+impl Table {
+    fn entries(k: usize) -> usize { 2 * ROWS * HALF_COLS * (1..k).map(|i| BOARD_SIZE - i).product::<usize>() }
+
+    // Mirrors the placement when A's Core is on the right half, then counts
+    // each later piece's square among those the earlier pieces left free.
+    fn index(&self, squares: &[u8], b_to_move: bool) -> usize {
+        let k = self.pieces.len();
+        let mut sq = [0u8; MAX_PIECES];
+        sq[..k].copy_from_slice(squares);
+        if rc(sq[0] as usize).1 >= HALF_COLS { for s in &mut sq[..k] { *s = mirror(*s); } }
+        let (r, c) = rc(sq[0] as usize);
+        let mut entry = r * HALF_COLS + c;
+        for i in 1..k {
+            let taken = sq[..i].iter().filter(|&&s| s < sq[i]).count();
+            entry = entry * (BOARD_SIZE - i) + sq[i] as usize - taken;
+        }
+        entry * 2 + b_to_move as usize
+    }
+
+    fn placement(&self, entry: usize) -> Placement {
+        let k = self.pieces.len();
+        let mut rest = entry / 2;
+        let mut free = [0usize; MAX_PIECES];
+        for i in (1..k).rev() { free[i] = rest % (BOARD_SIZE - i); rest /= BOARD_SIZE - i; }
+        let mut squares = [0u8; MAX_PIECES];
+        squares[0] = idx(rest / HALF_COLS, rest % HALF_COLS) as u8;
+        for i in 1..k {
+            let mut taken = squares;
+            taken[..i].sort_unstable();
+            squares[i] = taken[..i].iter().fold(free[i], |sq, &t| if t as usize <= sq { sq + 1 } else { sq }) as u8;
+        }
+        (squares, entry % 2 == 1)
+    }
+
+    fn state(&self, (squares, b_to_move): &Placement) -> State {
+        let mut board = [None; BOARD_SIZE];
+        for (p, &sq) in self.pieces.iter().zip(squares) { board[sq as usize] = Some(*p); }
+        let turn = if *b_to_move { 'B' } else { 'A' };
+        // the last Core move was the other side's, so touching Cores read as their win
+        let last = if *b_to_move { 'A' } else { 'B' };
+        State { board, turn, last_core_moved_by: Some(last), rules: Ruleset::default() }
+    }
+
+    // Entries whose side to move may have moved into `placement`: one of its
+    // pieces stepping back and, for a placement of the smaller table without
+    // piece `captured`, that piece put back where it was taken. Entries that
+    // aren't parents after all only cost a look in `settle`.
+    fn parents(&self, (squares, b_to_move): &Placement, captured: Option<usize>, out: &mut Vec<usize>) {
+        out.clear();
+        let k = self.pieces.len();
+        let mut sq = [0u8; MAX_PIECES];
+        let rest = (0..k).filter(|&j| Some(j) != captured);
+        for (j, &s) in rest.zip(squares.iter()) { sq[j] = s; }
+        let placed = |sq: &[u8; MAX_PIECES], s: u8| (0..k).any(|j| Some(j) != captured && sq[j] == s);
+        let mover = if *b_to_move { 'A' } else { 'B' };
+        let forward = if mover == 'A' { 1 } else { -1 };
+        for j in (0..k).filter(|&j| Some(j) != captured && self.pieces[j].owner == mover) {
+            let (kind, at) = (self.pieces[j].kind, sq[j]);
+            let mut push = |from: u8, taken: Option<u8>| {
+                let mut parent = sq;
+                parent[j] = from;
+                if let (Some(i), Some(t)) = (captured, taken) { parent[i] = t; }
+                out.push(self.index(&parent[..k], !b_to_move));
+                // with A's Core on the middle file the mirror image is an entry of its own
+                if rc(parent[0] as usize).1 == COLS / 2 { out.push(self.index(&parent.map(mirror)[..k], !b_to_move)); }
+            };
+            let Some(i) = captured else {
+                for from in sources(kind, at).filter(|&f| !placed(&sq, f)) { push(from, None); }
+                continue;
+            };
+            let enemy = self.pieces[i].owner != mover;
+            match kind {
+                PieceKind::Monarch | PieceKind::Core if enemy => {
+                    for from in sources(kind, at).filter(|&f| !placed(&sq, f)) { push(from, Some(at)); }
+                }
+                PieceKind::BruteL | PieceKind::BruteR if enemy => {
+                    if let Some(t) = offset(at, forward, 0).filter(|&t| !placed(&sq, t)) { push(at, Some(t)); }
+                }
+                // the jumped piece may be the Tank's own
+                PieceKind::Tank => {
+                    if let (Some(t), Some(from)) = (offset(at, -forward, 0), offset(at, -2 * forward, 0)) {
+                        if !placed(&sq, t) && !placed(&sq, from) { push(from, Some(t)); }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn cores_touch(&self, squares: &[u8]) -> bool {
+        let mut cores = self.pieces.iter().zip(squares).filter(|(p, _)| p.kind == PieceKind::Core).map(|(_, &sq)| sq as usize);
+        match (cores.next(), cores.next()) {
+            (Some(a), Some(b)) => chebyshev_distance(a, b) == 1,
+            _ => false,
+        }
+    }
+
+    pub fn material(&self) -> Material { self.material }
+
+    pub fn probe(&self, s: &State) -> TbValue {
+        let mut squares = [0u8; MAX_PIECES];
+        for (i, p) in self.pieces.iter().enumerate() {
+            squares[i] = s.find_piece(p.owner, p.kind).expect("material matches the table") as u8;
+        }
+        decode(self.data[self.index(&squares[..self.pieces.len()], s.turn == 'B')])
+    }
+
+    // entries per result, for reports
+    pub fn summary(&self) -> (usize, usize, usize, u16) {
+        let (mut wins, mut losses, mut draws, mut longest) = (0, 0, 0, 0);
+        for &b in &self.data {
+            match decode(b) {
+                TbValue::Win(d) => { wins += 1; longest = longest.max(d); }
+                TbValue::Loss(d) => { losses += 1; longest = longest.max(d); }
+                TbValue::Draw => draws += 1,
+            }
+        }
+        (wins, losses, draws, longest)
+    }
+}
+
+// Tables by material, each generated together with the smaller ones its
+// captures lead into.
+#[derive(Default)]
+pub struct Tablebases {
+    tables: HashMap<Material, Table>,
+}
+
+impl fmt::Debug for Tablebases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.tables.keys().map(|m| m.to_string())).finish()
+    }
+}
+
+impl Tablebases {
+    pub fn new() -> Tablebases { Tablebases::default() }
+
+    pub fn tables(&self) -> impl Iterator<Item = &Table> { self.tables.values() }
+
+    // tables are built under the full game's rules, other rulesets find nothing
+    pub fn probe(&self, s: &State) -> Option<TbValue> {
+        if s.rules != Ruleset::default() { return None; }
+        let material = Material::of(s);
+        if !material.has_cores() { return Some(TbValue::Draw); }
+        self.tables.get(&material).map(|t| t.probe(s))
+    }
+
+    // Retrograde analysis: pass p settles every position whose touch is
+    // exactly p plies away, wins on odd passes, losses on even. Only the
+    // parents of the positions settled on the pass before, here or in the
+    // tables captures lead to, are looked at again.
+    pub fn generate(&mut self, material: Material) {
+        if self.tables.contains_key(&material) || !material.has_cores() { return; }
+        let pieces = material.pieces();
+        assert!(pieces.len() <= MAX_PIECES, "{} has more than {} pieces", material, MAX_PIECES);
+        for p in &pieces {
+            if p.kind != PieceKind::Core { self.generate(material.without(*p)); }
+        }
+        let mut table = Table { material, pieces, data: vec![0; Table::entries(material.pieces().len())] };
+        // captures leave the table, those results are known from the start
+        let deepest_capture = table.pieces.iter()
+            .filter_map(|p| self.tables.get(&material.without(*p)))
+            .flat_map(|t| t.data.iter().copied())
+            .max()
+            .unwrap_or(0);
+
+        for idx in 0..table.data.len() {
+            let (squares, _) = table.placement(idx);
+            if table.cores_touch(&squares[..table.pieces.len()]) { table.data[idx] = 1; }
+        }
+        let subs: Vec<(usize, &Table)> = (0..table.pieces.len()).filter_map(|i| self.tables.get(&material.without(table.pieces[i])).map(|t| (i, t))).collect();
+        let (mut moves, mut parents) = (Vec::new(), Vec::new());
+        for pass in 1u8.. {
+            // a position settles on the pass after its deciding child did
+            let mut settled = false;
+            for y in 0..table.data.len() {
+                if table.data[y] != pass { continue; }
+                table.parents(&table.placement(y), None, &mut parents);
+                for &x in &parents { settled |= self.settle(&mut table, x, pass, &mut moves); }
+            }
+            for &(i, sub) in &subs {
+                for z in 0..sub.data.len() {
+                    if sub.data[z] != pass { continue; }
+                    table.parents(&sub.placement(z), Some(i), &mut parents);
+                    for &x in &parents { settled |= self.settle(&mut table, x, pass, &mut moves); }
+                }
+            }
+            if !settled && pass >= deepest_capture { break; }
+            assert!(pass < u8::MAX - 1, "{}: touch more than {} plies away", material, u8::MAX - 2);
+        }
+        self.tables.insert(material, table);
+    }
+
+    // Settles an unknown entry on `pass` if its children decide it: a win
+    // needs a loss among them on odd passes, a loss only wins on even ones.
+    fn settle(&self, table: &mut Table, x: usize, pass: u8, moves: &mut Vec<Move>) -> bool {
+        if table.data[x] != 0 { return false; }
+        let placement = table.placement(x);
+        table.state(&placement).legal_moves_into(moves);
+        if moves.is_empty() { return false; }
+        let mut children = moves.iter().map(|m| self.child(table, &placement, m));
+        let known = if pass % 2 == 1 {
+            children.any(|b| is_loss(b) && b <= pass)
+        } else {
+            children.all(|b| b != 0 && !is_loss(b) && b <= pass)
+        };
+        if known { table.data[x] = pass + 1; }
+        known
+    }
+
+    // entry byte of the position after `m`, which may be in a smaller table
+    fn child(&self, table: &Table, (squares, b_to_move): &Placement, m: &Move) -> u8 {
+        let k = table.pieces.len();
+        let mut next = *squares;
+        let actor = next[..k].iter().position(|&sq| sq == m.actor).expect("actor is a table piece");
+        let captured = m.captured.map(|c| next[..k].iter().position(|&sq| sq == c).expect("captured piece is a table piece"));
+        if let Some(dest) = m.dest { next[actor] = dest; }
+        match captured {
+            None => table.data[table.index(&next[..k], !b_to_move)],
+            Some(i) if table.pieces[i].kind == PieceKind::Core => 0,
+            Some(i) => {
+                let sub = &self.tables[&table.material.without(table.pieces[i])];
+                let rest: Vec<u8> = (0..k).filter(|&j| j != i).map(|j| next[j]).collect();
+                sub.data[sub.index(&rest, !b_to_move)]
+            }
+        }
+    }
+
+    // ---------------- files ----------------
+
+    // Header then the entries, with each run of zero bytes (draws) written as a
+    // 0 followed by the run length as a LEB128 varint.
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for table in self.tables.values() {
+            let mut out = Vec::with_capacity(table.data.len() / 4);
+            out.extend_from_slice(MAGIC);
+            out.extend_from_slice(&[VERSION, ROWS as u8, COLS as u8, table.material.0[0], table.material.0[1]]);
+            out.extend_from_slice(&(table.data.len() as u64).to_le_bytes());
+            let mut i = 0;
+            while i < table.data.len() {
+                if table.data[i] != 0 { out.push(table.data[i]); i += 1; continue; }
+                let start = i;
+                while i < table.data.len() && table.data[i] == 0 { i += 1; }
+                out.push(0);
+                let mut run = i - start;
+                loop {
+                    let byte = (run & 0x7f) as u8;
+                    run >>= 7;
+                    if run == 0 { out.push(byte); break; }
+                    out.push(byte | 0x80);
+                }
+            }
+            fs::File::create(dir.join(format!("{}.cbtb", table.material)))?.write_all(&out)?;
+        }
+        Ok(())
+    }
+
+    // loads every .cbtb file in `dir`
+    pub fn load(dir: &Path) -> io::Result<Tablebases> {
+        let mut tbs = Tablebases::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "cbtb") {
+                let table = read_table(&path)?;
+                tbs.tables.insert(table.material, table);
+            }
+        }
+        Ok(tbs)
+    }
+}
+
+fn read_table(path: &Path) -> io::Result<Table> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), what));
+    let mut bytes = Vec::new();
+    fs::File::open(path)?.read_to_end(&mut bytes)?;
+    if bytes.len() < 17 || &bytes[..4] != MAGIC { return Err(invalid("not a tablebase file")); }
+    if bytes[4] != VERSION || bytes[5] as usize != ROWS || bytes[6] as usize != COLS { return Err(invalid("version or board size differs")); }
+    let material = Material([bytes[7], bytes[8]]);
+    let entries = u64::from_le_bytes(bytes[9..17].try_into().unwrap()) as usize;
+    if material.pieces().len() > MAX_PIECES || entries != Table::entries(material.pieces().len()) { return Err(invalid("entry count does not match the material")); }
+    let mut data = Vec::with_capacity(entries);
+    let mut body = bytes[17..].iter().copied();
+    while let Some(b) = body.next() {
+        if b != 0 { data.push(b); continue; }
+        let (mut run, mut shift) = (0usize, 0);
+        loop {
+            let byte = body.next().ok_or_else(|| invalid("truncated run"))?;
+            run |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 { break; }
+        }
+        data.resize(data.len() + run, 0);
+    }
+    if data.len() != entries { return Err(invalid("entry count does not match the data")); }
+    Ok(Table { material, pieces: material.pieces(), data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{idx, position};
+    use std::sync::OnceLock;
+
+    // generated once, it takes a few seconds in debug builds
+    fn cm_c() -> &'static Tablebases {
+        static TBS: OnceLock<Tablebases> = OnceLock::new();
+        TBS.get_or_init(|| {
+            let mut tbs = Tablebases::new();
+            tbs.generate(Material::parse("CM-C").unwrap());
+            tbs
+        })
+    }
+
+    #[test]
+    fn material_names() {
+        let m = Material::parse("cm-cmt").unwrap();
+        assert_eq!(m.to_string(), "CM-CMT");
+        assert_eq!(m.pieces().len(), 5);
+        assert_eq!(Material::of(&State::default()).to_string(), "CMLRT-CMLRT");
+        assert_eq!(Material::parse("CC-M"), None);
+        assert_eq!(Material::parse("CM"), None);
+    }
+
+    #[test]
+    fn probes_known_positions() {
+        let tbs = cm_c();
+        // A's Core next to its Monarch hops beside B's Core
+        let s = position('A', &[(idx(1, 5), 'A', PieceKind::Core), (idx(1, 4), 'A', PieceKind::Monarch), (idx(4, 5), 'B', PieceKind::Core)]);
+        assert_eq!(tbs.probe(&s), Some(TbValue::Win(1)));
+        // B's lone Core can't move, so it is stuck as soon as A misses the touch
+        let far = position('A', &[(idx(0, 0), 'A', PieceKind::Core), (idx(0, 1), 'A', PieceKind::Monarch), (idx(6, 10), 'B', PieceKind::Core)]);
+        assert_eq!(tbs.probe(&far), Some(TbValue::Draw));
+        let touching = position('B', &[(idx(3, 5), 'A', PieceKind::Core), (idx(3, 4), 'A', PieceKind::Monarch), (idx(4, 5), 'B', PieceKind::Core)]);
+        assert_eq!(tbs.probe(&touching), Some(TbValue::Loss(0)));
+        // no Core left for B
+        assert_eq!(tbs.probe(&position('A', &[(0, 'A', PieceKind::Core)])), Some(TbValue::Draw));
+        assert_eq!(tbs.probe(&State::default()), None);
+    }
+
+    // every win has a move into a loss one ply shorter, every loss only moves into wins
+    #[test]
+    fn entries_agree_with_their_children() {
+        let tbs = cm_c();
+        let table = &tbs.tables[&Material::parse("CM-C").unwrap()];
+        for idx in (0..table.data.len()).step_by(7) {
+            let s = table.state(&table.placement(idx));
+            let values: Vec<TbValue> = s.legal_moves().iter().map(|m| {
+                let next = s.apply_move(m);
+                if next.is_terminal().is_some() { TbValue::Loss(0) } else { tbs.probe(&next).unwrap() }
+            }).collect();
+            match decode(table.data[idx]) {
+                TbValue::Win(d) => assert!(values.contains(&TbValue::Loss(d - 1))),
+                TbValue::Loss(0) => assert!(s.is_terminal().is_some()),
+                TbValue::Loss(d) => assert!(values.iter().all(|v| matches!(v, TbValue::Win(w) if *w < d))),
+                TbValue::Draw => assert!(!values.iter().any(|v| matches!(v, TbValue::Loss(_)))),
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored_placements_share_an_entry() {
+        let tbs = cm_c();
+        let table = &tbs.tables[&Material::parse("CM-C").unwrap()];
+        for idx in (0..table.data.len()).step_by(5) {
+            let (squares, b_to_move) = table.placement(idx);
+            let k = table.pieces.len();
+            let mut distinct = squares[..k].to_vec();
+            distinct.sort_unstable();
+            distinct.dedup();
+            assert_eq!(distinct.len(), k);
+            assert_eq!(table.index(&squares[..k], b_to_move), idx);
+            let mirrored: Vec<u8> = squares[..k].iter().map(|&sq| mirror(sq)).collect();
+            assert_eq!(table.data[table.index(&mirrored, b_to_move)], table.data[idx]);
+        }
+        // without the reduction CM-CMT took 2 * 77^5 bytes, more than 5 GB
+        assert!(Table::entries(5) < 2_600_000_000);
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let tbs = cm_c();
+        let dir = std::env::temp_dir().join(format!("cbtb-test-{}", std::process::id()));
+        tbs.save(&dir).unwrap();
+        let loaded = Tablebases::load(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.tables.len(), tbs.tables.len());
+        for (m, t) in &tbs.tables { assert!(loaded.tables[m].data == t.data, "{}", m); }
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::net::state_from_features;
+use crate::playout::{eval_features, eval_logit, EvalWeights, EVAL_FEATURES};
+
+// A corpus position reduced to its evaluation features, with the final result
+// for the side to move and how often it was seen.
+pub struct Position {
+    pub features: [f64; EVAL_FEATURES],
+    pub outcome: f64,
+    pub weight: f64,
+}
+
+// Reads the CSV written by --export: the inputs column is decoded back into a
+// board with the side to move as A, so `outcome` is A's result.
+pub fn read_corpus(path: &Path) -> io::Result<Vec<Position>> {
+    let invalid = |line: usize, what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path.display(), line, what));
+    let text = fs::read_to_string(path)?;
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, "inputs,policy,search_value,outcome,count")) => {}
+        _ => return Err(invalid(1, "expected the header of an --export-format csv file")),
+    }
+    let mut positions = Vec::new();
+    for (i, line) in lines {
+        let cols: Vec<&str> = line.split(',').collect();
+        let [inputs, _, _, outcome, count] = cols[..] else { return Err(invalid(i + 1, "expected 5 columns")) };
+        let active: Vec<u16> = inputs.split_whitespace().map(str::parse).collect::<Result<_, _>>().map_err(|_| invalid(i + 1, "bad input index"))?;
+        let state = state_from_features(&active).ok_or_else(|| invalid(i + 1, "inputs are not a position"))?;
+        let outcome = outcome.parse().map_err(|_| invalid(i + 1, "bad outcome"))?;
+        let weight = count.parse().map_err(|_| invalid(i + 1, "bad count"))?;
+        positions.push(Position { features: eval_features(&state, true), outcome, weight });
+    }
+    Ok(positions)
+}
+
+fn predict(p: &Position, weights: &EvalWeights) -> f64 {
+    1.0 / (1.0 + (-eval_logit(&p.features, weights)).exp())
+}
+
+// weighted mean squared error between the evaluation and the outcomes
+pub fn error(positions: &[Position], weights: &EvalWeights) -> f64 {
+    let total: f64 = positions.iter().map(|p| p.weight).sum();
+    if total == 0.0 { return 0.0 }
+    positions.iter().map(|p| p.weight * (predict(p, weights) - p.outcome).powi(2)).sum::<f64>() / total
+}
+
+const ADAM_BETAS: (f64, f64) = (0.9, 0.999);
+
+// Full-batch Adam on `error`. Features are scaled to unit RMS while fitting,
+// mobility counts run much larger than the material differences, and a
+// feature that never varies in the corpus keeps its starting weight.
+pub fn tune(positions: &[Position], start: EvalWeights, steps: usize, lr: f64) -> EvalWeights {
+    let total: f64 = positions.iter().map(|p| p.weight).sum::<f64>().max(f64::MIN_POSITIVE);
+    let mut scale = [0.0; EVAL_FEATURES];
+    for p in positions {
+        for (s, x) in scale.iter_mut().zip(p.features) { *s += p.weight * x * x; }
+    }
+    let scale = scale.map(|s| (s / total).sqrt());
+    let mut weights = start;
+    let (mut m, mut v) = ([0.0; EVAL_FEATURES], [0.0; EVAL_FEATURES]);
+    for t in 1..=steps {
+        let mut grad = [0.0; EVAL_FEATURES];
+        for p in positions {
+            let y = predict(p, &weights);
+            let g = 2.0 * p.weight * (y - p.outcome) * y * (1.0 - y) / total;
+            for (gr, x) in grad.iter_mut().zip(p.features) { *gr += g * x; }
+        }
+        for j in 0..EVAL_FEATURES {
+            if scale[j] == 0.0 { continue }
+            // gradient and step in the scaled weight w * scale
+            let g = grad[j] / scale[j];
+            m[j] = ADAM_BETAS.0 * m[j] + (1.0 - ADAM_BETAS.0) * g;
+            v[j] = ADAM_BETAS.1 * v[j] + (1.0 - ADAM_BETAS.1) * g * g;
+            let m_hat = m[j] / (1.0 - ADAM_BETAS.0.powi(t as i32));
+            let v_hat = v[j] / (1.0 - ADAM_BETAS.1.powi(t as i32));
+            weights.0[j] -= lr * m_hat / (v_hat.sqrt() + 1e-12) / scale[j];
+        }
+    }
+    weights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playout::DEFAULT_WEIGHTS;
+
+    fn position(features: [f64; EVAL_FEATURES], outcome: f64) -> Position {
+        Position { features, outcome, weight: 1.0 }
+    }
+
+    #[test]
+    fn tuning_fits_the_outcomes() {
+        // a Tank up wins 80% of the time, the Core distance doesn't matter and nothing else varies
+        let mut positions = Vec::new();
+        for d in 1..6 {
+            let d = d as f64;
+            positions.push(position([0.0, 1.0, 0.0, 0.0, 0.0, d, 0.0], 0.8));
+            positions.push(position([0.0, -1.0, 0.0, 0.0, 0.0, -d, 0.0], 0.2));
+            positions.push(position([0.0, 0.0, 0.0, 0.0, 0.0, d, 0.0], 0.5));
+        }
+        let tuned = tune(&positions, DEFAULT_WEIGHTS, 2000, 0.05);
+        assert!(error(&positions, &tuned) < 1e-6);
+        assert!((tuned.0[1] - 4.0f64.ln()).abs() < 1e-2, "{:?}", tuned);
+        assert!(tuned.0[5].abs() < 1e-2);
+        assert_eq!(tuned.0[0], DEFAULT_WEIGHTS.0[0]);
+    }
+
+    #[test]
+    fn corpus_rows_become_positions() {
+        use crate::export::{Dataset, Format};
+        use crate::net::Sample;
+        use crate::state::State;
+        let mut data = Dataset::new();
+        let state = State::default();
+        let visits: Vec<_> = state.legal_moves().into_iter().map(|m| (m, 1)).collect();
+        data.add(&Sample::new(&state, &visits, 0.5, 1.0));
+        data.add(&Sample::new(&state, &visits, 0.5, 0.0));
+        let path = std::env::temp_dir().join(format!("cb-corpus-test-{}.csv", std::process::id()));
+        data.write(&path, Format::Csv).unwrap();
+        let positions = read_corpus(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!((positions[0].outcome, positions[0].weight), (0.5, 2.0));
+        assert_eq!(positions[0].features, eval_features(&state, true));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::state::{chebyshev_distance, opponent, Move, PieceKind, State};
+
+// Answer to "can the side to move force Core contact within n of its moves?"
+// under the analyzer's rules. This is the only touch search, rs-board plays
+// by other move rules and a search there wouldn't check the analyzer's games.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ForcedTouch {
+    // the quickest touch, with the defender's longest resistance in between
+    Win(Vec<Move>),
+    // every move of the attacker with a reply that holds out for the remaining
+    // moves, or None if the move can't touch in time whatever the reply (it
+    // ends the round, or it was the last move allowed)
+    Refuted(Vec<(Move, Option<Move>)>),
+}
+
+// squares a Core covers in one move
+const CORE_RANGE: usize = 2;
+
+// Depth-first win/loss search: the attacker needs one move that wins, the
+// defender one reply that doesn't lose, so most branches are cut early. A
+// side without moves draws, which counts as a failure for the attacker, and
+// a round that is already over gives an empty refutation.
+pub fn find_forced_touch(s: &State, moves: u8) -> ForcedTouch {
+    let search = Search { attacker: s.turn };
+    if s.is_terminal().is_some() { return ForcedTouch::Refuted(Vec::new()) }
+    for n in 1..=moves {
+        if search.attacker_wins(s, n) { return ForcedTouch::Win(search.winning_line(s, n)) }
+    }
+    ForcedTouch::Refuted(search.refutation(s, moves))
+}
+
+// every move that forces a touch within `moves`, more than one means a puzzle has several solutions
+pub fn winning_moves(s: &State, moves: u8) -> Vec<Move> {
+    let search = Search { attacker: s.turn };
+    if moves == 0 || s.is_terminal().is_some() || search.out_of_reach(s, moves, moves - 1) { return Vec::new() }
+    ordered_moves(s).into_iter().filter(|m| search.move_wins(&s.apply_move(m), moves)).collect()
+}
+
+// Core moves first, they are the only ones that touch
+fn ordered_moves(s: &State) -> Vec<Move> {
+    let mut moves = s.legal_moves();
+    moves.sort_by_key(|m| s.board[m.actor as usize].map(|p| p.kind) != Some(PieceKind::Core));
+    moves
+}
+
+struct Search {
+    attacker: char,
+}
+
+impl Search {
+    // Cores move at most two squares at a time and the attacker's Core has to
+    // make the last move, so Cores too far apart can't touch within n moves,
+    // even if the defender's Core comes towards them as well.
+    fn out_of_reach(&self, s: &State, n: u8, defender_moves: u8) -> bool {
+        match (s.find_piece(self.attacker, PieceKind::Core), s.find_piece(opponent(self.attacker), PieceKind::Core)) {
+            (Some(a), Some(b)) => chebyshev_distance(a, b) > 1 + CORE_RANGE * (n as usize + defender_moves as usize),
+            _ => true,
+        }
+    }
+
+    // the attacker to move and n of its moves left
+    fn attacker_wins(&self, s: &State, n: u8) -> bool {
+        if n == 0 || self.out_of_reach(s, n, n - 1) { return false }
+        ordered_moves(s).iter().any(|m| self.move_wins(&s.apply_move(m), n))
+    }
+
+    // `next` is the position after an attacker's move with n moves left, that one included
+    fn move_wins(&self, next: &State, n: u8) -> bool {
+        match next.is_terminal() {
+            Some(winner) => winner == self.attacker,
+            None => n > 1 && self.defender_loses(next, n - 1),
+        }
+    }
+
+    // the defender to move and n moves left for the attacker afterwards
+    fn defender_loses(&self, s: &State, n: u8) -> bool {
+        if self.out_of_reach(s, n, n) { return false }
+        let moves = ordered_moves(s);
+        // no move at all is a draw
+        if moves.is_empty() { return false }
+        for m in &moves {
+            let next = s.apply_move(m);
+            match next.is_terminal() {
+                Some(winner) if winner == self.attacker => continue,
+                Some(_) => return false,
+                None => {}
+            }
+            if !self.attacker_wins(&next, n) { return false }
+        }
+        true
+    }
+
+    // fewest attacker moves that win, up to n
+    fn moves_to_win(&self, s: &State, n: u8) -> Option<u8> {
+        (1..=n).find(|&k| self.attacker_wins(s, k))
+    }
+
+    // `s` is known to be won within n moves
+    fn winning_line(&self, s: &State, mut n: u8) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut s = s.clone();
+        loop {
+            // the defence may have given up more than it had to
+            n = self.moves_to_win(&s, n).expect("the position is won");
+            let (m, next) = ordered_moves(&s).into_iter().map(|m| { let next = s.apply_move(&m); (m, next) }).find(|(_, next)| self.move_wins(next, n)).expect("the position is won");
+            line.push(m);
+            if next.is_terminal().is_some() { return line }
+            let reply = self.longest_defence(&next, n - 1);
+            line.push(reply);
+            s = next.apply_move(&reply);
+            n -= 1;
+        }
+    }
+
+    // the defender's reply that puts off the touch the longest
+    fn longest_defence(&self, s: &State, n: u8) -> Move {
+        ordered_moves(s).into_iter()
+            .map(|m| { let depth = self.moves_to_win(&s.apply_move(&m), n); (m, depth) })
+            .max_by_key(|(_, depth)| depth.unwrap_or(u8::MAX))
+            .map(|(m, _)| m)
+            .expect("a lost position still has moves")
+    }
+
+    fn refutation(&self, s: &State, n: u8) -> Vec<(Move, Option<Move>)> {
+        let mut refutation = Vec::new();
+        if n == 0 { return refutation }
+        for m in ordered_moves(s) {
+            let next = s.apply_move(&m);
+            if next.is_terminal().is_some() || n == 1 { refutation.push((m, None)); continue }
+            let reply = ordered_moves(&next).into_iter().find(|reply| {
+                let after = next.apply_move(reply);
+                match after.is_terminal() {
+                    Some(winner) => winner != self.attacker,
+                    None => !self.attacker_wins(&after, n - 1),
+                }
+            });
+            refutation.push((m, reply));
+        }
+        refutation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{idx, position, Piece};
+
+    // A's Monarch has to come next to the Core before it can step up to B's
+    fn touch_in_two() -> State {
+        position('A', &[(idx(0, 10), 'A', PieceKind::Monarch), (idx(1, 10), 'B', PieceKind::Tank), (idx(2, 9), 'A', PieceKind::Core), (idx(5, 9), 'B', PieceKind::Core)])
+    }
+
+    fn play_line(s: &State, line: &[Move]) -> State {
+        line.iter().fold(s.clone(), |s, m| { assert!(s.legal_moves().contains(m)); s.apply_move(m) })
+    }
+
+    #[test]
+    fn finds_touch_in_two() {
+        let s = touch_in_two();
+        assert!(winning_moves(&s, 1).is_empty());
+        assert_eq!(winning_moves(&s, 2).len(), 1);
+        let ForcedTouch::Win(line) = find_forced_touch(&s, 3) else { panic!("A touches in two") };
+        assert_eq!(line.len(), 3);
+        assert_eq!((line[0].actor, line[0].dest), (idx(0, 10) as u8, Some(idx(1, 9) as u8)));
+        assert_eq!(play_line(&s, &line).is_terminal(), Some('A'));
+    }
+
+    #[test]
+    fn refutes_when_the_defender_can_answer() {
+        // with its Monarch beside it B's Core touches first
+        let mut s = touch_in_two();
+        s.board[idx(6, 9)] = Some(Piece { owner: 'B', kind: PieceKind::Monarch });
+        let ForcedTouch::Refuted(refutation) = find_forced_touch(&s, 2) else { panic!("B holds out") };
+        assert_eq!(refutation.len(), s.legal_moves().len());
+        let (m, reply) = refutation.iter().find(|(m, _)| m.dest == Some(idx(1, 9) as u8)).unwrap();
+        assert_eq!(play_line(&s, &[*m, reply.unwrap()]).is_terminal(), Some('B'));
+        assert_eq!(find_forced_touch(&s, 0), ForcedTouch::Refuted(Vec::new()));
+    }
+
+    #[test]
+    fn finished_rounds_have_no_touch() {
+        let mut s = position('B', &[(idx(3, 5), 'A', PieceKind::Core), (idx(3, 4), 'A', PieceKind::Monarch), (idx(4, 5), 'B', PieceKind::Core)]);
+        s.last_core_moved_by = Some('A');
+        assert_eq!(find_forced_touch(&s, 2), ForcedTouch::Refuted(Vec::new()));
+        assert!(winning_moves(&s, 2).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::mcts::{mcts_action, MctsConfig, Proof};
+use crate::net::{Network, Sample};
+use crate::state::{Move, State};
+
+// a searched position with its root visits and search value
+type Searched = (State, Vec<(Move, u32)>, f32);
+
+// Plays one game with `cfg` for both sides and turns every searched position
+// into a training sample. For the first `temperature_plies` moves the move is
+// drawn in proportion to the root visits, so games from the same net differ.
+pub fn self_play_game(cfg: &MctsConfig, max_turns: usize, temperature_plies: usize, rng: &mut impl Rng) -> (Vec<Sample>, Option<char>) {
+    let mut st = State::default();
+    let mut searched: Vec<Searched> = Vec::new();
+    let mut winner = None;
+    for turn in 0..max_turns {
+        if let Some(w) = st.is_terminal() { winner = Some(w); break }
+        let result = mcts_action(&st, cfg, rng);
+        let Some(best) = result.best else { break };
+        let mv = if turn < temperature_plies && result.proof == Proof::Unknown {
+            result.visits.choose_weighted(rng, |&(_, n)| n.max(1)).map(|&(m, _)| m).unwrap_or(best)
+        } else { best };
+        searched.push((st.clone(), result.visits, result.value as f32));
+        st = st.apply_move(&mv);
+    }
+    if winner.is_none() { winner = st.is_terminal(); }
+    let samples = searched.iter().map(|(s, visits, search_value)| {
+        let value = match winner { Some(w) if w == s.turn => 1.0, Some(_) => 0.0, None => (if s.turn == 'A' { cfg.draw_value[0] } else { cfg.draw_value[1] }) as f32 };
+        Sample::new(s, visits, *search_value, value)
+    }).collect();
+    (samples, winner)
+}
+
+// `epochs` shuffled passes of single-sample SGD, returns the mean policy and
+// value loss of the last pass
+pub fn train(net: &mut Network, samples: &[Sample], epochs: usize, lr: f32, rng: &mut impl Rng) -> (f32, f32) {
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    let mut losses = (0.0, 0.0);
+    for _ in 0..epochs {
+        order.shuffle(rng);
+        let (mut p, mut v) = (0.0, 0.0);
+        for &i in &order {
+            let (pl, vl) = net.train_step(&samples[i], lr);
+            p += pl;
+            v += vl;
+        }
+        let n = samples.len().max(1) as f32;
+        losses = (p / n, v / n);
+    }
+    losses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn self_play_samples_every_searched_position() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+        let net = Network::new(8, &mut rng);
+        let cfg = MctsConfig { iterations: 30, network: Some(std::sync::Arc::new(net.clone())), ..MctsConfig::default() };
+        let (samples, winner) = self_play_game(&cfg, 12, 4, &mut rng);
+        assert!(!samples.is_empty() && samples.len() <= 12);
+        for s in &samples {
+            assert_eq!(s.actions.len(), s.policy.len());
+            assert!((s.policy.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            if winner.is_none() { assert_eq!(s.value, 0.5); }
+        }
+        let mut trained = net.clone();
+        let before = train(&mut trained, &samples, 1, 0.0, &mut rng);
+        let after = train(&mut trained, &samples, 20, 0.02, &mut rng);
+        assert!(after.0 + after.1 < before.0 + before.1);
+    }
+}